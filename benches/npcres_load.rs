@@ -0,0 +1,56 @@
+use jx_remake::common::bundle::{NpcResView, decode_npcres, encode_npcres};
+use jx_remake::common::npcres::NpcResDb;
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Instant;
+
+// So thời gian nạp npcres: `cargo bench --bench npcres_load`.
+// Đọc data/newdata/npcres.json (hoặc file trong JX_NPCRES), tạo bundle trong bộ nhớ rồi đo
+// serde JSON, `decode_npcres` (dựng lại NpcResDb) và `NpcResView::parse` (mượn bundle).
+
+const ROUNDS: usize = 20;
+
+fn main() {
+    let path = env::var("JX_NPCRES").unwrap_or_else(|_| "data/newdata/npcres.json".to_string());
+    let json = fs::read(&path).unwrap_or_else(|e| {
+        eprintln!("Không đọc được {}: {}", path, e);
+        process::exit(1);
+    });
+    let db = NpcResDb::from_reader(json.as_slice()).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
+    });
+    let bundle = encode_npcres(&db).expect("không tạo được bundle");
+    println!(
+        "{}: {} nhân vật, JSON {} KB, bundle {} KB",
+        path,
+        db.chars.len(),
+        json.len() / 1024,
+        bundle.len() / 1024
+    );
+
+    let time = |name: &str, load: &dyn Fn() -> usize| {
+        let started = Instant::now();
+        let mut chars = 0;
+        for _ in 0..ROUNDS {
+            chars += load();
+        }
+        let elapsed = started.elapsed();
+        println!(
+            "{:<24} {:>5} nhân vật  {:>8.3} ms/lần",
+            name,
+            chars / ROUNDS,
+            elapsed.as_secs_f64() * 1000.0 / ROUNDS as f64
+        );
+    };
+    time("JSON (serde)", &|| {
+        NpcResDb::from_reader(json.as_slice()).unwrap().chars.len()
+    });
+    time("bundle -> NpcResDb", &|| {
+        decode_npcres(&bundle).unwrap().chars.len()
+    });
+    time("bundle -> NpcResView", &|| {
+        NpcResView::parse(&bundle).unwrap().len()
+    });
+}
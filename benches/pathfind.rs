@@ -0,0 +1,143 @@
+use jx_remake::common::map::{CellGrid, MapList, RegionData, RegionSide, WorldInfo};
+use jx_remake::common::pak::PakSet;
+use jx_remake::common::pathfind::{MapGrid, ObstacleMap, PathOptions, find_path};
+use jx_remake::common::world::{REGION_CELLS_X, REGION_CELLS_Y, WorldPos};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::env;
+use std::process;
+use std::time::Instant;
+
+// Benchmark tìm đường trên cả bản đồ: `cargo bench --bench pathfind`.
+// Có JX_PAK_DIR và JX_MAP_ID thì dùng bản đồ thật trong pak, không thì sinh bản đồ giả
+// 16 x 8 region với tường ngẫu nhiên (seed cố định để các lần chạy so sánh được).
+
+const QUERIES: usize = 500;
+// Số lần bốc thử một ô đi được trước khi bỏ cuộc (bản đồ gần như kín vật cản)
+const MAX_CELL_ATTEMPTS: usize = 100_000;
+
+fn main() {
+    let (grid, rect) = match (env::var("JX_PAK_DIR"), env::var("JX_MAP_ID")) {
+        (Ok(dir), Ok(id)) => load_map(&dir, id.parse().expect("JX_MAP_ID phải là số")),
+        _ => synthetic_map(16, 8),
+    };
+    let (left, top, right, bottom) = rect;
+    println!(
+        "Bản đồ {} region ({} x {} ô)",
+        grid.len(),
+        (right - left + 1) * REGION_CELLS_X,
+        (bottom - top + 1) * REGION_CELLS_Y
+    );
+
+    // Chọn cặp điểm ngẫu nhiên trên ô đi được
+    let mut rng = StdRng::seed_from_u64(36);
+    let mut random_cell = || {
+        (0..MAX_CELL_ATTEMPTS).find_map(|_| {
+            let cell = (
+                rng.gen_range(left * REGION_CELLS_X..(right + 1) * REGION_CELLS_X),
+                rng.gen_range(top * REGION_CELLS_Y..(bottom + 1) * REGION_CELLS_Y),
+            );
+            grid.is_walkable(cell)
+                .then(|| WorldPos::from_global_cell(0, cell))
+        })
+    };
+    let pairs: Vec<(WorldPos, WorldPos)> = (0..QUERIES)
+        .map(|_| random_cell().zip(random_cell()))
+        .collect::<Option<_>>()
+        .unwrap_or_else(|| {
+            eprintln!("Không tìm được ô đi được sau {} lần thử", MAX_CELL_ATTEMPTS);
+            process::exit(1);
+        });
+
+    for (name, options) in [
+        (
+            "A*",
+            PathOptions {
+                smooth: false,
+                ..PathOptions::default()
+            },
+        ),
+        ("A* + nắn đường", PathOptions::default()),
+        (
+            "A* giới hạn 2000 ô",
+            PathOptions {
+                max_nodes: 2000,
+                ..PathOptions::default()
+            },
+        ),
+    ] {
+        let started = Instant::now();
+        let (mut found, mut waypoints) = (0, 0);
+        for &(from, to) in &pairs {
+            if let Ok(path) = find_path(&grid, from, to, &options) {
+                found += 1;
+                waypoints += path.len();
+            }
+        }
+        let elapsed = started.elapsed();
+        println!(
+            "{:<20} {:>4}/{} tìm được  {:>6.1} điểm/đường  {:>8.3} ms/lần",
+            name,
+            found,
+            QUERIES,
+            waypoints as f64 / found.max(1) as f64,
+            elapsed.as_secs_f64() * 1000.0 / QUERIES as f64
+        );
+    }
+}
+
+fn synthetic_map(width: i32, height: i32) -> (MapGrid, (i32, i32, i32, i32)) {
+    let mut rng = StdRng::seed_from_u64(39);
+    let mut grid = MapGrid::new();
+    for ry in 0..height {
+        for rx in 0..width {
+            let mut cells = CellGrid::default();
+            // Vài bức tường ngang / dọc mỗi region
+            for _ in 0..4 {
+                let (x, y) = (
+                    rng.gen_range(0..REGION_CELLS_X),
+                    rng.gen_range(0..REGION_CELLS_Y),
+                );
+                let len = rng.gen_range(4..12);
+                let vertical = rng.gen_bool(0.5);
+                for i in 0..len {
+                    let (cx, cy) = if vertical { (x, y + i) } else { (x + i, y) };
+                    if cx < REGION_CELLS_X && cy < REGION_CELLS_Y {
+                        cells.cells[(cx * REGION_CELLS_Y + cy) as usize] = 1;
+                    }
+                }
+            }
+            grid.insert((rx, ry), cells);
+        }
+    }
+    (grid, (0, 0, width - 1, height - 1))
+}
+
+fn load_map(dir: &str, id: u32) -> (MapGrid, (i32, i32, i32, i32)) {
+    let mut paks = PakSet::open_dir(dir).expect("không mở được thư mục pak");
+    let list = MapList::load(&mut paks).expect("không đọc được maplist");
+    let map = list.get(id).expect("không có bản đồ trong maplist");
+    let world = WorldInfo::load(&mut paks, &map.path).expect("không đọc được file .wor");
+
+    let mut grid = MapGrid::new();
+    let (mut missing, mut failed) = (0, 0);
+    for region in world.regions() {
+        match RegionData::load(&mut paks, &map.path, region, RegionSide::Server) {
+            Ok(Some(data)) => grid.insert_region(region, &data),
+            Ok(None) => missing += 1,
+            Err(e) => {
+                eprintln!("Region {},{} lỗi: {}", region.0, region.1, e);
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "Bản đồ {}: {} ({}), {} region thiếu, {} region lỗi",
+        id, map.name, map.path, missing, failed
+    );
+    if grid.is_empty() {
+        eprintln!("Không đọc được region nào của {}", map.path);
+        process::exit(1);
+    }
+    (grid, world.region_rect)
+}
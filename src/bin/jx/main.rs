@@ -0,0 +1,318 @@
+use std::env;
+use std::fmt::Display;
+use std::io;
+use std::process::ExitCode;
+
+use jx_remake::common::text::Encoding;
+use serde_json::{Value, json};
+
+mod map;
+mod npcres;
+mod pak;
+mod spr;
+
+// Công cụ dòng lệnh gộp: `jx <nhóm> <lệnh> [tham số]`.
+// Mã thoát: 0 thành công, 1 thực thi thất bại (kể cả thất bại một phần), 2 sai cú pháp.
+
+const USAGE: &str = "\
+Cách dùng: jx [--json] [-q|--quiet] <nhóm> <lệnh> [tham số]
+
+  pak list <pak|thư mục>... [--names <filelist>]
+  pak extract <pak|thư mục>... (-l <filelist> | -f <đường dẫn game>)... [-o <thư mục>]
+  pak verify <pak|thư mục>...
+  pak pack <thư mục> -o <file.pak>
+
+  spr info <file.spr>...
+  spr export <file.spr> [-o <thư mục>]
+  spr pack <thư mục> -o <file.spr>
+
+  npcres build [-i <thư mục bảng> | --pak <pak|thư mục>...] [-o <file.json>]
+               [--report <file.json>] [--bundle <file.bin>] [--lenient] [--encoding <mã>]
+  npcres list-assets [-i <npcres.json>] [--status <bảng>] [--kind spr,shadow,sound,status]
+                     [--sound-root <thư mục>] [-o <file.txt | ->]
+  npcres extract <pak|thư mục>... [như list-assets] [-o <thư mục>]
+  npcres bundle [-i <npcres.json>] [-o <file.bin>]
+  npcres dump <file.bin> [-o <file.json | ->]
+  npcres render -a <action> [-i <npcres.json>] [-c MainMan|MainLady] [-d 0,1,..|all]
+                [-f png|gif|sheet] [-o <thư mục>] [--part Slot=Item]...
+                [--pak <pak|thư mục>... | --extracted <thư mục>]
+
+  map list (--pak <pak|thư mục>... | --extracted <thư mục>) [--encoding <mã>]
+  map render (--pak <pak|thư mục>... | --extracted <thư mục>) (-m <id> | --map-path <đường dẫn>)
+             [-r <x,y>] [--server] [-o <file.png>] [-s <pixel mỗi ô>] [--encoding <mã>]
+
+  --encoding: bảng mã văn bản của client (utf-8, gbk, big5, cp1258, tcvn3);
+              mặc định tự nhận diện UTF-8 / GBK, còn lại đọc như cp1258
+
+Tuỳ chọn chung:
+  --json        In kết quả dạng JSON ra stdout (thông báo tiến độ bị tắt)
+  -q, --quiet   Chỉ in kết quả và lỗi, không in tiến độ / cảnh báo";
+
+// --- KHUNG CLI ---
+
+/// Lỗi của một lệnh
+pub enum CliError {
+    // Sai tham số: mã thoát 2
+    Usage(String),
+    // Thực thi thất bại: mã thoát 1
+    Failed(String),
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Failed(e.to_string())
+    }
+}
+
+/// Kết quả một lệnh: dữ liệu cho `--json` và lệnh có hoàn thành trọn vẹn không
+pub struct Report {
+    pub data: Value,
+    pub ok: bool,
+}
+
+impl Report {
+    pub fn ok(data: Value) -> Self {
+        Report { data, ok: true }
+    }
+
+    /// Thành công nếu `ok`, không thì mã thoát 1 nhưng vẫn in dữ liệu
+    pub fn with_status(data: Value, ok: bool) -> Self {
+        Report { data, ok }
+    }
+}
+
+pub type CliResult = Result<Report, CliError>;
+
+/// Nơi in thông báo theo tuỳ chọn chung
+pub struct Output {
+    pub json: bool,
+    pub quiet: bool,
+}
+
+impl Output {
+    /// Kết quả chính (danh sách, thông tin file): in trừ khi dùng `--json`
+    pub fn print(&self, msg: impl Display) {
+        if !self.json {
+            println!("{}", msg);
+        }
+    }
+
+    /// Tiến độ: tắt khi `--quiet` hoặc `--json`
+    pub fn info(&self, msg: impl Display) {
+        if !self.quiet && !self.json {
+            println!("{}", msg);
+        }
+    }
+
+    /// Cảnh báo ra stderr, tắt khi `--quiet`
+    pub fn warn(&self, msg: impl Display) {
+        if !self.quiet {
+            eprintln!("cảnh báo: {}", msg);
+        }
+    }
+}
+
+/// Tham số còn lại của một lệnh: lấy dần tuỳ chọn ra, phần còn lại là tham số vị trí
+pub struct Args {
+    items: Vec<String>,
+}
+
+impl Args {
+    /// `true` nếu có một trong các cờ `names` (xoá mọi lần xuất hiện)
+    pub fn flag(&mut self, names: &[&str]) -> bool {
+        let before = self.items.len();
+        self.items.retain(|a| !names.contains(&a.as_str()));
+        self.items.len() != before
+    }
+
+    /// Giá trị của tuỳ chọn lặp lại được (`-f a -f b`)
+    pub fn values(&mut self, names: &[&str]) -> Result<Vec<String>, CliError> {
+        let mut values = Vec::new();
+        while let Some(i) = self.items.iter().position(|a| names.contains(&a.as_str())) {
+            if i + 1 >= self.items.len() {
+                return Err(CliError::Usage(format!("{} thiếu giá trị", self.items[i])));
+            }
+            values.push(self.items.remove(i + 1));
+            self.items.remove(i);
+        }
+        Ok(values)
+    }
+
+    /// Giá trị của tuỳ chọn (lần cuối nếu lặp lại)
+    pub fn value(&mut self, names: &[&str]) -> Result<Option<String>, CliError> {
+        Ok(self.values(names)?.pop())
+    }
+
+    /// Bảng mã văn bản của nguồn dữ liệu (`--encoding gbk`), `None` là tự nhận diện
+    pub fn encoding(&mut self) -> Result<Option<Encoding>, CliError> {
+        self.value(&["--encoding"])?
+            .map(|name| name.parse().map_err(CliError::Usage))
+            .transpose()
+    }
+
+    /// Tham số vị trí; báo lỗi nếu còn tuỳ chọn không nhận ra
+    pub fn positionals(self) -> Result<Vec<String>, CliError> {
+        match self
+            .items
+            .iter()
+            .find(|a| a.starts_with('-') && a.len() > 1)
+        {
+            Some(unknown) => Err(CliError::Usage(format!("Tuỳ chọn lạ: {}", unknown))),
+            None => Ok(self.items),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = Args {
+        items: env::args().skip(1).collect(),
+    };
+    let out = Output {
+        json: args.flag(&["--json"]),
+        quiet: args.flag(&["-q", "--quiet"]),
+    };
+    if args.flag(&["-h", "--help"]) || args.items.first().is_some_and(|a| a == "help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let (code, data) = execute(args, &out);
+    if out.json {
+        println!("{}", pretty(&data));
+    }
+    ExitCode::from(code)
+}
+
+/// Chạy `<nhóm> <lệnh> [tham số]`: mã thoát và dữ liệu in ra khi dùng `--json`
+fn execute(mut args: Args, out: &Output) -> (u8, Value) {
+    let group = take_word(&mut args);
+    let command = take_word(&mut args);
+    let result = match (group.as_deref(), command.as_deref()) {
+        (Some("pak"), Some(cmd)) => pak::run(cmd, args, out),
+        (Some("spr"), Some(cmd)) => spr::run(cmd, args, out),
+        (Some("npcres"), Some(cmd)) => npcres::run(cmd, args, out),
+        (Some("map"), Some(cmd)) => map::run(cmd, args, out),
+        _ => Err(CliError::Usage("Thiếu nhóm lệnh hoặc lệnh".to_string())),
+    };
+
+    match result {
+        Ok(report) => (if report.ok { 0 } else { 1 }, report.data),
+        Err(err) => {
+            let (code, msg) = match err {
+                CliError::Usage(msg) => (2, msg),
+                CliError::Failed(msg) => (1, msg),
+            };
+            eprintln!("lỗi: {}", msg);
+            if code == 2 && !out.json {
+                eprintln!("\n{}", USAGE);
+            }
+            (code, json!({ "error": msg }))
+        }
+    }
+}
+
+/// Lấy từ đầu tiên nếu không phải tuỳ chọn (tên nhóm / tên lệnh)
+fn take_word(args: &mut Args) -> Option<String> {
+    (!args.items.is_empty() && !args.items[0].starts_with('-')).then(|| args.items.remove(0))
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Lỗi "lệnh không có trong nhóm" dùng chung cho các module lệnh
+pub fn unknown_command(group: &str, cmd: &str) -> CliError {
+    CliError::Usage(format!("Nhóm '{}' không có lệnh '{}'", group, cmd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Args {
+        Args {
+            items: line.split_whitespace().map(str::to_string).collect(),
+        }
+    }
+
+    const QUIET_JSON: Output = Output {
+        json: true,
+        quiet: true,
+    };
+
+    #[test]
+    fn args_take_options_then_positionals() {
+        let mut a = args("a.pak -f x -q --list l1 -f y b.pak -q");
+        assert!(a.flag(&["-q", "--quiet"]));
+        assert!(!a.flag(&["--json"]));
+        assert_eq!(
+            a.values(&["-f"]).ok(),
+            Some(vec!["x".to_string(), "y".to_string()])
+        );
+        assert_eq!(
+            a.value(&["-l", "--list"]).ok(),
+            Some(Some("l1".to_string()))
+        );
+        assert_eq!(
+            a.positionals().ok(),
+            Some(vec!["a.pak".to_string(), "b.pak".to_string()])
+        );
+
+        assert!(matches!(args("-o").value(&["-o"]), Err(CliError::Usage(_))));
+        assert!(matches!(
+            args("a --bogus").positionals(),
+            Err(CliError::Usage(_))
+        ));
+        // "-" một mình là tham số (stdout), không phải tuỳ chọn
+        assert_eq!(args("-").positionals().ok(), Some(vec!["-".to_string()]));
+
+        let mut a = args("--encoding cp1258");
+        assert_eq!(a.encoding().ok(), Some(Some(Encoding::Windows1258)));
+        assert!(matches!(
+            args("--encoding latin9").encoding(),
+            Err(CliError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn exit_codes_and_json_reports() {
+        // Sai cú pháp: mã 2, JSON có trường error
+        let (code, data) = execute(args("pak"), &QUIET_JSON);
+        assert_eq!(code, 2);
+        assert!(data["error"].is_string());
+        assert_eq!(execute(args("pak nope"), &QUIET_JSON).0, 2);
+        assert_eq!(execute(args("map list"), &QUIET_JSON).0, 2);
+
+        // Thực thi thất bại: mã 1
+        let dir = std::env::temp_dir().join(format!("jx_cli_test_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("in/settings")).unwrap();
+        let missing = dir.join("missing.bin");
+        let (code, data) = execute(
+            args(&format!("npcres dump {}", missing.display())),
+            &QUIET_JSON,
+        );
+        assert_eq!(code, 1);
+        assert!(data["error"].as_str().unwrap().contains("missing.bin"));
+
+        // Thành công: mã 0, JSON là dữ liệu của lệnh
+        std::fs::write(dir.join("in/settings/a.txt"), "x").unwrap();
+        let pak = dir.join("a.pak");
+        let line = format!("pak pack {} -o {}", dir.join("in").display(), pak.display());
+        assert_eq!(execute(args(&line), &QUIET_JSON).0, 0);
+        let (code, data) = execute(args(&format!("pak list {}", pak.display())), &QUIET_JSON);
+        assert_eq!(code, 0);
+        assert_eq!(data[0]["count"], 1);
+
+        // Thiếu file cần giải nén: vẫn có báo cáo nhưng mã 1
+        let line = format!(
+            "pak extract {} -f \\settings\\a.txt -f \\nope -o {}",
+            pak.display(),
+            dir.join("out").display()
+        );
+        let (code, data) = execute(args(&line), &QUIET_JSON);
+        assert_eq!(code, 1);
+        assert_eq!(data["extracted"], 1);
+        assert_eq!(data["missing"][0], "\\nope");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
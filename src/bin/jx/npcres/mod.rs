@@ -0,0 +1,868 @@
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use jx_remake::client::audio::DEFAULT_SOUND_ROOT;
+use jx_remake::client::canvas::{RenderedAnimation, render_animation, sprite_sheet};
+use jx_remake::client::compose::{
+    CharacterComposer, DirSpriteSource, EquippedParts, PakSpriteSource, SpriteSource,
+};
+use jx_remake::common::bundle::{BundleHeader, decode_npcres, encode_npcres};
+use jx_remake::common::ini::IniFile;
+use jx_remake::common::npc::{ActionId, PartSlot};
+use jx_remake::common::npcres::{
+    ActionSprInfo, AssetKind, AssetRef, NPCRES_SCHEMA_VERSION, NpcResDb, merge_assets,
+    parse_npc_res_table, parse_npc_sound_table, parse_part_list, parse_shadow_table,
+    parse_special_sound_table, parse_status_table, status_assets,
+};
+use jx_remake::common::pak::PakSet;
+use jx_remake::common::tabfile::TabFile;
+use jx_remake::common::text::{Encoding, decode_with};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use crate::pak::{extract_files, open_paks};
+use crate::{Args, CliError, CliResult, Output, Report, unknown_command};
+
+const DEFAULT_INPUT_DIR: &str = "data/settings/npcres";
+const DEFAULT_OUTPUT_FILE: &str = "data/newdata/npcres.json";
+const DEFAULT_ASSETS_INPUT: &str = "data/newdata/npcres.json";
+const DEFAULT_ASSETS_OUTPUT: &str = "data/unpak_list/npcres.txt";
+const DEFAULT_EXTRACT_DIR: &str = "extracted";
+const DEFAULT_BUNDLE_FILE: &str = "data/newdata/npcres.bin";
+const DEFAULT_RENDER_DIR: &str = "compose_output";
+const DEFAULT_PAK_DIR: &str = "data/pak";
+
+// Thư mục bảng trong pak của client
+const PAK_TABLE_DIR: &str = "\\settings\\npcres\\";
+
+const CHARACTER_TABLE: &str = "人物类型.txt";
+const SHADOW_TABLE: &str = "主角动作阴影对应表.txt";
+const SPECIAL_SOUND_TABLE: &str = "主角动作声音表.txt";
+const NPC_SOUND_TABLE: &str = "npc动作声音表.txt";
+const NPC_RES_TABLE: &str = "普通npc资源.txt";
+const NPC_RES_INFO_TABLE: &str = "普通npc资源信息.txt";
+const STATUS_TABLE: &str = "状态图形对照表.txt";
+
+pub fn run(cmd: &str, args: Args, out: &Output) -> CliResult {
+    match cmd {
+        "build" => build(args, out),
+        "list-assets" => list_assets(args, out),
+        "extract" => extract(args, out),
+        "bundle" => bundle(args, out),
+        "dump" => dump(args, out),
+        "render" => render(args, out),
+        _ => Err(unknown_command("npcres", cmd)),
+    }
+}
+
+// --- jx npcres build ---
+// Chuyển các bảng settings/npcres của client thành file JSON của `NpcResDb`.
+// Cùng nguồn thì cùng kết quả từng byte: key sắp xếp (serde_json::Map là BTreeMap),
+// không ghi thời gian hay đường dẫn nguồn vào file.
+
+fn build(mut args: Args, out: &Output) -> CliResult {
+    let input = args.value(&["-i", "--input"])?;
+    let paks = args.values(&["--pak"])?;
+    let output = args
+        .value(&["-o", "--output"])?
+        .unwrap_or_else(|| DEFAULT_OUTPUT_FILE.to_string());
+    let report_file = args.value(&["--report"])?;
+    let bundle_file = args.value(&["--bundle"])?;
+    let lenient = args.flag(&["--lenient"]);
+    let encoding = args.encoding()?;
+    args.positionals()?;
+
+    let source = match (input, paks.is_empty()) {
+        (Some(_), false) => {
+            return Err(CliError::Usage(
+                "Chỉ chọn một nguồn: -i <thư mục> hoặc --pak <pak>".to_string(),
+            ));
+        }
+        (None, false) => {
+            let mut set = PakSet::new();
+            for reader in open_paks(&paks, out)? {
+                set.add(reader);
+            }
+            Source::Pak(set)
+        }
+        (input, true) => Source::Dir(PathBuf::from(
+            input.unwrap_or_else(|| DEFAULT_INPUT_DIR.to_string()),
+        )),
+    };
+
+    out.info(format!("Đọc bảng từ {}", source.describe()));
+    let mut builder = Builder {
+        source,
+        encoding,
+        out,
+        errors: Vec::new(),
+        missing: Vec::new(),
+    };
+    let characters = builder.generate()?;
+
+    // Có lỗi thì không ghi đè file cũ, trừ khi --lenient
+    let written = builder.errors.is_empty() || lenient;
+    if written {
+        let root = json!({
+            "schema_version": NPCRES_SCHEMA_VERSION,
+            "characters": characters,
+        });
+        let text = serde_json::to_string_pretty(&root).unwrap_or_default();
+        if let Some(bundle_file) = &bundle_file {
+            let db = NpcResDb::from_reader(text.as_bytes())?;
+            write_file(bundle_file, encode_npcres(&db)?)?;
+            out.info(format!("Đã ghi bundle {}", bundle_file));
+        }
+        write_file(&output, text)?;
+    }
+
+    let mut kinds = Map::new();
+    for entry in characters.values() {
+        let kind = entry["type"].as_str().unwrap_or_default();
+        let count = kinds.get(kind).and_then(Value::as_u64).unwrap_or(0);
+        kinds.insert(kind.to_string(), json!(count + 1));
+    }
+    let summary = json!({
+        "source": builder.source.describe(),
+        "output": written.then_some(&output),
+        "bundle": bundle_file.filter(|_| written),
+        "schema_version": NPCRES_SCHEMA_VERSION,
+        "characters": characters.len(),
+        "types": kinds,
+        "errors": builder.errors,
+        "missing": builder.missing,
+    });
+    if let Some(report_file) = &report_file {
+        write_file(
+            report_file,
+            serde_json::to_string_pretty(&summary).unwrap_or_default(),
+        )?;
+    }
+
+    if written {
+        out.print(format!(
+            "Đã ghi {} nhân vật vào {} ({} lỗi, {} nhân vật thiếu tài nguyên)",
+            characters.len(),
+            output,
+            builder.errors.len(),
+            builder.missing.len()
+        ));
+    } else {
+        out.print(format!(
+            "{} lỗi, không ghi {} (dùng --lenient để vẫn ghi)",
+            builder.errors.len(),
+            output
+        ));
+    }
+    Ok(Report::with_status(
+        summary,
+        builder.errors.is_empty() || lenient,
+    ))
+}
+
+fn write_file(path: &str, content: impl AsRef<[u8]>) -> Result<(), CliError> {
+    let path = Path::new(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content).map_err(|e| CliError::Failed(format!("{}: {}", path.display(), e)))
+}
+
+/// Nơi đọc bảng: thư mục đã giải nén hoặc thẳng từ pak của client
+enum Source {
+    Dir(PathBuf),
+    Pak(PakSet),
+}
+
+impl Source {
+    fn read(&mut self, filename: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Source::Dir(dir) => fs::read(dir.join(filename)),
+            Source::Pak(paks) => {
+                let path = format!("{}{}", PAK_TABLE_DIR, filename);
+                paks.read(&path)?.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Không có trong pak: {}", path),
+                    )
+                })
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Source::Dir(dir) => dir.display().to_string(),
+            Source::Pak(paks) => {
+                let names: Vec<&str> = paks.readers.iter().map(|r| r.file_path.as_str()).collect();
+                format!("{} ({})", PAK_TABLE_DIR, names.join(", "))
+            }
+        }
+    }
+}
+
+/// Một bảng đọc lỗi hoặc một dòng sai trong 人物类型.txt
+#[derive(Serialize)]
+struct Problem {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    character: Option<String>,
+    file: String,
+    message: String,
+}
+
+struct Builder<'a> {
+    source: Source,
+    // Bảng mã của bảng client, `None` là tự nhận diện
+    encoding: Option<Encoding>,
+    out: &'a Output,
+    errors: Vec<Problem>,
+    // NPC thường không có dòng nào trong 普通npc资源.txt (không có hình)
+    missing: Vec<String>,
+}
+
+impl Builder<'_> {
+    fn error(&mut self, character: Option<&str>, file: &str, message: impl Display) {
+        let message = message.to_string();
+        match character {
+            Some(name) => self.out.warn(format!("{} ({}): {}", name, file, message)),
+            None => self.out.warn(format!("{}: {}", file, message)),
+        }
+        self.errors.push(Problem {
+            character: character.map(str::to_string),
+            file: file.to_string(),
+            message,
+        });
+    }
+
+    /// Bảng do cột `column` của nhân vật `character` trỏ tới bị lỗi
+    fn broken(&mut self, character: &str, column: &str, file: &str, e: io::Error) {
+        self.error(Some(character), file, format!("cột {}: {}", column, e));
+    }
+
+    /// Đọc bảng, dòng có dấu nháy không đóng được ghi nhận là lỗi dữ liệu
+    fn table(&mut self, filename: &str) -> io::Result<TabFile> {
+        let table = TabFile::from_text(&decode_with(&self.source.read(filename)?, self.encoding));
+        for &row in table.malformed() {
+            // +2: dòng header và đánh số từ 1 như trình soạn thảo
+            self.error(
+                None,
+                filename,
+                format!("dòng {}: dấu nháy không đóng", row + 2),
+            );
+        }
+        Ok(table)
+    }
+
+    /// Bảng dùng chung cho mọi nhân vật: lỗi thì ghi nhận và dùng giá trị rỗng
+    fn shared<T: Default>(&mut self, filename: &str, parse: impl Fn(&TabFile) -> T) -> T {
+        match self.table(filename) {
+            Ok(table) => parse(&table),
+            Err(e) => {
+                self.error(None, filename, e);
+                T::default()
+            }
+        }
+    }
+
+    fn generate(&mut self) -> Result<Map<String, Value>, CliError> {
+        let char_table = self
+            .table(CHARACTER_TABLE)
+            .map_err(|e| CliError::Failed(format!("{}: {}", CHARACTER_TABLE, e)))?;
+        let mut json_root = Map::new();
+
+        // Bảng bóng / âm thanh / NPC thường không được tham chiếu từ 人物类型.txt,
+        // đọc riêng theo tên cố định
+        let shadows = self.shared(SHADOW_TABLE, parse_shadow_table);
+        let special_sounds = self.shared(SPECIAL_SOUND_TABLE, parse_special_sound_table);
+        let npc_sounds = self.shared(NPC_SOUND_TABLE, parse_npc_sound_table);
+        let npc_res = match (self.table(NPC_RES_TABLE), self.table(NPC_RES_INFO_TABLE)) {
+            (Ok(spr_table), Ok(info_table)) => parse_npc_res_table(&spr_table, &info_table),
+            (Err(e), _) => {
+                self.error(None, NPC_RES_TABLE, e);
+                Default::default()
+            }
+            (_, Err(e)) => {
+                self.error(None, NPC_RES_INFO_TABLE, e);
+                Default::default()
+            }
+        };
+
+        for row in char_table.rows() {
+            let char_name = row.get("CharacterName").to_string();
+            if char_name.is_empty() {
+                continue;
+            }
+            if json_root.contains_key(&char_name) {
+                self.error(Some(&char_name), CHARACTER_TABLE, "trùng tên, bỏ dòng sau");
+                continue;
+            }
+
+            // [SỬA ĐỔI 1]: Chuẩn hóa root path thành Slash (/)
+            let raw_root = row.get("ResFilePath").replace("\\", "/");
+
+            let char_type = row.get("CharacterType");
+            if char_type != "SpecialNpc" && char_type != "NormalNpc" {
+                let msg = format!("CharacterType lạ '{}'", char_type);
+                self.error(Some(&char_name), CHARACTER_TABLE, msg);
+                continue;
+            }
+
+            let mut char_data = Map::new();
+            char_data.insert("type".to_string(), json!(char_type));
+
+            // Đảm bảo root path bắt đầu bằng / nếu chưa có
+            let clean_root = if raw_root.starts_with('/') {
+                raw_root
+            } else {
+                format!("/{}", raw_root)
+            };
+            char_data.insert("root_path".to_string(), json!(&clean_root));
+
+            if char_type == "SpecialNpc" {
+                // --- XỬ LÝ SPECIAL NPC ---
+
+                // A. Weapon Logic
+                let mut weapon_logic = Map::new();
+
+                let filename = row.get("WeaponActionTab1");
+                if !filename.is_empty() {
+                    match self.matrix(filename, |id| id.to_string()) {
+                        Ok((data, _)) => {
+                            weapon_logic.insert("unmounted".to_string(), Value::Object(data));
+                        }
+                        Err(e) => self.broken(&char_name, "WeaponActionTab1", filename, e),
+                    }
+                }
+
+                let filename = row.get("WeaponActionTab2");
+                if !filename.is_empty() {
+                    match self.matrix(filename, |id| id.to_string()) {
+                        Ok((data, _)) => {
+                            weapon_logic.insert("mounted".to_string(), Value::Object(data));
+                        }
+                        Err(e) => self.broken(&char_name, "WeaponActionTab2", filename, e),
+                    }
+                }
+
+                if !weapon_logic.is_empty() {
+                    char_data.insert("weapon_logic".to_string(), Value::Object(weapon_logic));
+                }
+
+                // B. Render Order
+                let filename = row.get("ActionRenderOrderTab");
+                if !filename.is_empty() {
+                    match self.render_order(filename) {
+                        Ok(data) => {
+                            char_data.insert("render_order".to_string(), Value::Object(data));
+                        }
+                        Err(e) => self.broken(&char_name, "ActionRenderOrderTab", filename, e),
+                    }
+                }
+
+                // C. Components
+                let component_cols = vec![
+                    "Head",
+                    "Hair",
+                    "Shoulder",
+                    "Body",
+                    "LeftHand",
+                    "RightHead",
+                    "LeftWeapon",
+                    "RightWeapon",
+                    "HorseFront",
+                    "HorseMiddle",
+                    "HorseBack",
+                ];
+
+                let mut components_data = Map::new();
+                let mut part_rows = Map::new();
+
+                for col in component_cols {
+                    let filename = row.get(col);
+                    if !filename.is_empty() {
+                        // Ô là tên spr tương đối so với root của nhân vật
+                        match self.matrix(filename, |spr| resolve_game_path(&clean_root, spr)) {
+                            Ok((data, rows)) => {
+                                components_data.insert(col.to_lowercase(), Value::Object(data));
+                                part_rows.insert(col.to_lowercase(), json!(rows));
+                            }
+                            Err(e) => self.broken(&char_name, col, filename, e),
+                        }
+                    }
+                }
+
+                if !components_data.is_empty() {
+                    char_data.insert("components".to_string(), Value::Object(components_data));
+                    char_data.insert("part_rows".to_string(), Value::Object(part_rows));
+                }
+
+                // D. Part List (loại trang bị)
+                let filename = row.get("PartFileName");
+                if !filename.is_empty() {
+                    match self.table(filename) {
+                        Ok(table) => {
+                            char_data
+                                .insert("part_list".to_string(), json!(parse_part_list(&table)));
+                        }
+                        Err(e) => self.broken(&char_name, "PartFileName", filename, e),
+                    }
+                }
+
+                // E. Shadows
+                if let Some(actions) = shadows.get(&char_name) {
+                    let mut shadows_data = Map::new();
+                    for (action, info) in actions {
+                        let resolved = ActionSprInfo {
+                            spr: resolve_game_path(&clean_root, &info.spr),
+                            ..info.clone()
+                        };
+                        shadows_data.insert(action.name().to_string(), json!(resolved));
+                    }
+                    char_data.insert("shadows".to_string(), Value::Object(shadows_data));
+                }
+
+                // F. Sounds
+                if let Some(actions) = special_sounds.get(&char_name) {
+                    let sounds_data: Map<String, Value> = actions
+                        .iter()
+                        .map(|(action, wav)| (action.name().to_string(), json!(wav)))
+                        .collect();
+                    char_data.insert("sounds".to_string(), Value::Object(sounds_data));
+                }
+            } else {
+                // --- XỬ LÝ NORMAL NPC ---
+                if let Some(actions) = npc_res.get(&char_name) {
+                    let mut actions_data = Map::new();
+                    for (action, info) in actions {
+                        let resolved = ActionSprInfo {
+                            spr: resolve_game_path(&clean_root, &info.spr),
+                            ..info.clone()
+                        };
+                        actions_data.insert(action.name().to_string(), json!(resolved));
+                    }
+                    char_data.insert("actions".to_string(), Value::Object(actions_data));
+                } else {
+                    self.missing.push(char_name.clone());
+                }
+
+                if let Some(actions) = npc_sounds.get(&char_name) {
+                    let sounds_data: Map<String, Value> = actions
+                        .iter()
+                        .map(|(action, wav)| (action.name().to_string(), json!(wav)))
+                        .collect();
+                    char_data.insert("sounds".to_string(), Value::Object(sounds_data));
+                }
+            }
+
+            json_root.insert(char_name, Value::Object(char_data));
+        }
+
+        Ok(json_root)
+    }
+
+    /// Bảng ma trận (bảng vũ khí, bảng bộ phận): cột đầu là tên dòng (vũ khí / item),
+    /// header là tên cột (lệnh / action). Kết quả: tên dòng -> tên cột -> `value(ô)`, bỏ ô trống;
+    /// kèm tên các dòng theo thứ tự trong bảng (index = res id mà item dùng).
+    fn matrix(
+        &mut self,
+        filename: &str,
+        value: impl Fn(&str) -> String,
+    ) -> io::Result<(Map<String, Value>, Vec<String>)> {
+        let table = self.table(filename)?;
+        let headers = table.headers().get(1..).unwrap_or_default();
+
+        let mut matrix = Map::new();
+        let mut rows = Vec::with_capacity(table.len());
+        for row in table.rows() {
+            rows.push(row.cell(0).to_string());
+            let mut cells = Map::new();
+            for (i, header) in headers.iter().enumerate() {
+                let cell = row.cell(i + 1);
+                if !cell.is_empty() {
+                    cells.insert(header.clone(), json!(value(cell)));
+                }
+            }
+            matrix.insert(row.cell(0).to_string(), Value::Object(cells));
+        }
+        Ok((matrix, rows))
+    }
+
+    fn render_order(&mut self, filename: &str) -> io::Result<Map<String, Value>> {
+        let ini = IniFile::from_text(&decode_with(&self.source.read(filename)?, self.encoding));
+        // Section lặp lại gộp vào cùng một tên
+        let mut sections = Map::new();
+        for section in ini.sections() {
+            let keys = sections
+                .entry(section.name().to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(keys) = keys {
+                keys.extend(
+                    section
+                        .entries()
+                        .map(|(key, value)| (key.to_string(), json!(value))),
+                );
+            }
+        }
+        Ok(sections)
+    }
+}
+
+/// Ghép `file` vào `root`, đổi '\\' thành '/' và xử lý "." / "..": ("/a/b", "..\\c.spr") -> "/a/c.spr"
+fn resolve_game_path(root: &str, file: &str) -> String {
+    let combined = format!("{}/{}", root, file).replace('\\', "/");
+    let mut stack: Vec<&str> = Vec::new();
+    for part in combined.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                stack.pop();
+            }
+            _ => stack.push(part),
+        }
+    }
+    format!("/{}", stack.join("/"))
+}
+
+// --- jx npcres bundle / dump ---
+// Bundle nhị phân (common::bundle) để engine khởi động nhanh; dump đọc ngược lại ra JSON
+// giống hệt npcres.json để so sánh khi gỡ lỗi.
+
+fn bundle(mut args: Args, out: &Output) -> CliResult {
+    let input = args
+        .value(&["-i", "--input"])?
+        .unwrap_or_else(|| DEFAULT_ASSETS_INPUT.to_string());
+    let output = args
+        .value(&["-o", "--output"])?
+        .unwrap_or_else(|| DEFAULT_BUNDLE_FILE.to_string());
+    args.positionals()?;
+
+    let db = NpcResDb::load(&input).map_err(|e| CliError::Failed(format!("{}: {}", input, e)))?;
+    let bytes = encode_npcres(&db)?;
+    write_file(&output, &bytes)?;
+    let header = BundleHeader::parse(&bytes)?;
+
+    out.print(format!(
+        "Đã ghi {} nhân vật vào {} ({} byte, {} chuỗi, crc32 {:08X})",
+        db.chars.len(),
+        output,
+        bytes.len(),
+        header.string_count,
+        header.checksum
+    ));
+    Ok(Report::ok(json!({
+        "output": output,
+        "characters": db.chars.len(),
+        "bytes": bytes.len(),
+        "strings": header.string_count,
+        "checksum": format!("{:08X}", header.checksum),
+    })))
+}
+
+fn dump(mut args: Args, out: &Output) -> CliResult {
+    let output = args.value(&["-o", "--output"])?;
+    let [input] = &args.positionals()?[..] else {
+        return Err(CliError::Usage(
+            "npcres dump cần đúng một file bundle".to_string(),
+        ));
+    };
+
+    let data = fs::read(input).map_err(|e| CliError::Failed(format!("{}: {}", input, e)))?;
+    let header =
+        BundleHeader::parse(&data).map_err(|e| CliError::Failed(format!("{}: {}", input, e)))?;
+    let db = decode_npcres(&data).map_err(|e| CliError::Failed(format!("{}: {}", input, e)))?;
+    let text = serde_json::to_string_pretty(&db.to_json()).unwrap_or_default();
+
+    match &output {
+        Some(file) if file != "-" => {
+            write_file(file, text)?;
+            out.print(format!(
+                "Bundle v{}: {} nhân vật, {} chuỗi -> {}",
+                header.version,
+                db.chars.len(),
+                header.string_count,
+                file
+            ));
+        }
+        // --json đã in kết quả ra stdout nên không in thêm JSON dump
+        _ => out.print(text),
+    }
+    Ok(Report::ok(json!({
+        "input": input,
+        "output": output,
+        "version": header.version,
+        "characters": db.chars.len(),
+        "strings": header.string_count,
+        "checksum": format!("{:08X}", header.checksum),
+    })))
+}
+
+// --- jx npcres list-assets / extract ---
+// Đi qua model npcres.json (và bảng hiệu ứng trạng thái) để lấy mọi file được tham chiếu:
+// spr bộ phận / action, bóng, wav, spr trạng thái. Đường dẫn ra đúng dạng hash của pak.
+
+/// Tham số chung: -i <npcres.json>, --status <bảng trạng thái>, --kind <loại>...
+fn collect_assets(args: &mut Args, out: &Output) -> Result<Vec<AssetRef>, CliError> {
+    let input = args
+        .value(&["-i", "--input"])?
+        .unwrap_or_else(|| DEFAULT_ASSETS_INPUT.to_string());
+    let status = args.value(&["--status"])?;
+    let sound_root = args
+        .value(&["--sound-root"])?
+        .unwrap_or_else(|| DEFAULT_SOUND_ROOT.to_string());
+    let mut kinds = Vec::new();
+    for name in args.values(&["--kind"])? {
+        for name in name.split(',') {
+            let kind = AssetKind::from_name(name.trim())
+                .ok_or_else(|| CliError::Usage(format!("Loại tài nguyên lạ: {}", name)))?;
+            kinds.push(kind);
+        }
+    }
+
+    let db = NpcResDb::load(&input).map_err(|e| CliError::Failed(format!("{}: {}", input, e)))?;
+    let mut refs = db.assets(&sound_root);
+
+    // Bảng trạng thái: mặc định lấy trong thư mục bảng nếu có, chỉ định rõ thì bắt buộc
+    let status_file = match status {
+        Some(file) => Some(file),
+        None => {
+            let file = Path::new(DEFAULT_INPUT_DIR).join(STATUS_TABLE);
+            file.exists().then(|| file.to_string_lossy().into_owned())
+        }
+    };
+    match &status_file {
+        Some(file) => {
+            let table =
+                TabFile::load(file).map_err(|e| CliError::Failed(format!("{}: {}", file, e)))?;
+            refs.extend(status_assets(&parse_status_table(&table)));
+        }
+        None => out.info(format!("Không có {}, bỏ qua spr trạng thái", STATUS_TABLE)),
+    }
+
+    let mut refs = merge_assets(refs);
+    if !kinds.is_empty() {
+        refs.retain(|r| kinds.contains(&r.kind));
+    }
+    Ok(refs)
+}
+
+fn count_by_kind(refs: &[AssetRef]) -> Map<String, Value> {
+    AssetKind::ALL
+        .iter()
+        .map(|kind| {
+            let count = refs.iter().filter(|r| r.kind == *kind).count();
+            (kind.name().to_string(), json!(count))
+        })
+        .collect()
+}
+
+fn list_assets(mut args: Args, out: &Output) -> CliResult {
+    let output = args
+        .value(&["-o", "--output"])?
+        .unwrap_or_else(|| DEFAULT_ASSETS_OUTPUT.to_string());
+    let refs = collect_assets(&mut args, out)?;
+    args.positionals()?;
+
+    if output == "-" {
+        for r in &refs {
+            out.print(&r.path);
+        }
+    } else {
+        let mut content = String::new();
+        for r in &refs {
+            content.push_str(&r.path);
+            content.push('\n');
+        }
+        write_file(&output, content)?;
+        out.print(format!("Đã ghi {} đường dẫn vào {}", refs.len(), output));
+    }
+    Ok(Report::ok(json!({
+        "output": output,
+        "count": refs.len(),
+        "kinds": count_by_kind(&refs),
+        "assets": refs,
+    })))
+}
+
+fn extract(mut args: Args, out: &Output) -> CliResult {
+    let output = PathBuf::from(
+        args.value(&["-o", "--output"])?
+            .unwrap_or_else(|| DEFAULT_EXTRACT_DIR.to_string()),
+    );
+    let refs = collect_assets(&mut args, out)?;
+    let pak_paths = args.positionals()?;
+
+    let mut paks = PakSet::new();
+    for reader in open_paks(&pak_paths, out)? {
+        paks.add(reader);
+    }
+    let files: Vec<String> = refs.into_iter().map(|r| r.path).collect();
+    Ok(extract_files(&mut paks, &files, &output, out))
+}
+
+// --- jx npcres render ---
+// Render nhân vật ghép (không cần GPU) ra PNG / GIF / sprite sheet. Mặc định mặc item đầu
+// tiên của các bộ phận cơ thể, --part Slot=Item để thay.
+
+fn render(mut args: Args, out: &Output) -> CliResult {
+    let input = args
+        .value(&["-i", "--input"])?
+        .unwrap_or_else(|| DEFAULT_OUTPUT_FILE.to_string());
+    let char_name = args
+        .value(&["-c", "--char"])?
+        .unwrap_or_else(|| "MainMan".to_string());
+    let action_name = args
+        .value(&["-a", "--action"])?
+        .ok_or_else(|| CliError::Usage("Cần -a <action>".to_string()))?;
+    let dirs_arg = args
+        .value(&["-d", "--dir"])?
+        .unwrap_or_else(|| "0".to_string());
+    let format = args
+        .value(&["-f", "--format"])?
+        .unwrap_or_else(|| "png".to_string());
+    let output = PathBuf::from(
+        args.value(&["-o", "--output"])?
+            .unwrap_or_else(|| DEFAULT_RENDER_DIR.to_string()),
+    );
+    let paks = args.values(&["--pak"])?;
+    let extracted = args.value(&["--extracted"])?;
+    let part_args = args.values(&["--part"])?;
+    args.positionals()?;
+
+    let action = ActionId::from_name(&action_name)
+        .ok_or_else(|| CliError::Usage(format!("Action lạ: {}", action_name)))?;
+    if !["png", "gif", "sheet"].contains(&format.as_str()) {
+        return Err(CliError::Usage(format!(
+            "Định dạng phải là png, gif hoặc sheet: {}",
+            format
+        )));
+    }
+    let dirs: Vec<u32> = if dirs_arg == "all" {
+        (0..8).collect()
+    } else {
+        dirs_arg
+            .split(',')
+            .map(|s| s.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| CliError::Usage(format!("Hướng không hợp lệ: {}", dirs_arg)))?
+    };
+
+    // --- 1. Dữ liệu ---
+    let db = NpcResDb::load(&input).map_err(|e| CliError::Failed(format!("{}: {}", input, e)))?;
+    let res = db
+        .get(&char_name)
+        .filter(|r| r.is_special())
+        .ok_or_else(|| {
+            CliError::Failed(format!(
+                "{} không phải nhân vật chính (SpecialNpc)",
+                char_name
+            ))
+        })?;
+
+    let mut sprites: Box<dyn SpriteSource> = match (extracted, paks.is_empty()) {
+        (Some(_), false) => {
+            return Err(CliError::Usage(
+                "Chỉ chọn một nguồn: --pak <pak> hoặc --extracted <thư mục>".to_string(),
+            ));
+        }
+        (Some(dir), true) => Box::new(DirSpriteSource::new(dir)),
+        (None, no_pak) => {
+            let paths = if no_pak {
+                vec![DEFAULT_PAK_DIR.to_string()]
+            } else {
+                paks
+            };
+            let mut set = PakSet::new();
+            for reader in open_paks(&paths, out)? {
+                set.add(reader);
+            }
+            Box::new(PakSpriteSource::new(set))
+        }
+    };
+
+    // --- 2. Đồ đang mặc ---
+    let mut parts = EquippedParts::new();
+    for slot in [
+        PartSlot::Head,
+        PartSlot::Hair,
+        PartSlot::Shoulder,
+        PartSlot::Body,
+        PartSlot::LeftHand,
+        PartSlot::RightHand,
+    ] {
+        if let Some(item) = res.part_items(slot).first() {
+            parts.insert(slot, item.to_string());
+        }
+    }
+    for part in &part_args {
+        let (slot_name, item) = part
+            .split_once('=')
+            .ok_or_else(|| CliError::Usage(format!("--part phải có dạng Slot=Item: {}", part)))?;
+        let slot = PartSlot::from_column_name(slot_name)
+            .ok_or_else(|| CliError::Usage(format!("Bộ phận không hợp lệ: {}", slot_name)))?;
+        parts.insert(slot, item.to_string());
+    }
+
+    // --- 3. Render ---
+    let composer = CharacterComposer::new(res.clone());
+    let animations: Vec<(u32, RenderedAnimation)> = dirs
+        .iter()
+        .map(|&dir| {
+            (
+                dir,
+                render_animation(&composer, &parts, action, dir, sprites.as_mut()),
+            )
+        })
+        .collect();
+
+    // Không vẽ được gì (thiếu spr, bộ phận không có action này...) thì không ghi ảnh trống
+    let empty: Vec<String> = animations
+        .iter()
+        .filter(|(_, anim)| anim.is_empty())
+        .map(|(dir, _)| dir.to_string())
+        .collect();
+    if !empty.is_empty() {
+        return Err(CliError::Failed(format!(
+            "Không vẽ được {} ở hướng {}: thiếu spr hoặc bộ phận không có action này",
+            action.name(),
+            empty.join(",")
+        )));
+    }
+
+    let frames: usize = animations.iter().map(|(_, a)| a.frames.len()).sum();
+    let prefix = format!("{}_{}", char_name, action.name());
+    let result = match format.as_str() {
+        "gif" => animations.iter().try_for_each(|(dir, anim)| {
+            anim.save_gif(output.join(format!("{}_d{}.gif", prefix, dir)))
+        }),
+        "sheet" => {
+            let rows: Vec<RenderedAnimation> = animations.into_iter().map(|(_, a)| a).collect();
+            sprite_sheet(&rows)
+                .map_err(image::ImageError::IoError)
+                .and_then(|sheet| {
+                    fs::create_dir_all(&output)?;
+                    sheet.save(output.join(format!("{}_sheet.png", prefix)))
+                })
+        }
+        _ => animations.iter().try_for_each(|(dir, anim)| {
+            anim.save_png_frames(&output, &format!("{}_d{}", prefix, dir))
+        }),
+    };
+    result.map_err(|e| CliError::Failed(format!("{}: {}", output.display(), e)))?;
+
+    out.print(format!(
+        "Đã render {} ({} frame) vào {}",
+        prefix,
+        frames,
+        output.display()
+    ));
+    Ok(Report::ok(json!({
+        "character": char_name,
+        "action": action.name(),
+        "dirs": dirs,
+        "format": format,
+        "frames": frames,
+        "output": output,
+    })))
+}
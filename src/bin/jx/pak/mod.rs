@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use jx_remake::common::pak::{PakReader, PakSet, jx_file_name_hash, write_pak};
+use jx_remake::common::text::decode_auto;
+use serde_json::{Value, json};
+
+use crate::{Args, CliError, CliResult, Output, Report, unknown_command};
+
+const DEFAULT_EXTRACT_DIR: &str = "extracted";
+
+pub fn run(cmd: &str, args: Args, out: &Output) -> CliResult {
+    match cmd {
+        "list" => list(args, out),
+        "extract" => extract(args, out),
+        "verify" => verify(args, out),
+        "pack" => pack(args, out),
+        _ => Err(unknown_command("pak", cmd)),
+    }
+}
+
+// --- jx pak list ---
+
+fn list(mut args: Args, out: &Output) -> CliResult {
+    let names_file = args.value(&["--names"])?;
+    let paks = open_paks(&args.positionals()?, out)?;
+
+    // Index pak chỉ có hash: tra ngược tên từ file list nếu có
+    let names: HashMap<u32, String> = match names_file {
+        Some(path) => read_file_list(&path)?
+            .into_iter()
+            .map(|name| (jx_file_name_hash(&name), name))
+            .collect(),
+        None => HashMap::new(),
+    };
+
+    let mut result = Vec::new();
+    for reader in &paks {
+        let entries = reader.entries();
+        out.print(format!("{} ({} file)", reader.file_path, entries.len()));
+        let mut rows = Vec::with_capacity(entries.len());
+        for e in &entries {
+            let name = names.get(&e.id);
+            out.print(format!(
+                "  {:08X} {:>10} {:>10} {:>10} {} {}",
+                e.id,
+                e.offset,
+                e.original_size,
+                e.get_stored_size(),
+                compression_name(e.get_compression_type()),
+                name.map(|n| n.as_str()).unwrap_or("")
+            ));
+            rows.push(json!({
+                "id": format!("{:08X}", e.id),
+                "offset": e.offset,
+                "size": e.original_size,
+                "stored": e.get_stored_size(),
+                "compression": compression_name(e.get_compression_type()),
+                "path": name,
+            }));
+        }
+        result.push(json!({ "pak": reader.file_path, "count": entries.len(), "entries": rows }));
+    }
+    Ok(Report::ok(Value::Array(result)))
+}
+
+fn compression_name(kind: u8) -> &'static str {
+    match kind {
+        0 => "none",
+        1 => "ucl",
+        2 => "bzip2",
+        _ => "unknown",
+    }
+}
+
+// --- jx pak extract ---
+
+fn extract(mut args: Args, out: &Output) -> CliResult {
+    let lists = args.values(&["-l", "--list"])?;
+    let mut files = args.values(&["-f", "--file"])?;
+    let output = PathBuf::from(
+        args.value(&["-o", "--output"])?
+            .unwrap_or_else(|| DEFAULT_EXTRACT_DIR.to_string()),
+    );
+    let pak_paths = args.positionals()?;
+    for list in &lists {
+        files.extend(read_file_list(list)?);
+    }
+    if files.is_empty() {
+        return Err(CliError::Usage(
+            "Cần ít nhất một -l <filelist> hoặc -f <đường dẫn>".to_string(),
+        ));
+    }
+
+    let mut paks = PakSet::new();
+    for reader in open_paks(&pak_paths, out)? {
+        paks.add(reader);
+    }
+    Ok(extract_files(&mut paks, &files, &output, out))
+}
+
+/// Giải nén `files` (đường dẫn game) từ `paks` vào `output`. Thiếu hoặc lỗi file nào thì
+/// lệnh kết thúc với mã 1 nhưng vẫn giải nén các file còn lại.
+pub fn extract_files(paks: &mut PakSet, files: &[String], output: &Path, out: &Output) -> Report {
+    out.info(format!("{} file cần giải nén", files.len()));
+
+    let (mut extracted, mut missing, mut failed) = (0, Vec::new(), Vec::new());
+    for path in files {
+        match paks.read(path) {
+            Ok(Some(data)) => match save_extracted(output, path, &data) {
+                Ok(()) => {
+                    out.info(format!("  {}", path));
+                    extracted += 1;
+                }
+                Err(e) => {
+                    out.warn(format!("không ghi được {}: {}", path, e));
+                    failed.push(json!({ "path": path, "error": e.to_string() }));
+                }
+            },
+            Ok(None) => {
+                out.warn(format!(
+                    "không có trong pak: {} (hash {:08X})",
+                    path,
+                    jx_file_name_hash(path)
+                ));
+                missing.push(path.clone());
+            }
+            Err(e) => {
+                out.warn(format!("không đọc được {}: {}", path, e));
+                failed.push(json!({ "path": path, "error": e.to_string() }));
+            }
+        }
+    }
+
+    out.print(format!(
+        "Đã giải nén {} / {} file vào {} ({} thiếu, {} lỗi)",
+        extracted,
+        files.len(),
+        output.display(),
+        missing.len(),
+        failed.len()
+    ));
+    let ok = missing.is_empty() && failed.is_empty();
+    Report::with_status(
+        json!({
+            "output": output,
+            "extracted": extracted,
+            "missing": missing,
+            "failed": failed,
+        }),
+        ok,
+    )
+}
+
+/// `\spr\a\b.spr` -> `<output>/spr/a/b.spr`
+fn save_extracted(output: &Path, game_path: &str, data: &[u8]) -> std::io::Result<()> {
+    let clean = game_path.replace('\\', "/");
+    let path = output.join(clean.trim_start_matches('/'));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)
+}
+
+// --- jx pak verify ---
+
+fn verify(args: Args, out: &Output) -> CliResult {
+    let mut paks = open_paks(&args.positionals()?, out)?;
+    let mut result = Vec::new();
+    let mut all_ok = true;
+
+    for reader in &mut paks {
+        let entries = reader.entries();
+        let mut errors = Vec::new();
+        for e in &entries {
+            let problem = match e.get_compression_type() {
+                0 | 1 => match reader.read_entry_data(e) {
+                    Ok(data) if data.len() == e.original_size as usize => None,
+                    Ok(data) => Some(format!(
+                        "kích thước {} khác {} trong index",
+                        data.len(),
+                        e.original_size
+                    )),
+                    Err(err) => Some(err.to_string()),
+                },
+                kind => Some(format!("kiểu nén {} chưa hỗ trợ", compression_name(kind))),
+            };
+            if let Some(problem) = problem {
+                out.warn(format!("{}: {:08X} {}", reader.file_path, e.id, problem));
+                errors.push(json!({ "id": format!("{:08X}", e.id), "error": problem }));
+            }
+        }
+        out.print(format!(
+            "{}: {} file, {} lỗi",
+            reader.file_path,
+            entries.len(),
+            errors.len()
+        ));
+        all_ok &= errors.is_empty();
+        result.push(json!({ "pak": reader.file_path, "count": entries.len(), "errors": errors }));
+    }
+    Ok(Report::with_status(Value::Array(result), all_ok))
+}
+
+// --- jx pak pack ---
+
+fn pack(mut args: Args, out: &Output) -> CliResult {
+    let output = args
+        .value(&["-o", "--output"])?
+        .ok_or_else(|| CliError::Usage("pak pack cần -o <file.pak>".to_string()))?;
+    let [dir] = &args.positionals()?[..] else {
+        return Err(CliError::Usage(
+            "pak pack cần đúng một thư mục nguồn".to_string(),
+        ));
+    };
+
+    // Đường dẫn game là đường dẫn tương đối so với thư mục nguồn: <dir>/spr/a.spr -> \spr\a.spr
+    let mut paths = Vec::new();
+    collect_files(Path::new(dir), &mut paths)?;
+    paths.sort();
+    let mut files = Vec::with_capacity(paths.len());
+    let mut total = 0;
+    for path in paths {
+        let rel = path.strip_prefix(dir).unwrap_or(&path);
+        let game_path = format!("\\{}", rel.to_string_lossy().replace('/', "\\"));
+        let data = fs::read(&path)?;
+        total += data.len();
+        out.info(format!("  {}", game_path));
+        files.push((game_path, data));
+    }
+
+    let count = write_pak(&output, &files)?;
+    out.print(format!(
+        "Đã đóng gói {} file ({} byte) vào {}",
+        count, total, output
+    ));
+    Ok(Report::ok(
+        json!({ "output": output, "files": count, "bytes": total }),
+    ))
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+// --- DÙNG CHUNG ---
+
+/// Mở các file pak; thư mục thì lấy mọi *.pak bên trong (theo tên)
+pub fn open_paks(paths: &[String], out: &Output) -> Result<Vec<PakReader>, CliError> {
+    if paths.is_empty() {
+        return Err(CliError::Usage(
+            "Cần ít nhất một file pak hoặc thư mục".to_string(),
+        ));
+    }
+    let mut readers = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            let set = PakSet::open_dir(path)
+                .map_err(|e| CliError::Failed(format!("{}: {}", path.display(), e)))?;
+            readers.extend(set.readers);
+        } else {
+            let reader = PakReader::new(path)
+                .map_err(|e| CliError::Failed(format!("{}: {}", path.display(), e)))?;
+            readers.push(reader);
+        }
+    }
+    if readers.is_empty() {
+        return Err(CliError::Failed("Không tìm thấy file pak nào".to_string()));
+    }
+    for reader in &readers {
+        out.info(format!(
+            "Đã mở {} ({} file)",
+            reader.file_path, reader.header.count
+        ));
+    }
+    Ok(readers)
+}
+
+/// File list (một đường dẫn game mỗi dòng), có thể là GBK khi lấy từ client
+pub fn read_file_list(path: &str) -> Result<Vec<String>, CliError> {
+    let data = fs::read(path).map_err(|e| CliError::Failed(format!("{}: {}", path, e)))?;
+    Ok(decode_auto(&data)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
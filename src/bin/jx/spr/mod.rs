@@ -0,0 +1,203 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+use jx_remake::client::spr::SprFile;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{Args, CliError, CliResult, Output, Report, unknown_command};
+
+const DEFAULT_EXPORT_DIR: &str = "spr_output";
+const META_FILE: &str = "meta.json";
+
+pub fn run(cmd: &str, args: Args, out: &Output) -> CliResult {
+    match cmd {
+        "info" => info(args, out),
+        "export" => export(args, out),
+        "pack" => pack(args, out),
+        _ => Err(unknown_command("spr", cmd)),
+    }
+}
+
+/// `meta.json` cạnh các frame png: đủ để `spr pack` dựng lại file spr
+#[derive(Serialize, Deserialize)]
+struct SprMeta {
+    width: u16,
+    height: u16,
+    center_x: u16,
+    center_y: u16,
+    directions: u16,
+    interval: u16,
+    frames: Vec<FrameMeta>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FrameMeta {
+    id: usize,
+    w: u16,
+    h: u16,
+    off_x: i16,
+    off_y: i16,
+}
+
+fn load_spr(path: &str) -> Result<SprFile, CliError> {
+    SprFile::load(path).map_err(|e| CliError::Failed(format!("{}: {}", path, e)))
+}
+
+/// Tên file png của frame thứ `index`: d<hướng>_f<frame>.png
+fn frame_file_name(index: usize, per_dir: usize) -> String {
+    let per_dir = per_dir.max(1);
+    format!("d{}_f{:03}.png", index / per_dir, index % per_dir)
+}
+
+// --- jx spr info ---
+
+fn info(args: Args, out: &Output) -> CliResult {
+    let files = args.positionals()?;
+    if files.is_empty() {
+        return Err(CliError::Usage("spr info cần ít nhất một file".to_string()));
+    }
+
+    let mut result = Vec::new();
+    for file in &files {
+        let spr = load_spr(file)?;
+        let h = &spr.header;
+        out.print(format!(
+            "{}: {}x{} tâm ({}, {}), {} frame / {} hướng ({} frame mỗi hướng), interval {}, {} màu",
+            file,
+            h.width,
+            h.height,
+            h.center_x,
+            h.center_y,
+            spr.frames.len(),
+            h.directions,
+            spr.frames_per_direction(),
+            h.interval,
+            spr.palette.len()
+        ));
+        result.push(json!({
+            "file": file,
+            "width": h.width,
+            "height": h.height,
+            "center": [h.center_x, h.center_y],
+            "frames": spr.frames.len(),
+            "directions": h.directions,
+            "frames_per_direction": spr.frames_per_direction(),
+            "interval": h.interval,
+            "colors": spr.palette.len(),
+        }));
+    }
+    Ok(Report::ok(Value::Array(result)))
+}
+
+// --- jx spr export ---
+
+fn export(mut args: Args, out: &Output) -> CliResult {
+    let output = args.value(&["-o", "--output"])?;
+    let [file] = &args.positionals()?[..] else {
+        return Err(CliError::Usage("spr export cần đúng một file".to_string()));
+    };
+    let spr = load_spr(file)?;
+    let dir = match output {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let stem = Path::new(file).file_stem().unwrap_or_default();
+            Path::new(DEFAULT_EXPORT_DIR).join(stem)
+        }
+    };
+    fs::create_dir_all(&dir)?;
+
+    let per_dir = spr.frames_per_direction();
+    let mut frames = Vec::with_capacity(spr.frames.len());
+    for (i, frame) in spr.frames.iter().enumerate() {
+        let rgba = spr.frame_rgba(i).unwrap_or_default();
+        let img = RgbaImage::from_raw(frame.width as u32, frame.height as u32, rgba)
+            .ok_or_else(|| CliError::Failed(format!("Frame {} hỏng", i)))?;
+        let path = dir.join(frame_file_name(i, per_dir));
+        img.save(&path)
+            .map_err(|e| CliError::Failed(format!("{}: {}", path.display(), e)))?;
+        frames.push(FrameMeta {
+            id: i,
+            w: frame.width,
+            h: frame.height,
+            off_x: frame.offset_x,
+            off_y: frame.offset_y,
+        });
+    }
+
+    let h = &spr.header;
+    let meta = SprMeta {
+        width: h.width,
+        height: h.height,
+        center_x: h.center_x,
+        center_y: h.center_y,
+        directions: h.directions,
+        interval: h.interval,
+        frames,
+    };
+    let meta_json = serde_json::to_string_pretty(&meta).unwrap_or_default();
+    fs::write(dir.join(META_FILE), meta_json)?;
+
+    out.print(format!(
+        "Đã xuất {} frame vào {}",
+        spr.frames.len(),
+        dir.display()
+    ));
+    Ok(Report::ok(
+        json!({ "output": dir, "frames": spr.frames.len() }),
+    ))
+}
+
+// --- jx spr pack ---
+
+fn pack(mut args: Args, out: &Output) -> CliResult {
+    let output = args
+        .value(&["-o", "--output"])?
+        .ok_or_else(|| CliError::Usage("spr pack cần -o <file.spr>".to_string()))?;
+    let [dir] = &args.positionals()?[..] else {
+        return Err(CliError::Usage(
+            "spr pack cần đúng một thư mục (kết quả của spr export)".to_string(),
+        ));
+    };
+    let dir = Path::new(dir);
+
+    let meta_path = dir.join(META_FILE);
+    let meta: SprMeta = fs::read(&meta_path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| serde_json::from_slice(&data).map_err(|e| e.to_string()))
+        .map_err(|e| CliError::Failed(format!("{}: {}", meta_path.display(), e)))?;
+
+    let per_dir = meta.frames.len() / (meta.directions as usize).max(1);
+    let mut images = Vec::with_capacity(meta.frames.len());
+    for (i, frame) in meta.frames.iter().enumerate() {
+        let path = dir.join(frame_file_name(i, per_dir));
+        let img = image::open(&path)
+            .map_err(|e| CliError::Failed(format!("{}: {}", path.display(), e)))?;
+        images.push((img.to_rgba8(), frame.off_x, frame.off_y));
+    }
+
+    let mut spr = SprFile::from_images(
+        &images,
+        (meta.center_x, meta.center_y),
+        meta.directions,
+        meta.interval,
+    )
+    .map_err(|e| CliError::Failed(e.to_string()))?;
+    // Giữ kích thước khung gốc (có thể lớn hơn frame lớn nhất)
+    spr.header.width = spr.header.width.max(meta.width);
+    spr.header.height = spr.header.height.max(meta.height);
+    fs::write(&output, spr.to_bytes())?;
+
+    out.print(format!(
+        "Đã đóng gói {} frame, {} màu vào {}",
+        spr.frames.len(),
+        spr.palette.len(),
+        output
+    ));
+    Ok(Report::ok(json!({
+        "output": output,
+        "frames": spr.frames.len(),
+        "colors": spr.palette.len(),
+    })))
+}
@@ -1,674 +1,904 @@
-// --- 1. IMPORT & FIX LỖI XUNG ĐỘT ---
-use ::rand as rand_crate;
-use rand_crate::seq::SliceRandom;
-
-use byteorder::{LittleEndian, ReadBytesExt};
-use encoding_rs::GBK;
+use jx_remake::client::assets::{
+    AssetLoader, AssetManager, AssetStats, DirLoader, FrameState, PakLoader,
+};
+use jx_remake::client::audio::{ActionSoundPlayer, AudioBackend, SoundEvent};
+use jx_remake::client::camera::Camera;
+use jx_remake::client::canvas::Bounds;
+use jx_remake::client::compose::{
+    CharacterComposer, DrawCommand, Layer, SpriteSource, compose_npc,
+};
+use jx_remake::client::overlay::{AttachAnchors, StatusOverlays};
+use jx_remake::common::npc::{
+    ActionId, ActionSet, ActionTiming, AnimEvent, Equipment, Npc, NpcAction, NpcCommand, NpcKind,
+    PartSlot, Sex, UNARMED_WEAPON,
+};
+use jx_remake::common::npcres::{NpcResDb, NpcResEntry, parse_status_table};
+use jx_remake::common::pak::PakSet;
+use jx_remake::common::sim::Simulation;
+use jx_remake::common::tabfile::TabFile;
+use jx_remake::common::world::{WorldPos, dir64_from_sprite, sprite_dir};
 use macroquad::prelude::*;
-use serde::Deserialize;
 use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
-use std::path::Path;
-
-// --- MODULE PAK READER ---
-pub const PACK_SIGNATURE: u32 = 0x4b434150;
-
-#[derive(Debug, Clone, Copy)]
-pub struct PakHeader {
-    pub signature: u32,
-    pub count: u32,
-    pub index_offset: u32,
-    pub data_offset: u32,
-    pub _crc32: u32,
-    pub _reserved: [u8; 12],
-}
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+
+// Ngân sách bộ nhớ cho SPR đã parse + frame RGBA
+const ASSET_BUDGET: usize = 256 * 1024 * 1024;
+
+// Bảng hiệu ứng trạng thái mặc định (cùng thư mục với `jx npcres build`)
+const DEFAULT_STATUS_TABLE: &str = "data/settings/npcres/状态图形对照表.txt";
 
-#[derive(Debug, Clone, Copy)]
-pub struct PakEntry {
-    pub id: u32,
-    pub offset: u32,
-    pub original_size: u32,
-    pub compress_flag: u32,
+// --- HELPER MACROQUAD ---
+
+/// Cache texture theo (đường dẫn spr, frame tuyệt đối), frame RGBA lấy từ AssetManager.
+/// Frame đang giải mã thì bỏ qua, frame sau hỏi lại.
+#[derive(Default)]
+struct TextureCache {
+    textures: HashMap<(String, usize), Texture2D>,
 }
 
-impl PakEntry {
-    pub fn get_stored_size(&self) -> u32 {
-        self.compress_flag & 0x00FFFFFF
+impl TextureCache {
+    fn get(&mut self, path: &str, frame: usize, assets: &mut AssetManager) -> Option<&Texture2D> {
+        let key = (path.to_string(), frame);
+        if !self.textures.contains_key(&key) {
+            let FrameState::Ready(decoded) = assets.frame(path, frame) else {
+                return None;
+            };
+            let image = Image {
+                bytes: decoded.rgba.clone(),
+                width: decoded.width as u16,
+                height: decoded.height as u16,
+            };
+            let texture = Texture2D::from_image(&image);
+            texture.set_filter(FilterMode::Nearest);
+            self.textures.insert(key.clone(), texture);
+        }
+        self.textures.get(&key)
     }
-    pub fn get_compression_type(&self) -> u8 {
-        ((self.compress_flag >> 24) & 0xFF) as u8
+
+    // Đổi nhân vật thì bỏ texture cũ, frame RGBA vẫn còn trong AssetManager
+    fn clear(&mut self) {
+        self.textures.clear();
     }
 }
 
-pub fn read_u32_le(buf: &[u8]) -> u32 {
-    u32::from_le_bytes(buf.try_into().unwrap())
+/// Chưa có backend âm thanh: in file wav sẽ phát ra console
+struct LogAudio;
+
+impl AudioBackend for LogAudio {
+    fn play(&mut self, event: &SoundEvent) {
+        println!(
+            "🔊 NPC {} | {:?} | {}",
+            event.npc_id, event.action, event.path
+        );
+    }
 }
 
-// --- SỬA LỖI 1: Hàm Hash không tự ý cắt bỏ ký tự đầu nữa ---
-pub fn jx_file_name_hash(path: &str) -> u32 {
-    // Chỉ chuẩn hóa: Chuyển hết / thành \ (Backslash)
-    let normalized_path = path.replace('/', "\\");
-
-    // Encode GBK (Cho hỗ trợ tiếng Trung/Việt nếu có)
-    let (cow, _, _) = GBK.encode(&normalized_path);
-    let gbk_bytes: &[u8] = &cow;
-
-    let mut id: u32 = 0;
-    let mut index: i32 = 0;
-    for &byte in gbk_bytes {
-        index += 1;
-        let mut char_code = byte as i8 as i32;
-        if byte >= b'A' && byte <= b'Z' {
-            char_code = (byte + (b'a' - b'A')) as i8 as i32;
-        }
-        let term1 = index.wrapping_mul(char_code) as u32;
-        let sum = id.wrapping_add(term1);
-        let modded = sum % 0x8000000b;
-        id = modded.wrapping_mul(0xffffffef);
-    }
-    id ^ 0x12345678
+// --- TRẠNG THÁI VIEWER ---
+
+// Các dòng chọn trong panel bên trái
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Character,
+    Action,
+    Direction,
+    Status,
+    Mounted,
+    Category(usize),
 }
 
-pub struct PakReader {
-    pub file_path: String,
-    file: File,
-    pub header: PakHeader,
-    index_map: HashMap<u32, PakEntry>,
+const ROW_HEIGHT: f32 = 22.0;
+const PANEL_TOP: f32 = 30.0;
+const PANEL_WIDTH: f32 = 420.0;
+
+struct Viewer {
+    db: NpcResDb,
+    // MainMan, MainLady rồi tới NPC thường
+    names: Vec<String>,
+    char_index: usize,
+    composer: Option<CharacterComposer>,
+    outfit: Equipment,
+    action: NpcAction,
+    dir: u32,
+    mounted: bool,
+    selected: usize,
+    paused: bool,
+    // Nhân vật đang xem là một NPC trong mô phỏng: animation lấy từ `sim.view`,
+    // click chuột để đi / chạy tới đó
+    sim: Simulation,
+    npc_id: u64,
+    // Hiệu ứng trạng thái gắn lên nhân vật, `status` là vị trí trong `statuses` (None = không gắn)
+    overlays: StatusOverlays,
+    statuses: Vec<String>,
+    status: Option<usize>,
+    sounds: ActionSoundPlayer<LogAudio>,
+    show_bounds: bool,
+    show_anchors: bool,
+    // In lại danh sách spr ra console ở frame kế tiếp
+    dirty: bool,
 }
 
-impl PakReader {
-    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let path_str = path.as_ref().to_string_lossy().to_string();
-        let mut f = File::open(path)?;
-        let mut header_buf = [0u8; 24];
-        f.read_exact(&mut header_buf)?;
-        let header = PakHeader {
-            signature: read_u32_le(&header_buf[0..4]),
-            count: read_u32_le(&header_buf[4..8]),
-            index_offset: read_u32_le(&header_buf[8..12]),
-            data_offset: read_u32_le(&header_buf[12..16]),
-            _crc32: read_u32_le(&header_buf[16..20]),
-            _reserved: [0; 12],
+impl Viewer {
+    fn new(db: NpcResDb, start: &str, overlays: StatusOverlays, statuses: Vec<String>) -> Self {
+        let mut names: Vec<String> = db
+            .chars
+            .iter()
+            .filter(|(_, r)| r.is_special())
+            .map(|(n, _)| n.clone())
+            .collect();
+        names.sort_by_key(|n| n != "MainMan");
+        names.extend(
+            db.chars
+                .iter()
+                .filter(|(_, r)| !r.is_special() && !r.actions.is_empty())
+                .map(|(n, _)| n.clone()),
+        );
+        let char_index = names.iter().position(|n| n == start).unwrap_or(0);
+
+        let mut viewer = Viewer {
+            db,
+            names,
+            char_index,
+            composer: None,
+            outfit: Equipment::default(),
+            action: NpcAction::NormalStand1,
+            dir: 0,
+            mounted: false,
+            selected: 0,
+            paused: false,
+            sim: Simulation::new(),
+            npc_id: 0,
+            overlays,
+            statuses,
+            status: None,
+            sounds: ActionSoundPlayer::new(LogAudio),
+            show_bounds: false,
+            show_anchors: true,
+            dirty: true,
         };
-        if header.signature != PACK_SIGNATURE {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Invalid PAK: {}", path_str),
-            ));
-        }
-        let mut index_map = HashMap::new();
-        f.seek(SeekFrom::Start(header.index_offset as u64))?;
-        let mut entry_buf = [0u8; 16];
-        for _ in 0..header.count {
-            f.read_exact(&mut entry_buf)?;
-            let id = read_u32_le(&entry_buf[0..4]);
-            index_map.insert(
-                id,
-                PakEntry {
-                    id,
-                    offset: read_u32_le(&entry_buf[4..8]),
-                    original_size: read_u32_le(&entry_buf[8..12]),
-                    compress_flag: read_u32_le(&entry_buf[12..16]),
-                },
-            );
-        }
-        Ok(PakReader {
-            file_path: path_str,
-            file: f,
-            header,
-            index_map,
-        })
+        viewer.select_character(char_index);
+        viewer
     }
 
-    pub fn find_file(&self, path: &str) -> Option<&PakEntry> {
-        let target_hash = jx_file_name_hash(path);
-        self.index_map.get(&target_hash)
+    fn res(&self) -> &NpcResEntry {
+        &self.db.chars[&self.names[self.char_index]]
     }
 
-    pub fn read_entry_data(&mut self, entry: &PakEntry) -> io::Result<Vec<u8>> {
-        let stored_size = entry.get_stored_size();
-        self.file.seek(SeekFrom::Start(entry.offset as u64))?;
-        let mut buffer = vec![0u8; stored_size as usize];
-        self.file.read_exact(&mut buffer)?;
-        match entry.get_compression_type() {
-            0 => Ok(buffer),
-            1 => nrv2b_decompress_8(&buffer, entry.original_size as usize)
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
-            _ => Ok(buffer),
+    fn select_character(&mut self, index: usize) {
+        self.char_index = index;
+        let res = self.res().clone();
+        self.outfit = Equipment::for_character(&res);
+        if res.is_special() {
+            // Mặc mặc định item đầu tiên của mỗi loại, trừ vũ khí và ngựa
+            for category in 0..self.outfit.categories.len() {
+                let slots = &self.outfit.categories[category].slots;
+                if slots.contains(&PartSlot::RightWeapon) || slots.contains(&PartSlot::HorseMiddle)
+                {
+                    continue;
+                }
+                if let Some(item) = self.outfit.valid_items(&res, category).first() {
+                    let item = item.to_string();
+                    let _ = self.outfit.equip(&res, category, &item);
+                }
+            }
+            self.composer = Some(CharacterComposer::new(res.clone()));
+        } else {
+            self.composer = None;
         }
+        self.mounted = false;
+        self.selected = self.selected.min(self.rows().len() - 1);
+        self.restart();
     }
-}
 
-pub fn nrv2b_decompress_8(src: &[u8], dst_len: usize) -> Result<Vec<u8>, String> {
-    let mut dst = Vec::with_capacity(dst_len);
-    let mut bb: u32 = 0;
-    let mut ilen: usize = 0;
-    let mut olen: usize = 0;
-    let mut last_m_off: u32 = 1;
-    let src_len = src.len();
-
-    let mut getbit = |bb: &mut u32, src: &[u8], ilen: &mut usize| -> Result<u32, String> {
-        if (*bb & 0x7f) != 0 {
-            *bb = bb.wrapping_mul(2);
-        } else {
-            if *ilen >= src.len() {
-                return Err("INPUT_OVERRUN".into());
-            }
-            *bb = (src[*ilen] as u32).wrapping_mul(2).wrapping_add(1);
-            *ilen += 1;
+    /// Tạo lại NPC đang xem tại chỗ cũ với vũ khí / ngựa / hướng đang chọn
+    /// và cho diễn action đang chọn từ frame 0 (không diễn được thì đứng)
+    fn restart(&mut self) {
+        if let Some(npc) = self.sim.npc(self.npc_id) {
+            self.overlays.clear(npc);
         }
-        Ok((*bb >> 8) & 1)
-    };
+        let pos = self
+            .sim
+            .despawn(self.npc_id)
+            .map_or(WorldPos::from_cell(0, (1, 1), (8, 16)), |npc| npc.pos);
+        let res = self.res().clone();
+        let name = self.names[self.char_index].clone();
+        let kind = match name.as_str() {
+            "MainLady" => NpcKind::Special(Sex::Lady),
+            _ if res.is_special() => NpcKind::Special(Sex::Man),
+            _ => NpcKind::Normal,
+        };
+        let dir = dir64_from_sprite(self.dir, self.dir_count());
+        let weapon = self.weapon().to_string();
 
-    loop {
-        while getbit(&mut bb, src, &mut ilen)? != 0 {
-            if ilen >= src_len || olen >= dst_len {
-                return Err("OVERRUN".into());
-            }
-            dst.push(src[ilen]);
-            ilen += 1;
-            olen += 1;
-        }
-        let mut m_off: u32 = 1;
-        loop {
-            m_off = m_off
-                .wrapping_mul(2)
-                .wrapping_add(getbit(&mut bb, src, &mut ilen)?);
-            if getbit(&mut bb, src, &mut ilen)? != 0 {
-                break;
-            }
-        }
-        if m_off == 2 {
-            m_off = last_m_off;
-        } else {
-            if ilen >= src_len {
-                return Err("INPUT_OVERRUN".into());
-            }
-            m_off = m_off
-                .wrapping_sub(3)
-                .wrapping_mul(256)
-                .wrapping_add(src[ilen] as u32);
-            ilen += 1;
-            if m_off == u32::MAX {
-                break;
-            }
-            m_off = m_off.wrapping_add(1);
-            last_m_off = m_off;
-        }
-        let mut m_len = getbit(&mut bb, src, &mut ilen)?;
-        m_len = m_len
-            .wrapping_mul(2)
-            .wrapping_add(getbit(&mut bb, src, &mut ilen)?);
-        if m_len == 0 {
-            m_len = 1;
-            loop {
-                m_len = m_len
-                    .wrapping_mul(2)
-                    .wrapping_add(getbit(&mut bb, src, &mut ilen)?);
-                if getbit(&mut bb, src, &mut ilen)? != 0 {
-                    break;
-                }
-            }
-            m_len += 2;
-        }
-        if m_off > 0x0d00 {
-            m_len += 1;
+        let id = self.sim.spawn(&name, kind, pos, Arc::new(res));
+        self.sim.set_weapon(id, &weapon);
+        if self.mounted {
+            self.sim.command(id, NpcCommand::Mount);
         }
-        if olen + (m_len as usize) + 1 > dst_len {
-            return Err("OUTPUT_OVERRUN".into());
+        self.sim.set_dir(id, dir);
+        self.sim.perform(id, self.action);
+        self.npc_id = id;
+        // NPC mới có id mới: gắn lại hiệu ứng đang chọn
+        self.set_status(self.status);
+        self.dirty = true;
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![Row::Character, Row::Action, Row::Direction];
+        if !self.statuses.is_empty() {
+            rows.push(Row::Status);
         }
-        let start = olen - m_off as usize;
-        for i in 0..=m_len as usize {
-            dst.push(dst[start + i]);
-            olen += 1;
+        if self.composer.is_some() {
+            rows.push(Row::Mounted);
+            rows.extend((0..self.outfit.categories.len()).map(Row::Category));
         }
+        rows
     }
-    Ok(dst)
-}
 
-// --- MODULE SPR PARSER ---
-#[derive(Debug, Clone)]
-pub struct SprHeader {
-    pub signature: [u8; 4], // Đã thêm trường signature
-    pub width: u16,
-    pub height: u16,
-    pub center_x: u16,
-    pub center_y: u16,
-    pub frames: u16,
-    pub colors: u16,
-    pub directions: u16,
-    pub interval: u16,
-    pub reserved: [u16; 6],
-}
+    fn category_with(&self, slot: PartSlot) -> Option<usize> {
+        self.outfit.category_of(slot)
+    }
 
-#[derive(Debug, Clone, Copy)]
-pub struct SprColor {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
-}
+    fn weapon(&self) -> &str {
+        self.category_with(PartSlot::RightWeapon)
+            .and_then(|c| self.outfit.item(c))
+            .unwrap_or(UNARMED_WEAPON)
+    }
 
-#[derive(Debug, Clone)]
-pub struct SprFrameInfo {
-    pub width: u16,
-    pub height: u16,
-    pub offset_x: i16,
-    pub offset_y: i16,
-    pub decoded_indices: Vec<u8>,
-    pub alpha_map: Vec<u8>,
-}
+    /// ActionId đang vẽ (nhân vật chính) theo vũ khí / ngựa, lùi về đứng nếu không diễn được
+    fn state(&self) -> Option<ActionId> {
+        let res = self.res();
+        res.resolve(self.weapon(), self.mounted, self.action)
+            .or_else(|| res.resolve(self.weapon(), self.mounted, NpcAction::NormalStand1))
+    }
 
-pub struct SprFile {
-    pub header: SprHeader,
-    pub palette: Vec<SprColor>,
-    pub frames: Vec<SprFrameInfo>,
-}
+    /// Timing của action NPC đang diễn
+    fn timing(&self) -> ActionTiming {
+        self.sim
+            .npc(self.npc_id)
+            .and_then(|npc| self.res().timing(npc.action, npc.state))
+            .unwrap_or(ActionTiming::STILL)
+    }
 
-impl SprFile {
-    pub fn from_reader<R: Read + Seek>(mut f: R) -> io::Result<Self> {
-        let mut sig = [0u8; 4];
-        f.read_exact(&mut sig)?;
-        if &sig[0..3] != b"SPR" {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid SPR signature",
-            ));
-        }
-
-        let header = SprHeader {
-            signature: sig,
-            width: f.read_u16::<LittleEndian>()?,
-            height: f.read_u16::<LittleEndian>()?,
-            center_x: f.read_u16::<LittleEndian>()?,
-            center_y: f.read_u16::<LittleEndian>()?,
-            frames: f.read_u16::<LittleEndian>()?,
-            colors: f.read_u16::<LittleEndian>()?,
-            directions: f.read_u16::<LittleEndian>()?,
-            interval: f.read_u16::<LittleEndian>()?,
-            reserved: [
-                f.read_u16::<LittleEndian>()?,
-                f.read_u16::<LittleEndian>()?,
-                f.read_u16::<LittleEndian>()?,
-                f.read_u16::<LittleEndian>()?,
-                f.read_u16::<LittleEndian>()?,
-                f.read_u16::<LittleEndian>()?,
-            ],
+    /// Frame hiện tại (trong một hướng) của NPC đang xem
+    fn frame(&self) -> u32 {
+        self.sim.view(self.npc_id).map_or(0, |view| view.frame)
+    }
+
+    fn dir_count(&self) -> u32 {
+        self.dir_count_of(self.action, self.state())
+    }
+
+    fn dir_count_of(&self, action: NpcAction, state: Option<ActionId>) -> u32 {
+        let res = self.res();
+        let info = match state {
+            Some(state) if res.is_special() => res.shadow(state),
+            _ => res.npc_action_spr(action),
         };
+        info.map(|i| i.directions).filter(|&d| d > 0).unwrap_or(8)
+    }
+
+    /// NPC trong mô phỏng khi đang đi theo click chuột (lúc đó vẽ theo trạng thái của nó)
+    fn walking(&self) -> Option<&Npc> {
+        self.sim.npc(self.npc_id).filter(|npc| npc.speed() > 0)
+    }
 
-        let mut palette = Vec::with_capacity(header.colors as usize);
-        for _ in 0..header.colors {
-            let r = f.read_u8()?;
-            let g = f.read_u8()?;
-            let b = f.read_u8()?;
-            palette.push(SprColor { r, g, b });
-        }
-
-        let mut offsets = Vec::with_capacity(header.frames as usize);
-        for _ in 0..header.frames {
-            let offset = f.read_u32::<LittleEndian>()?;
-            let length = f.read_u32::<LittleEndian>()?;
-            offsets.push((offset, length));
-        }
-
-        let mut frames = Vec::with_capacity(header.frames as usize);
-        let data_start_pos = f.stream_position()?;
-
-        for (offset, _) in offsets {
-            f.seek(SeekFrom::Start(data_start_pos + offset as u64))?;
-            let f_width = f.read_u16::<LittleEndian>()?;
-            let f_height = f.read_u16::<LittleEndian>()?;
-            let f_off_x = f.read_i16::<LittleEndian>()?;
-            let f_off_y = f.read_i16::<LittleEndian>()?;
-
-            let total_pixels = (f_width as usize) * (f_height as usize);
-            let mut indices = vec![0u8; total_pixels];
-            let mut alphas = vec![0u8; total_pixels];
-            let mut pixel_idx = 0;
-
-            while pixel_idx < total_pixels {
-                let count = f.read_u8()?;
-                let alpha = f.read_u8()?;
-                let count_usize = count as usize;
-
-                if alpha > 0 {
-                    for _ in 0..count_usize {
-                        if pixel_idx >= total_pixels {
-                            break;
+    /// Vị trí chân để vẽ, nội suy giữa hai tick logic
+    fn foot(&self) -> (f32, f32) {
+        self.sim
+            .view(self.npc_id)
+            .map_or((0.0, 0.0), |view| (view.x, view.y))
+    }
+
+    fn foot_pos(&self) -> WorldPos {
+        self.sim
+            .npc(self.npc_id)
+            .map(|npc| npc.pos)
+            .unwrap_or_default()
+    }
+
+    /// Đi (hoặc chạy) thẳng tới `target`, vũ khí / ngựa đã đặt lúc `restart`
+    fn walk_to(&mut self, target: WorldPos, run: bool) {
+        self.sim.move_along(self.npc_id, vec![target], run);
+    }
+
+    /// Gỡ hiệu ứng cũ khỏi nhân vật và gắn hiệu ứng `status` (chạy lại từ đầu)
+    fn set_status(&mut self, status: Option<usize>) {
+        let Some(npc) = self.sim.npc(self.npc_id) else {
+            return;
+        };
+        self.overlays.clear(npc);
+        self.status = status.filter(|&i| {
+            self.overlays
+                .attach(npc, &self.statuses[i], self.sim.clock.tick)
+        });
+    }
+
+    fn set_mounted(&mut self, mounted: bool) {
+        let res = self.res().clone();
+        let Some(horse) = self.category_with(PartSlot::HorseMiddle) else {
+            return;
+        };
+        if mounted && self.outfit.item(horse).is_none() {
+            let Some(item) = self
+                .outfit
+                .valid_items(&res, horse)
+                .first()
+                .map(|s| s.to_string())
+            else {
+                return;
+            };
+            let _ = self.outfit.equip(&res, horse, &item);
+        }
+        if !mounted {
+            self.outfit.unequip(horse);
+        }
+        self.mounted = mounted;
+    }
+
+    /// Đổi giá trị của dòng `row` đi `delta` bước (quay vòng)
+    fn change(&mut self, row: Row, delta: i32) {
+        let wrap =
+            |value: usize, len: usize| (value as i32 + delta).rem_euclid(len as i32) as usize;
+        match row {
+            Row::Character => self.select_character(wrap(self.char_index, self.names.len())),
+            Row::Action => {
+                let index = wrap(self.action as usize, NpcAction::ALL.len());
+                self.action = NpcAction::ALL[index];
+            }
+            Row::Direction => self.dir = wrap(self.dir as usize, self.dir_count() as usize) as u32,
+            Row::Status => {
+                // Vị trí 0 là "không gắn"
+                let current = self.status.map_or(0, |i| i + 1);
+                let next = wrap(current, self.statuses.len() + 1);
+                self.set_status(next.checked_sub(1));
+            }
+            Row::Mounted => self.set_mounted(!self.mounted),
+            Row::Category(category) => {
+                let res = self.res().clone();
+                // Vị trí 0 là "không mặc"
+                let items = self.outfit.valid_items(&res, category);
+                let current = self
+                    .outfit
+                    .item(category)
+                    .and_then(|item| items.iter().position(|i| *i == item))
+                    .map_or(0, |i| i + 1);
+                match wrap(current, items.len() + 1) {
+                    0 => {
+                        self.outfit.unequip(category);
+                    }
+                    i => {
+                        let item = items[i - 1].to_string();
+                        if let Err(e) = self.outfit.equip(&res, category, &item) {
+                            println!("⚠️ {}", e);
                         }
-                        let color_index = f.read_u8()?;
-                        indices[pixel_idx] = color_index;
-                        alphas[pixel_idx] = alpha;
-                        pixel_idx += 1;
                     }
-                } else {
-                    pixel_idx += count_usize;
+                }
+                if self.outfit.categories[category]
+                    .slots
+                    .contains(&PartSlot::HorseMiddle)
+                {
+                    self.mounted = self.outfit.item(category).is_some();
                 }
             }
-
-            frames.push(SprFrameInfo {
-                width: f_width,
-                height: f_height,
-                offset_x: f_off_x,
-                offset_y: f_off_y,
-                decoded_indices: indices,
-                alpha_map: alphas,
-            });
         }
-
-        Ok(SprFile {
-            header,
-            palette,
-            frames,
-        })
+        self.restart();
     }
-}
 
-// --- HELPER MACROQUAD ---
-struct SprTexture {
-    textures: Vec<Texture2D>,
-    offsets: Vec<(f32, f32)>,
-    interval: f32,
-    total_frames: usize,
-}
-
-impl SprTexture {
-    fn from_spr_file(spr: &SprFile) -> Self {
-        let mut textures = Vec::new();
-        let mut offsets = Vec::new();
-
-        for frame in &spr.frames {
-            let mut image_data = vec![0u8; frame.width as usize * frame.height as usize * 4];
-            for (i, &color_idx) in frame.decoded_indices.iter().enumerate() {
-                let r_idx = i * 4;
-                if frame.alpha_map[i] > 0 {
-                    let color = spr.palette[color_idx as usize];
-                    image_data[r_idx] = color.r;
-                    image_data[r_idx + 1] = color.g;
-                    image_data[r_idx + 2] = color.b;
-                    image_data[r_idx + 3] = 255;
-                } else {
-                    image_data[r_idx + 3] = 0;
-                }
+    fn row_text(&self, row: Row) -> (String, String) {
+        match row {
+            Row::Character => ("Character".into(), self.names[self.char_index].clone()),
+            Row::Action => {
+                let state = match (&self.composer, self.state()) {
+                    (Some(_), Some(state)) => format!(" -> {}", state.name()),
+                    _ => String::new(),
+                };
+                ("Action".into(), format!("{}{}", self.action.name(), state))
             }
-            let image = Image {
-                bytes: image_data,
-                width: frame.width,
-                height: frame.height,
-            };
-            let texture = Texture2D::from_image(&image);
-            texture.set_filter(FilterMode::Nearest);
-            textures.push(texture);
-            offsets.push((frame.offset_x as f32, frame.offset_y as f32));
+            Row::Direction => (
+                "Direction".into(),
+                format!("{} / {}", self.dir, self.dir_count()),
+            ),
+            Row::Status => {
+                let value = match self.status {
+                    Some(i) => {
+                        let id = &self.statuses[i];
+                        let name = self.overlays.visual(id).map_or("", |v| v.name.as_str());
+                        format!("{} {}", id, name)
+                    }
+                    None => "-".to_string(),
+                };
+                ("Status".into(), value)
+            }
+            Row::Mounted => ("Mounted".into(), self.mounted.to_string()),
+            Row::Category(category) => (
+                self.outfit.categories[category].name.clone(),
+                self.outfit.item(category).unwrap_or("-").to_string(),
+            ),
         }
+    }
 
-        let interval_sec = if spr.header.interval > 0 {
-            spr.header.interval as f32 / 18.0
-        } else {
-            0.05
-        };
+    /// Chạy mô phỏng theo thời gian thực, phát âm thanh theo sự kiện animation.
+    /// Action chạy một lần (đánh, bị thương) diễn lại sau khi kết thúc để xem liên tục.
+    fn update(&mut self, dt: f32) {
+        if self.paused {
+            return;
+        }
+        let was_walking = self.walking().is_some();
+        self.sim.advance(dt);
+        let res = &self.db.chars[&self.names[self.char_index]];
+        let mut replay = false;
+        for (id, event) in self.sim.drain_events() {
+            self.sounds.on_event(id, res, event);
+            replay |= id == self.npc_id && event == AnimEvent::Finished(self.action);
+        }
+        if replay && self.action != NpcAction::Die {
+            self.sim.perform(self.npc_id, self.action);
+        }
+        // Hiệu ứng chạy một lần đã hết thì bỏ chọn
+        self.overlays.update(self.sim.clock.tick);
+        if let Some(npc) = self.sim.npc(self.npc_id)
+            && self.overlays.active(npc).is_empty()
+        {
+            self.status = None;
+        }
+        if was_walking && self.walking().is_none() {
+            // Vừa tới nơi: quay lại action đang xem, giữ hướng đi cuối
+            let dir = self
+                .sim
+                .npc(self.npc_id)
+                .map_or(0, |npc| npc.sprite_dir(self.dir_count()));
+            self.dir = dir;
+            self.restart();
+        }
+    }
 
-        SprTexture {
-            textures,
-            offsets,
-            interval: interval_sec,
-            total_frames: spr.header.frames as usize,
+    /// Lùi / tiến `delta` frame khi đang dừng: diễn lại từ đầu rồi chạy tick tới frame cần xem
+    fn step(&mut self, delta: i32) {
+        let timing = self.timing();
+        let frames = timing.frames.max(1);
+        let target = (self.frame() as i32 + delta).rem_euclid(frames as i32) as u32;
+        self.restart();
+        for _ in 0..frames * timing.interval.max(1) {
+            if self.frame() == target {
+                break;
+            }
+            self.sim.step();
         }
+        // Không phát âm thanh khi tua từng frame
+        self.sim.drain_events();
     }
-}
 
-// --- MAIN SIMULATION ---
+    /// Lệnh vẽ nhân vật cùng các hiệu ứng trạng thái đang gắn
+    fn compose(&self, sprites: &mut dyn SpriteSource) -> Vec<DrawCommand> {
+        let (mut commands, dir) = self.compose_character(sprites);
+        if let Some(npc) = self.sim.npc(self.npc_id) {
+            self.overlays
+                .apply(npc, dir, self.sim.clock.tick, &mut commands, sprites);
+        }
+        commands
+    }
 
-#[derive(Deserialize, Debug)]
-struct NpcResJson {
-    #[serde(rename = "MainMan")]
-    main_man: SpecialNpcData,
-    #[serde(rename = "MainLady")]
-    main_lady: SpecialNpcData,
+    /// Vẽ theo trạng thái của NPC trong mô phỏng (action, ActionId, hướng, frame)
+    fn compose_character(&self, sprites: &mut dyn SpriteSource) -> (Vec<DrawCommand>, u32) {
+        let Some(view) = self.sim.view(self.npc_id) else {
+            return (Vec::new(), 0);
+        };
+        let dir = sprite_dir(view.dir, self.dir_count_of(view.action, Some(view.state)));
+        let frame = view.frame as usize;
+        let commands = match &self.composer {
+            Some(composer) => {
+                composer.compose(&self.outfit.parts(), view.state, dir, frame, sprites)
+            }
+            None => compose_npc(self.res(), view.action, dir, frame, sprites),
+        };
+        (commands, dir)
+    }
 }
 
-#[derive(Deserialize, Debug)]
-struct SpecialNpcData {
-    #[serde(default)]
-    weapon_logic: HashMap<String, HashMap<String, HashMap<String, String>>>,
-    #[serde(default)]
-    components: HashMap<String, HashMap<String, HashMap<String, String>>>,
-    #[serde(default)]
-    render_order: HashMap<String, HashMap<String, String>>,
-}
+// --- VẼ ---
 
-struct RenderLayer {
-    #[allow(dead_code)]
-    z_index: i32,
-    #[allow(dead_code)]
-    slot_name: String,
-    spr_data: Option<SprTexture>,
+fn draw_panel(viewer: &Viewer, stats: &AssetStats) {
+    let rows = viewer.rows();
+    draw_rectangle(
+        0.0,
+        0.0,
+        PANEL_WIDTH,
+        PANEL_TOP + ROW_HEIGHT * (rows.len() as f32 + 2.0),
+        Color::new(0.0, 0.0, 0.0, 0.5),
+    );
+    for (i, &row) in rows.iter().enumerate() {
+        let y = PANEL_TOP + ROW_HEIGHT * i as f32;
+        if i == viewer.selected {
+            draw_rectangle(0.0, y - ROW_HEIGHT + 5.0, PANEL_WIDTH, ROW_HEIGHT, DARKBLUE);
+        }
+        let (label, value) = viewer.row_text(row);
+        draw_text(&label, 10.0, y, 20.0, LIGHTGRAY);
+        draw_text(&value, 150.0, y, 20.0, WHITE);
+    }
+    let timing = viewer.timing();
+    let status = format!(
+        "Frame {}/{}  interval {}{}",
+        viewer.frame() + 1,
+        timing.frames,
+        timing.interval,
+        if viewer.paused { "  [PAUSED]" } else { "" }
+    );
+    let y = PANEL_TOP + ROW_HEIGHT * rows.len() as f32;
+    draw_text(&status, 10.0, y, 20.0, YELLOW);
+
+    let cache = format!(
+        "Cache: {} hit / {} miss  {:.1}/{} MB  dang tai {}",
+        stats.hits,
+        stats.misses,
+        stats.used_bytes as f32 / (1024.0 * 1024.0),
+        stats.budget_bytes / (1024 * 1024),
+        stats.pending
+    );
+    draw_text(&cache, 10.0, y + ROW_HEIGHT, 18.0, GRAY);
+
+    let help = [
+        "Up/Down: chon dong   Left/Right, click trai/phai, lan chuot: doi gia tri",
+        "PgUp/PgDn: +-10   Q/E: huong   M: ngua   Space: dung   ,/.: lui/tien frame",
+        "B: khung bao   N: diem neo   P: in duong dan spr",
+        "+/-, Ctrl + lan chuot: zoom   0: zoom 1:1   Click: di toi (Shift: chay)",
+    ];
+    for (i, line) in help.iter().enumerate() {
+        draw_text(
+            line,
+            10.0,
+            screen_height() - 10.0 - ROW_HEIGHT * (help.len() - 1 - i) as f32,
+            18.0,
+            GRAY,
+        );
+    }
 }
 
-#[macroquad::main("JX1 Character Viewer")]
-async fn main() {
-    println!("🚀 Đang khởi động Engine...");
-
-    // A. Load PAKs
-    let mut pak_readers = Vec::new();
-    if let Ok(entries) = fs::read_dir("data/pak") {
-        for entry in entries.flatten() {
-            if entry.path().extension().map_or(false, |e| e == "pak") {
-                if let Ok(reader) = PakReader::new(&entry.path()) {
-                    println!("   Loaded PAK: {}", entry.path().display());
-                    pak_readers.push(reader);
-                }
-            }
+fn draw_overlays(viewer: &Viewer, commands: &[DrawCommand], cx: f32, cy: f32, zoom: f32) {
+    if viewer.show_bounds {
+        for cmd in commands {
+            let Some(b) = Bounds::of_commands(std::slice::from_ref(cmd)) else {
+                continue;
+            };
+            let color = match cmd.layer {
+                Layer::Shadow => GRAY,
+                Layer::Part(_) => SKYBLUE,
+                Layer::Status(_) => PINK,
+            };
+            draw_rectangle_lines(
+                cx + b.min_x as f32 * zoom,
+                cy + b.min_y as f32 * zoom,
+                b.width() as f32 * zoom,
+                b.height() as f32 * zoom,
+                1.0,
+                color,
+            );
+        }
+        if let Some(b) = Bounds::of_commands(commands) {
+            draw_rectangle_lines(
+                cx + b.min_x as f32 * zoom,
+                cy + b.min_y as f32 * zoom,
+                b.width() as f32 * zoom,
+                b.height() as f32 * zoom,
+                2.0,
+                YELLOW,
+            );
         }
     }
 
-    // B. Load Config & Random
-    let file = File::open("data/newdata/npcres.json").expect("Thiếu npcres.json");
-    let db: NpcResJson = serde_json::from_reader(BufReader::new(file)).unwrap();
-
-    let mut rng = rand_crate::thread_rng();
-    let is_male = rand_crate::random::<bool>();
+    if viewer.show_anchors {
+        let anchors = AttachAnchors::from_commands(commands);
+        for (name, (x, y), color) in [
+            ("head", anchors.head, GREEN),
+            ("body", anchors.body, ORANGE),
+            ("foot", anchors.foot, RED),
+        ] {
+            let (x, y) = (cx + x as f32 * zoom, cy + y as f32 * zoom);
+            draw_line(x - 6.0, y, x + 6.0, y, 1.0, color);
+            draw_line(x, y - 6.0, x, y + 6.0, 1.0, color);
+            draw_text(name, x + 8.0, y + 4.0, 16.0, color);
+        }
+    }
+}
 
-    let (char_id, char_data) = if is_male {
-        ("MainMan", &db.main_man)
-    } else {
-        ("MainLady", &db.main_lady)
-    };
+/// Lưới ô quanh chân nhân vật và ô dưới con trỏ, để kiểm tra phép chiếu của camera
+fn draw_ground_grid(camera: &Camera, foot: WorldPos) {
+    let (region, cell) = (foot.region(), foot.cell());
+    for dy in -6..=6 {
+        for dx in -4..=4 {
+            let pos = WorldPos::from_cell(foot.world, region, (cell.0 + dx, cell.1 + dy));
+            let (x, y, w, h) = camera.cell_rect(pos.region(), pos.cell());
+            let color = if (dx, dy) == (0, 0) {
+                BEIGE
+            } else {
+                Color::new(1.0, 1.0, 1.0, 0.15)
+            };
+            draw_rectangle_lines(x, y, w, h, 1.0, color);
+        }
+    }
 
-    let states: Vec<&String> = char_data.weapon_logic.keys().collect();
-    if states.is_empty() {
+    let (mx, my) = mouse_position();
+    if mx < PANEL_WIDTH {
         return;
     }
-    let state = *states.choose(&mut rng).unwrap();
-    let valid_weapons = char_data.weapon_logic.get(state).unwrap();
-    let weapon_name = *valid_weapons
-        .keys()
-        .collect::<Vec<_>>()
-        .choose(&mut rng)
-        .unwrap();
-    let command = *valid_weapons
-        .get(weapon_name)
-        .unwrap()
-        .keys()
-        .collect::<Vec<_>>()
-        .choose(&mut rng)
-        .unwrap();
-    let action_id = valid_weapons
-        .get(weapon_name)
-        .unwrap()
-        .get(command)
-        .unwrap();
+    let (region, cell) = camera.pick_cell(mx, my);
+    let (x, y, w, h) = camera.cell_rect(region, cell);
+    draw_rectangle_lines(x, y, w, h, 2.0, YELLOW);
+    let text = format!(
+        "region ({}, {})  o ({}, {})  zoom {:.2}",
+        region.0, region.1, cell.0, cell.1, camera.zoom
+    );
+    draw_text(&text, PANEL_WIDTH + 10.0, 24.0, 20.0, YELLOW);
+}
 
+fn print_paths(viewer: &Viewer, commands: &[DrawCommand]) {
+    let (_, action) = viewer.row_text(Row::Action);
     println!(
-        "🎭 KỊCH BẢN: {} | {} | {} | Hành động: {}",
-        char_id, state, weapon_name, action_id
+        "🎭 {} | {} | Hướng {} | Frame {}",
+        viewer.names[viewer.char_index],
+        action,
+        viewer.dir,
+        viewer.frame()
     );
+    for (idx, cmd) in commands.iter().enumerate() {
+        println!(
+            "   Layer {:02} | Slot: {:<12} | Frame {:>4} | {}",
+            idx,
+            cmd.layer.name(),
+            cmd.frame,
+            cmd.spr_path
+        );
+    }
+}
 
-    // C. Prepare Layers
-    let mut render_layers: Vec<RenderLayer> = Vec::new();
-    let mut equipment = HashMap::new();
-    for (slot, item_list) in &char_data.components {
-        if slot == "rightweapon" {
-            if item_list.contains_key(weapon_name) {
-                equipment.insert(slot, weapon_name);
-            }
-        } else {
-            if let Some(item) = item_list.keys().collect::<Vec<_>>().choose(&mut rng) {
-                equipment.insert(slot, *item);
-            }
+// --- INPUT ---
+
+fn handle_input(viewer: &mut Viewer, camera: &mut Camera) {
+    let rows = viewer.rows();
+    let row = rows[viewer.selected.min(rows.len() - 1)];
+
+    if is_key_pressed(KeyCode::Up) {
+        viewer.selected = (viewer.selected + rows.len() - 1) % rows.len();
+    }
+    if is_key_pressed(KeyCode::Down) {
+        viewer.selected = (viewer.selected + 1) % rows.len();
+    }
+    for (key, delta) in [
+        (KeyCode::Right, 1),
+        (KeyCode::Left, -1),
+        (KeyCode::PageDown, 10),
+        (KeyCode::PageUp, -10),
+    ] {
+        if is_key_pressed(key) {
+            viewer.change(row, delta);
         }
     }
+    if is_key_pressed(KeyCode::E) {
+        viewer.change(Row::Direction, 1);
+    }
+    if is_key_pressed(KeyCode::Q) {
+        viewer.change(Row::Direction, -1);
+    }
+    if is_key_pressed(KeyCode::M) && viewer.composer.is_some() {
+        viewer.change(Row::Mounted, 1);
+    }
+    if is_key_pressed(KeyCode::Space) {
+        viewer.paused = !viewer.paused;
+    }
+    if is_key_pressed(KeyCode::Period) {
+        viewer.paused = true;
+        viewer.step(1);
+        viewer.dirty = true;
+    }
+    if is_key_pressed(KeyCode::Comma) {
+        viewer.paused = true;
+        viewer.step(-1);
+        viewer.dirty = true;
+    }
+    if is_key_pressed(KeyCode::B) {
+        viewer.show_bounds = !viewer.show_bounds;
+    }
+    if is_key_pressed(KeyCode::N) {
+        viewer.show_anchors = !viewer.show_anchors;
+    }
+    if is_key_pressed(KeyCode::P) {
+        viewer.dirty = true;
+    }
+    let (vx, vy, vw, vh) = camera.viewport;
+    let (cx, cy) = (vx + vw / 2.0, vy + vh / 2.0);
+    if is_key_pressed(KeyCode::Equal) {
+        camera.zoom_at(1.25, cx, cy);
+    }
+    if is_key_pressed(KeyCode::Minus) {
+        camera.zoom_at(0.8, cx, cy);
+    }
+    if is_key_pressed(KeyCode::Key0) {
+        camera.set_zoom(1.0);
+    }
 
-    let default_order = HashMap::new();
-    let order_config = char_data
-        .render_order
-        .get(action_id)
-        .unwrap_or(&default_order);
-    let z_order_str = order_config
-        .get("Dir1")
-        .map(|s: &String| s.as_str())
-        .unwrap_or("-1,14,13,1,4,9,7,6,5,12,8,0");
+    // Chuột: click trái / phải vào dòng để đổi giá trị, lăn chuột trên panel đổi dòng đang chọn,
+    // lăn ngoài panel để xoay hướng
+    let (mx, my) = mouse_position();
+    let hovered = (mx < PANEL_WIDTH)
+        .then(|| ((my - PANEL_TOP + ROW_HEIGHT - 5.0) / ROW_HEIGHT).floor())
+        .filter(|&i| i >= 0.0 && (i as usize) < rows.len())
+        .map(|i| i as usize);
+    if let Some(i) = hovered {
+        if is_mouse_button_pressed(MouseButton::Left) {
+            viewer.selected = i;
+            viewer.change(rows[i], 1);
+        }
+        if is_mouse_button_pressed(MouseButton::Right) {
+            viewer.selected = i;
+            viewer.change(rows[i], -1);
+        }
+    }
+    // Click trái ngoài panel: đi tới đó, giữ Shift để chạy
+    if mx >= PANEL_WIDTH && is_mouse_button_pressed(MouseButton::Left) {
+        let run = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        viewer.walk_to(camera.screen_to_pos(0, mx, my), run);
+    }
+    let wheel = mouse_wheel().1;
+    let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+    if wheel != 0.0 && ctrl && hovered.is_none() {
+        camera.zoom_at(if wheel > 0.0 { 1.25 } else { 0.8 }, mx, my);
+    } else if wheel != 0.0 {
+        let delta = if wheel > 0.0 { -1 } else { 1 };
+        match hovered {
+            Some(i) => viewer.change(rows[i], delta),
+            None => viewer.change(Row::Direction, delta),
+        }
+    }
+}
 
-    let layer_ids: Vec<i32> = z_order_str
-        .split(',')
-        .filter_map(|s: &str| s.trim().parse::<i32>().ok())
-        .filter(|&id| id >= 0)
-        .collect();
+// --- MAIN ---
 
-    // --- SỬA LỖI 2: LOGIC DÒ TÌM ĐƯỜNG DẪN THÔNG MINH ---
-    for (idx, layer_id) in layer_ids.iter().enumerate() {
-        let slot_name = get_slot_name(*layer_id);
-        let mut spr_texture = None;
-        let mut debug_msg = "No Item".to_string();
-
-        if let Some(item_name) = equipment.get(&slot_name) {
-            if let Some(spr_path_raw) = char_data
-                .components
-                .get(&slot_name)
-                .and_then(|i: &HashMap<String, HashMap<String, String>>| i.get(*item_name))
-                .and_then(|a: &HashMap<String, String>| a.get(action_id))
-            {
-                // Chuẩn hóa input ban đầu
-                let raw = spr_path_raw.replace('/', "\\"); // Đổi / -> \
-
-                // Tạo 3 ứng viên đường dẫn để thử
-                let candidates = vec![
-                    format!("\\{}", raw.trim_start_matches('\\')), // 1. Có \ ở đầu: \spr\npcres...
-                    raw.trim_start_matches('\\').to_string(), // 2. Không \ ở đầu: spr\npcres...
-                    raw.replace("\\spr\\", "\\")
-                        .trim_start_matches('\\')
-                        .to_string(), // 3. Bỏ spr: npcres...
-                ];
-
-                let mut found = false;
-                'pak_loop: for pak in &mut pak_readers {
-                    for try_path in &candidates {
-                        if let Some(entry) = pak.find_file(try_path) {
-                            if let Ok(data) = pak.read_entry_data(&entry.clone()) {
-                                let cursor = Cursor::new(data);
-                                if let Ok(spr_file) = SprFile::from_reader(cursor) {
-                                    spr_texture = Some(SprTexture::from_spr_file(&spr_file));
-                                    debug_msg = format!("✅ OK ({})", try_path);
-                                    found = true;
-                                    break 'pak_loop;
-                                }
-                            }
-                        }
-                    }
-                }
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "JX1 Character Viewer".to_owned(),
+        window_width: 1024,
+        window_height: 720,
+        ..Default::default()
+    }
+}
 
-                if !found {
-                    debug_msg = format!("❌ Failed (Input: {})", spr_path_raw);
-                }
-            } else {
-                debug_msg = "No Action Logic".to_string();
+#[macroquad::main(window_conf)]
+async fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    // --- Parse Arguments ---
+    let mut char_name = "MainMan".to_string();
+    let mut npcres_path = PathBuf::from("data/newdata/npcres.json");
+    let mut pak_dir = PathBuf::from("data/pak");
+    let mut extracted_dir: Option<PathBuf> = None;
+    let mut status_path = PathBuf::from(DEFAULT_STATUS_TABLE);
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().unwrap_or_default();
+        match arg.as_str() {
+            "-c" | "--char" => char_name = value(),
+            "--npcres" => npcres_path = PathBuf::from(value()),
+            "--pak" => pak_dir = PathBuf::from(value()),
+            "--extracted" => extracted_dir = Some(PathBuf::from(value())),
+            "--status" => status_path = PathBuf::from(value()),
+            _ => {
+                println!("Usage:");
+                println!(
+                    "  jx_render_demo [-c <CharacterName>] [--npcres <npcres.json>] [--pak <pak_dir> | --extracted <dir>] [--status <bảng trạng thái>]"
+                );
+                process::exit(2);
             }
         }
-
-        render_layers.push(RenderLayer {
-            z_index: *layer_id,
-            slot_name: slot_name.clone(),
-            spr_data: spr_texture,
-        });
-
-        println!(
-            "   Layer {:02} | Slot: {:<12} | {}",
-            idx, slot_name, debug_msg
-        );
     }
 
-    // D. Game Loop
-    let mut global_timer = 0.0f32;
-    let center_x = screen_width() / 2.0;
-    let center_y = screen_height() / 2.0;
+    println!("🚀 Đang khởi động Engine...");
+    let loader: Arc<dyn AssetLoader> = match extracted_dir {
+        Some(root) => Arc::new(DirLoader { root }),
+        None => {
+            let paks = PakSet::open_dir(&pak_dir).unwrap_or_default();
+            for reader in &paks.readers {
+                println!("   Loaded PAK: {}", reader.file_path);
+            }
+            Arc::new(PakLoader::new(paks))
+        }
+    };
+    let workers = thread::available_parallelism().map_or(2, |n| n.get());
+    let mut assets = AssetManager::new(loader, workers, ASSET_BUDGET);
+
+    let db = NpcResDb::load(&npcres_path).unwrap_or_else(|e| {
+        eprintln!("❌ Không đọc được {}: {}", npcres_path.display(), e);
+        process::exit(1);
+    });
+    if db.chars.is_empty() {
+        eprintln!("❌ {} không có nhân vật nào", npcres_path.display());
+        process::exit(1);
+    }
 
-    // Tìm base_interval từ layer body (nếu có), nếu không thì lấy mặc định 0.055 (18 FPS)
-    let base_interval = render_layers
+    // Thiếu bảng trạng thái thì vẫn chạy, chỉ không có hiệu ứng để gắn
+    let visuals = match TabFile::load(&status_path) {
+        Ok(table) => parse_status_table(&table),
+        Err(e) => {
+            println!("⚠️ Không đọc được {}: {}", status_path.display(), e);
+            Vec::new()
+        }
+    };
+    let mut statuses: Vec<String> = visuals
         .iter()
-        .find(|l| l.slot_name == "body" && l.spr_data.is_some())
-        .and_then(|l| l.spr_data.as_ref())
-        .map(|s| s.interval)
-        .unwrap_or(0.055);
+        .filter(|v| v.spr.is_some())
+        .map(|v| v.id.clone())
+        .collect();
+    // Status1, Status2, ..., Status10
+    statuses.sort_by_key(|id| (id.len(), id.clone()));
+    let mut viewer = Viewer::new(db, &char_name, StatusOverlays::new(visuals), statuses);
+    let mut textures = TextureCache::default();
+    // Camera đuổi theo chân nhân vật trong mô phỏng
+    let mut camera = Camera::new(screen_width() - PANEL_WIDTH, screen_height());
+    camera.look_at(viewer.foot_pos());
 
     loop {
-        clear_background(DARKGRAY); // Đổi màu nền tối để dễ nhìn
-        global_timer += get_frame_time();
-
-        // Tính master frame dựa trên base_interval
-        let master_frame = (global_timer / base_interval) as usize;
-
-        for layer in &render_layers {
-            if let Some(anim) = &layer.spr_data {
-                if anim.total_frames == 0 {
-                    continue;
-                }
-
-                // Đồng bộ frame index
-                let frame_idx = master_frame % anim.total_frames;
-
-                let texture = &anim.textures[frame_idx];
-                let (off_x, off_y) = anim.offsets[frame_idx];
-
-                // Công thức vẽ chuẩn: Center - Anchor + Offset
-                let draw_x = center_x - anim.anchor.0 + off_x;
-                let draw_y = center_y - anim.anchor.1 + off_y;
+        clear_background(DARKGRAY);
+        let char_index = viewer.char_index;
+        camera.set_viewport(
+            PANEL_WIDTH,
+            0.0,
+            screen_width() - PANEL_WIDTH,
+            screen_height(),
+        );
+        handle_input(&mut viewer, &mut camera);
+        if viewer.char_index != char_index {
+            textures.clear();
+        }
+        viewer.update(get_frame_time());
+        let foot = viewer.foot_pos();
+        camera.follow(foot, get_frame_time());
+
+        assets.poll();
+        let commands = viewer.compose(&mut assets);
+        // Chờ tải xong mới in để danh sách layer đầy đủ
+        if viewer.dirty && assets.stats().pending == 0 {
+            print_paths(&viewer, &commands);
+            viewer.dirty = false;
+        }
 
-                draw_texture(texture, draw_x, draw_y, WHITE);
+        draw_ground_grid(&camera, foot);
+        let (fx, fy) = viewer.foot();
+        let (cx, cy) = camera.world_to_screen(fx, fy);
+        let zoom = camera.zoom;
+        for cmd in &commands {
+            if let Some(texture) = textures.get(&cmd.spr_path, cmd.frame, &mut assets) {
+                draw_texture_ex(
+                    texture,
+                    cx + cmd.x as f32 * zoom,
+                    cy + cmd.y as f32 * zoom,
+                    Color::new(1.0, 1.0, 1.0, cmd.opacity),
+                    DrawTextureParams {
+                        dest_size: Some(vec2(texture.width() * zoom, texture.height() * zoom)),
+                        ..Default::default()
+                    },
+                );
             }
         }
+        draw_overlays(&viewer, &commands, cx, cy, zoom);
+        // Con trỏ trên nhân vật: viền trắng quanh khung bao
+        let (mx, my) = mouse_position();
+        if let Some(b) = Bounds::of_commands(&commands)
+            && camera.pick(mx, my, [(0, foot, b)]).is_some()
+        {
+            draw_rectangle_lines(
+                cx + b.min_x as f32 * zoom,
+                cy + b.min_y as f32 * zoom,
+                b.width() as f32 * zoom,
+                b.height() as f32 * zoom,
+                1.0,
+                WHITE,
+            );
+        }
+        draw_panel(&viewer, &assets.stats());
 
-        draw_text(
-            format!("Action: {}", action_id).as_str(),
-            20.0,
-            20.0,
-            30.0,
-            WHITE,
-        );
-        draw_text(
-            format!("State: {}", state).as_str(),
-            20.0,
-            50.0,
-            30.0,
-            WHITE,
-        );
         next_frame().await
     }
 }
-
-fn get_slot_name(id: i32) -> String {
-    match id {
-        0 => "head",
-        1 => "body",
-        2 => "lefthand",
-        4 => "leftweapon",
-        5 => "shoulder",
-        6 => "horsemiddle",
-        7 => "horsefront",
-        8 => "horseback",
-        12 => "hair",
-        13 => "lefthand",
-        14 => "rightweapon",
-        _ => "unknown",
-    }
-    .to_string()
-}
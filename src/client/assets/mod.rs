@@ -0,0 +1,537 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::hash::Hash;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::client::compose::SpriteSource;
+use crate::client::spr::SprFile;
+use crate::common::pak::PakSet;
+
+// --- NGUỒN DỮ LIỆU THÔ ---
+
+/// Đọc bytes của file theo đường dẫn game, dùng chung giữa các worker
+pub trait AssetLoader: Send + Sync {
+    fn load(&self, path: &str) -> Option<Vec<u8>>;
+}
+
+/// Đọc từ các file PAK. `PakReader` dùng chung một file handle nên đọc phải khoá,
+/// phần giải mã SPR (tốn thời gian hơn) vẫn chạy song song.
+pub struct PakLoader {
+    paks: Mutex<PakSet>,
+}
+
+impl PakLoader {
+    pub fn new(paks: PakSet) -> Self {
+        PakLoader {
+            paks: Mutex::new(paks),
+        }
+    }
+}
+
+impl AssetLoader for PakLoader {
+    fn load(&self, path: &str) -> Option<Vec<u8>> {
+        self.paks.lock().ok()?.read(path).ok().flatten()
+    }
+}
+
+/// Đọc từ thư mục đã giải nén (vd "extracted/")
+pub struct DirLoader {
+    pub root: PathBuf,
+}
+
+impl AssetLoader for DirLoader {
+    fn load(&self, path: &str) -> Option<Vec<u8>> {
+        let clean = path.replace('\\', "/");
+        fs::read(self.root.join(clean.trim_start_matches('/'))).ok()
+    }
+}
+
+impl<F> AssetLoader for F
+where
+    F: Fn(&str) -> Option<Vec<u8>> + Send + Sync,
+{
+    fn load(&self, path: &str) -> Option<Vec<u8>> {
+        self(path)
+    }
+}
+
+// --- FRAME ĐÃ GIẢI MÃ ---
+
+/// Một frame RGBA8 đã giải mã, sẵn sàng upload lên GPU hoặc vẽ bằng CPU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl FrameImage {
+    /// Ô 1x1 trong suốt, trả về khi frame chưa giải mã xong
+    pub fn placeholder() -> Self {
+        FrameImage {
+            width: 1,
+            height: 1,
+            rgba: vec![0; 4],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameState {
+    Ready(Arc<FrameImage>),
+    // Đang giải mã, tạm vẽ placeholder
+    Loading(Arc<FrameImage>),
+    // Không có file hoặc file lỗi
+    Missing,
+}
+
+// --- LRU CACHE THEO DUNG LƯỢNG ---
+
+/// LRU cache giới hạn theo tổng số byte của các entry
+struct LruCache<K, V> {
+    map: HashMap<K, (V, usize, u64)>,
+    // thời điểm dùng gần nhất -> key, entry đầu tiên là entry cũ nhất
+    order: BTreeMap<u64, K>,
+    clock: u64,
+    used: usize,
+    budget: usize,
+}
+
+impl<K: Clone + Eq + Hash, V> LruCache<K, V> {
+    fn new(budget: usize) -> Self {
+        LruCache {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            used: 0,
+            budget,
+        }
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Lấy entry và đánh dấu vừa được dùng
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let entry = self.map.get_mut(key)?;
+        self.order.remove(&entry.2);
+        self.clock += 1;
+        entry.2 = self.clock;
+        self.order.insert(self.clock, key.clone());
+        Some(&entry.0)
+    }
+
+    /// Thêm entry rồi bỏ các entry cũ nhất cho tới khi vừa ngân sách.
+    /// Entry vừa thêm luôn được giữ lại (kể cả khi một mình nó vượt ngân sách).
+    /// Trả về số entry bị bỏ.
+    fn insert(&mut self, key: K, value: V, size: usize) -> usize {
+        if let Some((_, old_size, stamp)) = self.map.remove(&key) {
+            self.order.remove(&stamp);
+            self.used -= old_size;
+        }
+        self.clock += 1;
+        self.map.insert(key.clone(), (value, size, self.clock));
+        self.order.insert(self.clock, key);
+        self.used += size;
+
+        let mut evicted = 0;
+        while self.used > self.budget && self.map.len() > 1 {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((_, size, _)) = self.map.remove(&oldest) {
+                self.used -= size;
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+}
+
+// --- WORKER ---
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AssetKey {
+    Sprite(String),
+    Frame(String, usize),
+}
+
+#[derive(Clone)]
+enum CachedAsset {
+    Sprite(Arc<SprFile>),
+    Frame(Arc<FrameImage>),
+}
+
+enum Job {
+    Sprite(String),
+    Frame(String, usize, Arc<SprFile>),
+}
+
+enum JobResult {
+    Sprite(String, Option<Arc<SprFile>>),
+    Frame(String, usize, Option<FrameImage>),
+}
+
+fn run_job(loader: &dyn AssetLoader, job: Job) -> JobResult {
+    match job {
+        Job::Sprite(path) => {
+            let sprite = loader
+                .load(&path)
+                .and_then(|data| SprFile::from_bytes(&data).ok())
+                .map(Arc::new);
+            JobResult::Sprite(path, sprite)
+        }
+        Job::Frame(path, index, sprite) => {
+            let image = sprite.frame_rgba(index).map(|rgba| {
+                let info = &sprite.frames[index];
+                FrameImage {
+                    width: info.width as u32,
+                    height: info.height as u32,
+                    rgba,
+                }
+            });
+            JobResult::Frame(path, index, image)
+        }
+    }
+}
+
+/// Dung lượng ước tính của SPR đã parse (index + alpha của mọi frame)
+fn sprite_bytes(sprite: &SprFile) -> usize {
+    let frames: usize = sprite
+        .frames
+        .iter()
+        .map(|f| f.decoded_indices.len() + f.alpha_map.len())
+        .sum();
+    frames + sprite.palette.len() * 3
+}
+
+// --- ASSET MANAGER ---
+
+/// Thống kê cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AssetStats {
+    // Lấy được ngay từ cache
+    pub hits: u64,
+    // Chưa có trong cache (đã xếp hàng hoặc đang giải mã)
+    pub misses: u64,
+    pub loaded: u64,
+    pub failed: u64,
+    pub evictions: u64,
+    pub used_bytes: usize,
+    pub budget_bytes: usize,
+    pub pending: usize,
+}
+
+/// Giải mã SPR trên pool worker, giữ SPR đã parse và frame RGBA trong LRU cache
+/// giới hạn theo bộ nhớ. Gọi `poll` mỗi frame để nhận kết quả từ worker.
+pub struct AssetManager {
+    cache: LruCache<AssetKey, CachedAsset>,
+    failed: HashSet<AssetKey>,
+    pending: HashSet<AssetKey>,
+    jobs: Option<Sender<Job>>,
+    results: Receiver<JobResult>,
+    workers: Vec<JoinHandle<()>>,
+    placeholder: Arc<FrameImage>,
+    stats: AssetStats,
+}
+
+impl AssetManager {
+    pub fn new(loader: Arc<dyn AssetLoader>, workers: usize, budget_bytes: usize) -> Self {
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let loader = Arc::clone(&loader);
+                let jobs = Arc::clone(&job_rx);
+                let results = result_tx.clone();
+                thread::spawn(move || {
+                    loop {
+                        let job = match jobs.lock() {
+                            Ok(rx) => rx.recv(),
+                            Err(_) => break,
+                        };
+                        let Ok(job) = job else {
+                            break;
+                        };
+                        if results.send(run_job(loader.as_ref(), job)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        AssetManager {
+            cache: LruCache::new(budget_bytes),
+            failed: HashSet::new(),
+            pending: HashSet::new(),
+            jobs: Some(job_tx),
+            results: result_rx,
+            workers,
+            placeholder: Arc::new(FrameImage::placeholder()),
+            stats: AssetStats {
+                budget_bytes,
+                ..AssetStats::default()
+            },
+        }
+    }
+
+    pub fn stats(&self) -> AssetStats {
+        AssetStats {
+            used_bytes: self.cache.used,
+            pending: self.pending.len(),
+            ..self.stats
+        }
+    }
+
+    /// Nhận các kết quả đã xong, trả về số kết quả nhận được
+    pub fn poll(&mut self) -> usize {
+        let mut received = 0;
+        while let Ok(result) = self.results.try_recv() {
+            self.finish(result);
+            received += 1;
+        }
+        received
+    }
+
+    /// Chờ tới khi mọi việc đang xếp hàng xong (tool headless, test)
+    pub fn wait_idle(&mut self) {
+        while !self.pending.is_empty() {
+            match self.results.recv() {
+                Ok(result) => self.finish(result),
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn finish(&mut self, result: JobResult) {
+        let (key, asset, size) = match result {
+            JobResult::Sprite(path, sprite) => {
+                let size = sprite.as_deref().map_or(0, sprite_bytes);
+                (
+                    AssetKey::Sprite(path),
+                    sprite.map(CachedAsset::Sprite),
+                    size,
+                )
+            }
+            JobResult::Frame(path, index, image) => {
+                let size = image.as_ref().map_or(0, |i| i.rgba.len());
+                let asset = image.map(|i| CachedAsset::Frame(Arc::new(i)));
+                (AssetKey::Frame(path, index), asset, size)
+            }
+        };
+        self.pending.remove(&key);
+        match asset {
+            Some(asset) => {
+                self.stats.loaded += 1;
+                self.stats.evictions += self.cache.insert(key, asset, size) as u64;
+            }
+            None => {
+                self.stats.failed += 1;
+                self.failed.insert(key);
+            }
+        }
+    }
+
+    fn enqueue(&mut self, key: AssetKey, job: Job) {
+        if self.pending.contains(&key) {
+            return;
+        }
+        if let Some(jobs) = &self.jobs
+            && jobs.send(job).is_ok()
+        {
+            self.pending.insert(key);
+        }
+    }
+
+    /// SPR đã parse, `None` nếu đang tải (đã xếp hàng) hoặc không có file
+    pub fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>> {
+        let key = AssetKey::Sprite(path.to_string());
+        if let Some(CachedAsset::Sprite(sprite)) = self.cache.get(&key) {
+            self.stats.hits += 1;
+            return Some(Arc::clone(sprite));
+        }
+        if self.failed.contains(&key) {
+            return None;
+        }
+        self.stats.misses += 1;
+        self.enqueue(key, Job::Sprite(path.to_string()));
+        None
+    }
+
+    /// Frame RGBA `index` (tuyệt đối) của spr `path`. Chưa có thì xếp hàng giải mã
+    /// và trả về placeholder.
+    pub fn frame(&mut self, path: &str, index: usize) -> FrameState {
+        let key = AssetKey::Frame(path.to_string(), index);
+        if let Some(CachedAsset::Frame(image)) = self.cache.get(&key) {
+            self.stats.hits += 1;
+            return FrameState::Ready(Arc::clone(image));
+        }
+        let sprite_key = AssetKey::Sprite(path.to_string());
+        if self.failed.contains(&key) || self.failed.contains(&sprite_key) {
+            return FrameState::Missing;
+        }
+        self.stats.misses += 1;
+
+        // Frame cần SPR đã parse: chưa có thì tải SPR trước, lần hỏi sau mới giải mã frame
+        match self.cache.get(&sprite_key) {
+            Some(CachedAsset::Sprite(sprite)) => {
+                let job = Job::Frame(path.to_string(), index, Arc::clone(sprite));
+                self.enqueue(key, job);
+            }
+            _ => self.enqueue(sprite_key, Job::Sprite(path.to_string())),
+        }
+        FrameState::Loading(Arc::clone(&self.placeholder))
+    }
+
+    pub fn is_cached(&self, path: &str, index: usize) -> bool {
+        self.cache
+            .contains(&AssetKey::Frame(path.to_string(), index))
+    }
+}
+
+impl SpriteSource for AssetManager {
+    fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>> {
+        AssetManager::sprite(self, path)
+    }
+}
+
+impl Drop for AssetManager {
+    fn drop(&mut self) {
+        // Đóng hàng đợi để worker thoát vòng lặp
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    // SPR 2 frame, mỗi frame w x h toàn màu index 1 (đỏ)
+    fn make_spr(w: u16, h: u16) -> Vec<u8> {
+        let mut frames = Vec::new();
+        for _ in 0..2 {
+            let mut frame = Vec::new();
+            for v in [w, h, 0, 0] {
+                frame.write_u16::<LittleEndian>(v).unwrap();
+            }
+            for _ in 0..h {
+                frame.extend([w as u8, 255]);
+                frame.extend(std::iter::repeat_n(1u8, w as usize));
+            }
+            frames.push(frame);
+        }
+
+        let mut data = b"SPR\0".to_vec();
+        for v in [w, h, w / 2, h, 2, 2, 1, 1, 0, 0, 0, 0, 0, 0] {
+            data.write_u16::<LittleEndian>(v).unwrap();
+        }
+        data.extend([0, 0, 0, 255, 0, 0]);
+        let mut offset = 0u32;
+        for frame in &frames {
+            data.write_u32::<LittleEndian>(offset).unwrap();
+            data.write_u32::<LittleEndian>(frame.len() as u32).unwrap();
+            offset += frame.len() as u32;
+        }
+        for frame in frames {
+            data.extend(frame);
+        }
+        data
+    }
+
+    fn manager(budget: usize) -> AssetManager {
+        let loader = |path: &str| match path {
+            "\\spr\\a.spr" => Some(make_spr(4, 4)),
+            "\\spr\\b.spr" => Some(make_spr(8, 8)),
+            "\\spr\\bad.spr" => Some(b"not a sprite".to_vec()),
+            _ => None,
+        };
+        AssetManager::new(Arc::new(loader), 2, budget)
+    }
+
+    fn load_frame(assets: &mut AssetManager, path: &str, index: usize) -> FrameState {
+        for _ in 0..3 {
+            if let state @ (FrameState::Ready(_) | FrameState::Missing) = assets.frame(path, index)
+            {
+                return state;
+            }
+            assets.wait_idle();
+        }
+        assets.frame(path, index)
+    }
+
+    #[test]
+    fn placeholder_then_decoded_frame() {
+        let mut assets = manager(1 << 20);
+        assert!(matches!(
+            assets.frame("\\spr\\a.spr", 1),
+            FrameState::Loading(ref p) if p.width == 1
+        ));
+
+        let FrameState::Ready(image) = load_frame(&mut assets, "\\spr\\a.spr", 1) else {
+            panic!("frame chưa được giải mã");
+        };
+        assert_eq!((image.width, image.height), (4, 4));
+        assert_eq!(&image.rgba[..4], &[255, 0, 0, 255]);
+
+        let before = assets.stats();
+        assert!(matches!(
+            assets.frame("\\spr\\a.spr", 1),
+            FrameState::Ready(_)
+        ));
+        let after = assets.stats();
+        assert_eq!(after.hits, before.hits + 1);
+        assert_eq!(after.misses, before.misses);
+        assert_eq!(after.pending, 0);
+    }
+
+    #[test]
+    fn missing_and_invalid_files() {
+        let mut assets = manager(1 << 20);
+        assert_eq!(
+            load_frame(&mut assets, "\\spr\\none.spr", 0),
+            FrameState::Missing
+        );
+        assert_eq!(
+            load_frame(&mut assets, "\\spr\\bad.spr", 0),
+            FrameState::Missing
+        );
+        assert_eq!(
+            load_frame(&mut assets, "\\spr\\a.spr", 5),
+            FrameState::Missing
+        );
+        assert_eq!(assets.stats().failed, 3);
+        assert!(assets.sprite("\\spr\\none.spr").is_none());
+    }
+
+    #[test]
+    fn evicts_least_recently_used_within_budget() {
+        // SPR a: 2 x 16 x 2 + 6 = 70 byte, frame a: 64 byte, SPR b: 262, frame b: 256
+        let mut assets = manager(600);
+        load_frame(&mut assets, "\\spr\\a.spr", 0);
+        assert!(assets.is_cached("\\spr\\a.spr", 0));
+        assert_eq!(assets.stats().used_bytes, 70 + 64);
+
+        load_frame(&mut assets, "\\spr\\b.spr", 0);
+        let stats = assets.stats();
+        assert!(stats.used_bytes <= 600);
+        assert!(stats.evictions > 0);
+        // SPR a cũ nhất bị bỏ trước, frame a (dùng sau) vẫn còn
+        assert!(assets.is_cached("\\spr\\b.spr", 0));
+        assert!(assets.is_cached("\\spr\\a.spr", 0));
+
+        load_frame(&mut assets, "\\spr\\b.spr", 1);
+        assert!(!assets.is_cached("\\spr\\a.spr", 0));
+        assert!(assets.stats().used_bytes <= 600);
+    }
+}
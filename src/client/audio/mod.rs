@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::npc::{ActionId, AnimEvent, NpcAction};
+use crate::common::npcres::NpcResEntry;
+use crate::common::pak::PakSet;
+
+// Thư mục mặc định chứa file wav trong pak
+pub const DEFAULT_SOUND_ROOT: &str = "\\sound";
+
+// --- SỰ KIỆN ÂM THANH ---
+
+/// Action đang phát: của nhân vật chính (ActionId) hoặc NPC thường (NpcAction)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundAction {
+    Special(ActionId),
+    Npc(NpcAction),
+}
+
+#[derive(Debug, Clone)]
+pub struct SoundEvent {
+    pub npc_id: u64,
+    pub action: SoundAction,
+    // Đường dẫn game đầy đủ (vd "\sound\sound_m39.wav")
+    pub path: String,
+    // Dữ liệu wav đọc từ pak, `None` nếu không có SoundBank hoặc không tìm thấy file
+    pub data: Option<Arc<Vec<u8>>>,
+}
+
+/// Backend phát âm thanh, thay được tuỳ nền tảng
+pub trait AudioBackend {
+    fn play(&mut self, event: &SoundEvent);
+}
+
+/// Bỏ qua mọi âm thanh (server, CI)
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn play(&mut self, _event: &SoundEvent) {}
+}
+
+/// Ghi lại các sự kiện đã phát, dùng cho test
+#[derive(Default)]
+pub struct RecordingAudio {
+    pub events: Vec<SoundEvent>,
+}
+
+impl AudioBackend for RecordingAudio {
+    fn play(&mut self, event: &SoundEvent) {
+        self.events.push(event.clone());
+    }
+}
+
+// --- ĐỌC WAV TỪ PAK ---
+
+/// Đọc file wav từ pak, cache lại cả kết quả lỗi
+pub struct SoundBank {
+    pub paks: PakSet,
+    cache: HashMap<String, Option<Arc<Vec<u8>>>>,
+}
+
+impl SoundBank {
+    pub fn new(paks: PakSet) -> Self {
+        SoundBank {
+            paks,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self, path: &str) -> Option<Arc<Vec<u8>>> {
+        if let Some(cached) = self.cache.get(path) {
+            return cached.clone();
+        }
+        let loaded = self.paks.read(path).ok().flatten().map(Arc::new);
+        self.cache.insert(path.to_string(), loaded.clone());
+        loaded
+    }
+}
+
+// --- PHÁT ÂM THANH THEO ANIMATION ---
+
+/// Phát âm thanh của action theo sự kiện animation của mô phỏng
+/// (lúc bắt đầu action và mỗi lần animation quay vòng, xem `Simulation::drain_events`).
+pub struct ActionSoundPlayer<B: AudioBackend> {
+    pub backend: B,
+    pub sound_root: String,
+    bank: Option<SoundBank>,
+}
+
+impl<B: AudioBackend> ActionSoundPlayer<B> {
+    pub fn new(backend: B) -> Self {
+        ActionSoundPlayer {
+            backend,
+            sound_root: DEFAULT_SOUND_ROOT.to_string(),
+            bank: None,
+        }
+    }
+
+    pub fn with_bank(mut self, bank: SoundBank) -> Self {
+        self.bank = Some(bank);
+        self
+    }
+
+    /// Đường dẫn game đầy đủ của file wav
+    pub fn resolve_path(&self, wav: &str) -> String {
+        format!("{}\\{}", self.sound_root.trim_end_matches('\\'), wav)
+    }
+
+    /// Báo một sự kiện animation của NPC `npc_id`. `Started` và `Looped` phát âm thanh của
+    /// action ghi trong sự kiện (không phải action hiện tại của NPC: sự kiện được lấy ra sau
+    /// khi xếp hàng), `Finished` thì không. Trả về `true` nếu vừa phát âm thanh.
+    pub fn on_event(&mut self, npc_id: u64, res: &NpcResEntry, event: AnimEvent) -> bool {
+        let (action, state) = match event {
+            AnimEvent::Started(action, state) | AnimEvent::Looped(action, state) => (action, state),
+            AnimEvent::Finished(_) => return false,
+        };
+        let action = if res.is_special() {
+            SoundAction::Special(state)
+        } else {
+            SoundAction::Npc(action)
+        };
+        self.play(npc_id, res, action)
+    }
+
+    /// Phát âm thanh của `action` ngay, `false` nếu action không có âm thanh
+    pub fn play(&mut self, npc_id: u64, res: &NpcResEntry, action: SoundAction) -> bool {
+        let wav = match action {
+            SoundAction::Special(a) => res.action_sound(a),
+            SoundAction::Npc(a) => res.npc_action_sound(a),
+        };
+        let Some(wav) = wav else {
+            return false;
+        };
+
+        let path = self.resolve_path(wav);
+        let data = self.bank.as_mut().and_then(|bank| bank.load(&path));
+        self.backend.play(&SoundEvent {
+            npc_id,
+            action,
+            path,
+            data,
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::npc::{NpcCommand, NpcKind, Sex};
+    use crate::common::sim::Simulation;
+    use crate::common::world::WorldPos;
+
+    fn lady() -> NpcResEntry {
+        serde_json::from_str(
+            r#"{
+                "type": "SpecialNpc",
+                "root_path": "/spr/npcres/woman",
+                "sounds": { "NormalWalk": "sound_m39.wav", "FreeAttack": "sound_m22.wav" }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    // Phát lại mọi sự kiện đã tích luỹ của mô phỏng
+    fn drain(
+        sim: &mut Simulation,
+        res: &NpcResEntry,
+        player: &mut ActionSoundPlayer<RecordingAudio>,
+    ) {
+        for (id, event) in sim.drain_events() {
+            player.on_event(id, res, event);
+        }
+    }
+
+    #[test]
+    fn looping_one_frame_action_plays_every_loop() {
+        let res = lady();
+        let mut sim = Simulation::new();
+        let id = sim.spawn(
+            "lady",
+            NpcKind::Special(Sex::Lady),
+            WorldPos::default(),
+            Arc::new(res.clone()),
+        );
+        let mut player = ActionSoundPlayer::new(RecordingAudio::default());
+
+        // Không có bảng bóng: đi tại chỗ là action lặp 1 frame, quay vòng mỗi tick
+        assert!(sim.command(id, NpcCommand::Walk));
+        for _ in 0..3 {
+            sim.step();
+        }
+        drain(&mut sim, &res, &mut player);
+
+        let paths: Vec<&str> = player
+            .backend
+            .events
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect();
+        // Lúc bắt đầu + 3 lần quay vòng
+        assert_eq!(paths, ["\\sound\\sound_m39.wav"; 4]);
+    }
+
+    #[test]
+    fn action_change_restarts_sound() {
+        let res = lady();
+        let mut sim = Simulation::new();
+        let id = sim.spawn(
+            "lady",
+            NpcKind::Special(Sex::Lady),
+            WorldPos::default(),
+            Arc::new(res.clone()),
+        );
+        let mut player = ActionSoundPlayer::new(RecordingAudio::default());
+
+        sim.command(id, NpcCommand::Walk);
+        sim.command(id, NpcCommand::Attack);
+        drain(&mut sim, &res, &mut player);
+        // Đánh xong thì kết thúc và quay về đứng: đứng không có âm thanh
+        sim.step();
+        drain(&mut sim, &res, &mut player);
+
+        // Hai sự kiện xếp hàng trước khi lấy ra: mỗi cái phát âm thanh của action của nó
+        let events = &player.backend.events;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].path, "\\sound\\sound_m39.wav");
+        assert_eq!(events[1].action, SoundAction::Special(ActionId::FreeAttack));
+        assert_eq!(events[1].path, "\\sound\\sound_m22.wav");
+        assert!(events.iter().all(|e| e.npc_id == id && e.data.is_none()));
+        assert!(!player.play(id, &res, SoundAction::Special(ActionId::SitDown)));
+    }
+}
@@ -0,0 +1,320 @@
+use crate::client::canvas::Bounds;
+use crate::common::world::{CELL_HEIGHT, CELL_WIDTH, REGION_HEIGHT, REGION_WIDTH, WorldPos};
+
+// --- PHÉP CHIẾU ---
+// Bản đồ JX vẽ theo kiểu isometric nhưng lưới logic vuông: ô 32 x 32 điểm logic hiển thị
+// thành 32 x 16 pixel (trục y logic gấp đôi trục y màn hình), giống Map2Screen của client gốc.
+// Hình thoi chỉ nằm trong ảnh nền, nên mọi phép biến đổi ở đây là co giãn tuyến tính.
+
+/// Số điểm logic theo trục y ứng với một pixel màn hình
+pub const LOGIC_Y_PER_PIXEL: f32 = 2.0;
+
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 4.0;
+
+/// Camera 2D: điểm logic `center` nằm giữa viewport, phóng to `zoom` lần
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    // Điểm logic ở giữa viewport
+    pub center: (f32, f32),
+    // Góc trên trái và kích thước viewport trên màn hình (pixel)
+    pub viewport: (f32, f32, f32, f32),
+    pub zoom: f32,
+    // Số lần mỗi giây camera đuổi kịp mục tiêu khi follow (0 = dính luôn vào mục tiêu)
+    pub follow_rate: f32,
+    // Giới hạn tâm camera trong khung logic (trái, trên, phải, dưới), thường là khung bản đồ
+    pub bounds: Option<(f32, f32, f32, f32)>,
+}
+
+impl Camera {
+    pub fn new(width: f32, height: f32) -> Self {
+        Camera {
+            center: (0.0, 0.0),
+            viewport: (0.0, 0.0, width, height),
+            zoom: 1.0,
+            follow_rate: 8.0,
+            bounds: None,
+        }
+    }
+
+    pub fn set_viewport(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.viewport = (x, y, width, height);
+    }
+
+    /// Giới hạn camera trong các region `rect` (trái, trên, phải, dưới) như trong file .wor
+    pub fn set_region_bounds(&mut self, rect: (i32, i32, i32, i32)) {
+        self.bounds = Some((
+            (rect.0 * REGION_WIDTH) as f32,
+            (rect.1 * REGION_HEIGHT) as f32,
+            ((rect.2 + 1) * REGION_WIDTH) as f32,
+            ((rect.3 + 1) * REGION_HEIGHT) as f32,
+        ));
+        self.clamp();
+    }
+
+    // --- BIẾN ĐỔI TOẠ ĐỘ ---
+
+    /// Điểm logic -> pixel màn hình
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        let (vx, vy, vw, vh) = self.viewport;
+        (
+            vx + vw / 2.0 + (x - self.center.0) * self.zoom,
+            vy + vh / 2.0 + (y - self.center.1) / LOGIC_Y_PER_PIXEL * self.zoom,
+        )
+    }
+
+    /// Pixel màn hình -> điểm logic
+    pub fn screen_to_world(&self, sx: f32, sy: f32) -> (f32, f32) {
+        let (vx, vy, vw, vh) = self.viewport;
+        (
+            self.center.0 + (sx - vx - vw / 2.0) / self.zoom,
+            self.center.1 + (sy - vy - vh / 2.0) / self.zoom * LOGIC_Y_PER_PIXEL,
+        )
+    }
+
+    pub fn pos_to_screen(&self, pos: WorldPos) -> (f32, f32) {
+        self.world_to_screen(pos.x as f32, pos.y as f32)
+    }
+
+    pub fn screen_to_pos(&self, world: u32, sx: f32, sy: f32) -> WorldPos {
+        let (x, y) = self.screen_to_world(sx, sy);
+        WorldPos::new(world, x.floor() as i32, y.floor() as i32)
+    }
+
+    /// Khung logic (trái, trên, phải, dưới) đang thấy trong viewport
+    pub fn visible_rect(&self) -> (f32, f32, f32, f32) {
+        let (vx, vy, vw, vh) = self.viewport;
+        let (left, top) = self.screen_to_world(vx, vy);
+        let (right, bottom) = self.screen_to_world(vx + vw, vy + vh);
+        (left, top, right, bottom)
+    }
+
+    /// Các region giao với viewport (để tải / vẽ)
+    pub fn visible_regions(&self) -> Vec<(i32, i32)> {
+        let (left, top, right, bottom) = self.visible_rect();
+        let x0 = (left.floor() as i32).div_euclid(REGION_WIDTH);
+        let y0 = (top.floor() as i32).div_euclid(REGION_HEIGHT);
+        let x1 = (right.ceil() as i32 - 1).div_euclid(REGION_WIDTH);
+        let y1 = (bottom.ceil() as i32 - 1).div_euclid(REGION_HEIGHT);
+        (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+            .collect()
+    }
+
+    /// Góc trên trái viewport tính bằng điểm logic, dùng cho `Scene::render` khi zoom = 1
+    pub fn view_origin(&self) -> (i32, i32) {
+        let (left, top, _, _) = self.visible_rect();
+        (left.round() as i32, top.round() as i32)
+    }
+
+    // --- DI CHUYỂN ---
+
+    pub fn look_at(&mut self, pos: WorldPos) {
+        self.center = (pos.x as f32, pos.y as f32);
+        self.clamp();
+    }
+
+    /// Đuổi theo `target` sau `dt` giây. Tốc độ giảm dần khi gần tới (làm mượt kiểu mũ)
+    /// và nhảy thẳng tới nếu mục tiêu đã ra khỏi viewport (dịch chuyển, đổi bản đồ...).
+    pub fn follow(&mut self, target: WorldPos, dt: f32) {
+        let (tx, ty) = (target.x as f32, target.y as f32);
+        let (sx, sy) = self.world_to_screen(tx, ty);
+        let (vx, vy, vw, vh) = self.viewport;
+        let outside = sx < vx || sy < vy || sx > vx + vw || sy > vy + vh;
+        if self.follow_rate <= 0.0 || outside {
+            self.look_at(target);
+            return;
+        }
+        let t = 1.0 - (-self.follow_rate * dt.max(0.0)).exp();
+        self.center.0 += (tx - self.center.0) * t;
+        self.center.1 += (ty - self.center.1) * t;
+        self.clamp();
+    }
+
+    /// Cuộn theo pixel màn hình (kéo chuột, phím mũi tên)
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.center.0 += dx / self.zoom;
+        self.center.1 += dy / self.zoom * LOGIC_Y_PER_PIXEL;
+        self.clamp();
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.clamp();
+    }
+
+    /// Đổi zoom theo hệ số `factor` mà giữ nguyên điểm dưới con trỏ (sx, sy)
+    pub fn zoom_at(&mut self, factor: f32, sx: f32, sy: f32) {
+        let before = self.screen_to_world(sx, sy);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let after = self.screen_to_world(sx, sy);
+        self.center.0 += before.0 - after.0;
+        self.center.1 += before.1 - after.1;
+        self.clamp();
+    }
+
+    /// Giữ viewport trong `bounds`; bản đồ nhỏ hơn viewport thì đặt tâm ở giữa
+    fn clamp(&mut self) {
+        let Some((left, top, right, bottom)) = self.bounds else {
+            return;
+        };
+        let half_w = self.viewport.2 / 2.0 / self.zoom;
+        let half_h = self.viewport.3 / 2.0 / self.zoom * LOGIC_Y_PER_PIXEL;
+        let clamp_axis = |v: f32, lo: f32, hi: f32, half: f32| {
+            if hi - lo <= half * 2.0 {
+                (lo + hi) / 2.0
+            } else {
+                v.clamp(lo + half, hi - half)
+            }
+        };
+        self.center.0 = clamp_axis(self.center.0, left, right, half_w);
+        self.center.1 = clamp_axis(self.center.1, top, bottom, half_h);
+    }
+
+    // --- CHỌN ĐỐI TƯỢNG ---
+
+    /// Ô dưới pixel (sx, sy): (region, ô trong region)
+    pub fn pick_cell(&self, sx: f32, sy: f32) -> ((i32, i32), (i32, i32)) {
+        let pos = self.screen_to_pos(0, sx, sy);
+        (pos.region(), pos.cell())
+    }
+
+    /// Khung ô `cell` của region `region` trên màn hình (x, y, rộng, cao)
+    pub fn cell_rect(&self, region: (i32, i32), cell: (i32, i32)) -> (f32, f32, f32, f32) {
+        let x = region.0 * REGION_WIDTH + cell.0 * CELL_WIDTH;
+        let y = region.1 * REGION_HEIGHT + cell.1 * CELL_HEIGHT;
+        let (sx, sy) = self.world_to_screen(x as f32, y as f32);
+        (
+            sx,
+            sy,
+            CELL_WIDTH as f32 * self.zoom,
+            CELL_HEIGHT as f32 / LOGIC_Y_PER_PIXEL * self.zoom,
+        )
+    }
+
+    /// Đối tượng dưới pixel (sx, sy). `targets` gồm (id, chân, khung hình so với chân lúc zoom 1)
+    /// như `Bounds::of_commands`; chồng nhau thì lấy cái vẽ sau cùng (chân thấp nhất trên màn hình).
+    pub fn pick<I>(&self, sx: f32, sy: f32, targets: I) -> Option<u32>
+    where
+        I: IntoIterator<Item = (u32, WorldPos, Bounds)>,
+    {
+        targets
+            .into_iter()
+            .filter(|(_, foot, b)| {
+                let (fx, fy) = self.pos_to_screen(*foot);
+                let x = (sx - fx) / self.zoom;
+                let y = (sy - fy) / self.zoom;
+                x >= b.min_x as f32
+                    && x < b.max_x as f32
+                    && y >= b.min_y as f32
+                    && y < b.max_y as f32
+            })
+            .max_by_key(|(_, foot, _)| (foot.y, foot.x))
+            .map(|(id, _, _)| id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.look_at(WorldPos::new(1, 1000, 2000));
+        camera
+    }
+
+    fn approx(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3
+    }
+
+    #[test]
+    fn transforms_round_trip() {
+        let mut camera = camera();
+        assert_eq!(camera.world_to_screen(1000.0, 2000.0), (400.0, 300.0));
+        // Một ô 32 x 32 logic là 32 x 16 pixel
+        assert_eq!(camera.world_to_screen(1032.0, 2032.0), (432.0, 316.0));
+        assert_eq!(camera.screen_to_world(0.0, 0.0), (600.0, 1400.0));
+
+        camera.set_zoom(2.0);
+        camera.set_viewport(100.0, 50.0, 800.0, 600.0);
+        assert_eq!(camera.world_to_screen(1032.0, 2032.0), (564.0, 382.0));
+        for (x, y) in [(0.0, 0.0), (1234.5, 987.25), (-40.0, 3000.0)] {
+            let (sx, sy) = camera.world_to_screen(x, y);
+            assert!(approx(camera.screen_to_world(sx, sy), (x, y)));
+        }
+
+        // Zoom 1: khớp với phép chiếu của Scene
+        let camera = self::camera();
+        assert_eq!(camera.view_origin(), (600, 1400));
+        assert_eq!(camera.visible_rect(), (600.0, 1400.0, 1400.0, 2600.0));
+        assert_eq!(
+            camera.visible_regions(),
+            vec![(1, 1), (2, 1), (1, 2), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn zoom_keeps_cursor_point() {
+        let mut camera = camera();
+        let before = camera.screen_to_world(700.0, 100.0);
+        camera.zoom_at(2.0, 700.0, 100.0);
+        assert_eq!(camera.zoom, 2.0);
+        assert!(approx(camera.screen_to_world(700.0, 100.0), before));
+
+        camera.zoom_at(100.0, 700.0, 100.0);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+        assert!(approx(camera.screen_to_world(700.0, 100.0), before));
+    }
+
+    #[test]
+    fn follow_and_bounds() {
+        let mut camera = camera();
+        let target = WorldPos::new(1, 1100, 2000);
+        camera.follow(target, 0.1);
+        let moved = camera.center.0 - 1000.0;
+        let expected = 100.0 * (1.0 - (-0.8f32).exp());
+        assert!((moved - expected).abs() < 1e-3);
+        for _ in 0..100 {
+            camera.follow(target, 0.1);
+        }
+        assert!(approx(camera.center, (1100.0, 2000.0)));
+
+        // Mục tiêu ngoài màn hình: nhảy thẳng tới
+        camera.follow(WorldPos::new(1, 9000, 9000), 0.01);
+        assert_eq!(camera.center, (9000.0, 9000.0));
+
+        // Bản đồ 2 x 1 region: viewport 800 x 600 giữ trong 1024 x 1024 logic
+        camera.set_region_bounds((0, 0, 1, 0));
+        assert_eq!(camera.center, (624.0, 512.0));
+        camera.look_at(WorldPos::new(1, 0, 0));
+        assert_eq!(camera.center, (400.0, 512.0));
+    }
+
+    #[test]
+    fn picking_cells_and_targets() {
+        let camera = camera();
+        // Tâm màn hình là điểm (1000, 2000): region (1, 1), ô (15, 30)
+        assert_eq!(camera.pick_cell(400.0, 300.0), ((1, 1), (15, 30)));
+        assert_eq!(camera.pick_cell(400.0 + 24.0, 300.0), ((2, 1), (0, 30)));
+        assert_eq!(
+            camera.cell_rect((2, 1), (0, 30)),
+            (424.0, 292.0, 32.0, 16.0)
+        );
+
+        let body = Bounds {
+            min_x: -20,
+            min_y: -80,
+            max_x: 20,
+            max_y: 0,
+        };
+        let targets = [
+            (1, WorldPos::new(1, 1000, 2000), body),
+            (2, WorldPos::new(1, 1010, 2040), body),
+        ];
+        assert_eq!(camera.pick(382.0, 290.0, targets), Some(1));
+        // Vùng chồng nhau: NPC đứng trước (chân thấp hơn) được chọn
+        assert_eq!(camera.pick(405.0, 295.0, targets), Some(2));
+        assert_eq!(camera.pick(400.0, 330.0, targets), None);
+    }
+}
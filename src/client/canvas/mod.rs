@@ -0,0 +1,419 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageError, Rgba, RgbaImage};
+
+use crate::client::compose::{CharacterComposer, DrawCommand, EquippedParts, SpriteSource};
+use crate::common::npc::ActionId;
+use crate::common::world::GAME_FPS;
+
+// --- CPU COMPOSITOR (KHÔNG CẦN GPU) ---
+
+/// Khung chữ nhật tính theo toạ độ so với chân nhân vật
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl Bounds {
+    pub fn width(&self) -> u32 {
+        (self.max_x - self.min_x).max(0) as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        (self.max_y - self.min_y).max(0) as u32
+    }
+
+    pub fn union(self, other: Bounds) -> Bounds {
+        Bounds {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    /// Khung bao của tất cả lệnh vẽ, `None` nếu không có lệnh nào
+    pub fn of_commands(commands: &[DrawCommand]) -> Option<Bounds> {
+        commands
+            .iter()
+            .map(|cmd| {
+                let info = &cmd.sprite.frames[cmd.frame];
+                Bounds {
+                    min_x: cmd.x,
+                    min_y: cmd.y,
+                    max_x: cmd.x + info.width as i32,
+                    max_y: cmd.y + info.height as i32,
+                }
+            })
+            .reduce(Bounds::union)
+    }
+}
+
+/// Vẽ các lệnh lên `canvas`, chân nhân vật nằm tại (origin_x, origin_y).
+/// Alpha blend kiểu source-over, pixel ngoài canvas bị bỏ qua.
+pub fn draw_commands<L>(
+    canvas: &mut RgbaImage,
+    commands: &[DrawCommand<L>],
+    origin_x: i32,
+    origin_y: i32,
+) {
+    for cmd in commands {
+        draw_command(canvas, cmd, origin_x, origin_y, cmd.opacity);
+    }
+}
+
+/// Vẽ một lệnh với hệ số mờ `opacity` (0.0 - 1.0)
+pub fn draw_command<L>(
+    canvas: &mut RgbaImage,
+    cmd: &DrawCommand<L>,
+    origin_x: i32,
+    origin_y: i32,
+    opacity: f32,
+) {
+    let Some(rgba) = cmd.sprite.frame_rgba(cmd.frame) else {
+        return;
+    };
+    let info = &cmd.sprite.frames[cmd.frame];
+    let (cw, ch) = (canvas.width() as i32, canvas.height() as i32);
+
+    for y in 0..info.height as i32 {
+        let dy = origin_y + cmd.y + y;
+        if dy < 0 || dy >= ch {
+            continue;
+        }
+        for x in 0..info.width as i32 {
+            let dx = origin_x + cmd.x + x;
+            if dx < 0 || dx >= cw {
+                continue;
+            }
+            let i = ((y * info.width as i32 + x) * 4) as usize;
+            let src_a = rgba[i + 3] as f32 / 255.0 * opacity;
+            if src_a <= 0.0 {
+                continue;
+            }
+            let dst = canvas.get_pixel_mut(dx as u32, dy as u32);
+            *dst = blend(*dst, [rgba[i], rgba[i + 1], rgba[i + 2]], src_a);
+        }
+    }
+}
+
+fn blend(dst: Rgba<u8>, src: [u8; 3], src_a: f32) -> Rgba<u8> {
+    let dst_a = dst[3] as f32 / 255.0;
+    let out_a = src_a + dst_a * (1.0 - src_a);
+    if out_a <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let channel = |s: u8, d: u8| {
+        ((s as f32 * src_a + d as f32 * dst_a * (1.0 - src_a)) / out_a).round() as u8
+    };
+    Rgba([
+        channel(src[0], dst[0]),
+        channel(src[1], dst[1]),
+        channel(src[2], dst[2]),
+        (out_a * 255.0).round() as u8,
+    ])
+}
+
+// --- ANIMATION ---
+
+/// Animation đã ghép của một hướng: mọi frame cùng kích thước, chân nhân vật tại `origin`
+pub struct RenderedAnimation {
+    pub frames: Vec<RgbaImage>,
+    pub origin: (i32, i32),
+    // Thời gian mỗi frame (ms), theo interval của layer body (đơn vị 1/18 giây)
+    pub frame_ms: u32,
+    // Tổng số lệnh vẽ của mọi frame, 0 là không vẽ được gì (thiếu spr, sai bộ phận...)
+    pub drawn: usize,
+}
+
+/// Số frame mỗi hướng và thời gian mỗi frame (ms) của action, lấy theo layer body
+pub fn animation_timing(
+    composer: &CharacterComposer,
+    parts: &EquippedParts,
+    action: ActionId,
+    sprites: &mut dyn SpriteSource,
+) -> (usize, u32) {
+    match composer.body_sprite(parts, action, sprites) {
+        Some(body) => {
+            let interval = body.header.interval.max(1) as u32;
+            (
+                body.frames_per_direction().max(1),
+                interval * 1000 / GAME_FPS,
+            )
+        }
+        None => (1, 1000 / GAME_FPS),
+    }
+}
+
+/// Ghép toàn bộ frame của `action` ở hướng `dir`.
+/// Canvas lấy khung bao chung của mọi frame để animation không bị rung.
+pub fn render_animation(
+    composer: &CharacterComposer,
+    parts: &EquippedParts,
+    action: ActionId,
+    dir: u32,
+    sprites: &mut dyn SpriteSource,
+) -> RenderedAnimation {
+    let (frame_count, frame_ms) = animation_timing(composer, parts, action, sprites);
+
+    let commands: Vec<Vec<DrawCommand>> = (0..frame_count)
+        .map(|f| composer.compose(parts, action, dir, f, sprites))
+        .collect();
+
+    let bounds = commands
+        .iter()
+        .filter_map(|c| Bounds::of_commands(c))
+        .reduce(Bounds::union)
+        .unwrap_or(Bounds {
+            min_x: 0,
+            min_y: 0,
+            max_x: 1,
+            max_y: 1,
+        });
+
+    let origin = (-bounds.min_x, -bounds.min_y);
+    let frames = commands
+        .iter()
+        .map(|c| {
+            let mut canvas = RgbaImage::new(bounds.width().max(1), bounds.height().max(1));
+            draw_commands(&mut canvas, c, origin.0, origin.1);
+            canvas
+        })
+        .collect();
+
+    RenderedAnimation {
+        frames,
+        origin,
+        frame_ms,
+        drawn: commands.iter().map(|c| c.len()).sum(),
+    }
+}
+
+impl RenderedAnimation {
+    /// Không frame nào có lệnh vẽ (ảnh chỉ là canvas trống 1x1)
+    pub fn is_empty(&self) -> bool {
+        self.drawn == 0
+    }
+
+    /// Lưu từng frame thành `<prefix>_f000.png`, `<prefix>_f001.png`...
+    pub fn save_png_frames<P: AsRef<Path>>(&self, dir: P, prefix: &str) -> Result<(), ImageError> {
+        fs::create_dir_all(&dir)?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            frame.save(dir.as_ref().join(format!("{}_f{:03}.png", prefix, i)))?;
+        }
+        Ok(())
+    }
+
+    /// Lưu thành GIF động lặp vô hạn
+    pub fn save_gif<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = GifEncoder::new(writer);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = Delay::from_numer_denom_ms(self.frame_ms, 1);
+        encoder.encode_frames(
+            self.frames
+                .iter()
+                .map(|f| Frame::from_parts(f.clone(), 0, 0, delay)),
+        )
+    }
+}
+
+/// Ghép nhiều animation (mỗi hướng một hàng) thành sprite sheet.
+/// Mọi ô có cùng kích thước và chân nhân vật nằm cùng một vị trí trong ô.
+pub fn sprite_sheet(rows: &[RenderedAnimation]) -> io::Result<RgbaImage> {
+    if rows.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Không có animation nào",
+        ));
+    }
+
+    // Căn chân nhân vật của mọi hàng về cùng một điểm
+    let origin_x = rows.iter().map(|r| r.origin.0).max().unwrap_or(0);
+    let origin_y = rows.iter().map(|r| r.origin.1).max().unwrap_or(0);
+    let cell_w = rows
+        .iter()
+        .flat_map(|r| r.frames.first().map(|f| f.width() as i32 - r.origin.0))
+        .max()
+        .unwrap_or(1)
+        + origin_x;
+    let cell_h = rows
+        .iter()
+        .flat_map(|r| r.frames.first().map(|f| f.height() as i32 - r.origin.1))
+        .max()
+        .unwrap_or(1)
+        + origin_y;
+    let cols = rows.iter().map(|r| r.frames.len()).max().unwrap_or(1);
+
+    let mut sheet = RgbaImage::new(
+        cell_w as u32 * cols as u32,
+        cell_h as u32 * rows.len() as u32,
+    );
+    for (row, anim) in rows.iter().enumerate() {
+        for (col, frame) in anim.frames.iter().enumerate() {
+            let x = col as i64 * cell_w as i64 + (origin_x - anim.origin.0) as i64;
+            let y = row as i64 * cell_h as i64 + (origin_y - anim.origin.1) as i64;
+            image::imageops::overlay(&mut sheet, frame, x, y);
+        }
+    }
+    Ok(sheet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::compose::DirSpriteSource;
+    use crate::client::spr::SprFile;
+    use crate::common::npc::PartSlot;
+    use crate::common::npcres::NpcResDb;
+    use std::path::PathBuf;
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+
+    // Thư mục spr tạm cho DirSpriteSource:
+    // body.spr 2 hướng x 2 frame 2x2 đỏ, center (1, 2), interval 3
+    // head.spr 1 hướng x 2 frame 2x1 xanh alpha 128, center (0, 2)
+    fn sprite_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jx_canvas_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = |w, h, [r, g, b]: [u8; 3], a| RgbaImage::from_pixel(w, h, Rgba([r, g, b, a]));
+        let body: Vec<_> = (0..4).map(|_| (image(2, 2, RED, 255), 0, 0)).collect();
+        let head: Vec<_> = (0..2).map(|_| (image(2, 1, BLUE, 128), 0, 0)).collect();
+        let body = SprFile::from_images(&body, (1, 2), 2, 3).unwrap();
+        let head = SprFile::from_images(&head, (0, 2), 1, 1).unwrap();
+        fs::write(dir.join("body.spr"), body.to_bytes()).unwrap();
+        fs::write(dir.join("head.spr"), head.to_bytes()).unwrap();
+        dir
+    }
+
+    fn composer() -> CharacterComposer {
+        let json = r#"{"schema_version": 2, "characters": {"MainMan": {
+            "type": "SpecialNpc", "root_path": "/spr/npcres/man",
+            "components": {
+                "body": {"Thân thể 01": {"FreeStand1": "/body.spr"}},
+                "head": {"Đầu 01": {"FreeStand1": "/head.spr"}}},
+            "render_order": {"DEFAULT": {"Dir1": "-1,1,0,"}}
+        }}}"#;
+        let db = NpcResDb::from_reader(json.as_bytes()).unwrap();
+        CharacterComposer::new(db.get("MainMan").unwrap().clone())
+    }
+
+    fn parts() -> EquippedParts {
+        [
+            (PartSlot::Body, "Thân thể 01".to_string()),
+            (PartSlot::Head, "Đầu 01".to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn rgba(pixel: &Rgba<u8>) -> [u8; 4] {
+        pixel.0
+    }
+
+    #[test]
+    fn draws_layers_with_source_over_blend() {
+        let dir = sprite_dir("draw");
+        let mut sprites = DirSpriteSource::new(&dir);
+        let cmds = composer().compose(&parts(), ActionId::FreeStand1, 0, 0, &mut sprites);
+        assert_eq!(cmds.len(), 2);
+
+        // chân tại (2, 3): body phủ x 1..3, y 1..3; head phủ x 2..4, y 1
+        let mut canvas = RgbaImage::new(4, 4);
+        draw_commands(&mut canvas, &cmds, 2, 3);
+        assert_eq!(rgba(canvas.get_pixel(0, 0)), [0, 0, 0, 0]);
+        assert_eq!(rgba(canvas.get_pixel(1, 2)), [255, 0, 0, 255]);
+        // xanh alpha 128 đè lên đỏ đặc
+        assert_eq!(rgba(canvas.get_pixel(2, 1)), [127, 0, 128, 255]);
+        // xanh alpha 128 trên nền trong suốt giữ nguyên màu
+        assert_eq!(rgba(canvas.get_pixel(3, 1)), [0, 0, 255, 128]);
+        assert_eq!(rgba(canvas.get_pixel(3, 2)), [0, 0, 0, 0]);
+
+        // opacity nhân vào alpha của spr
+        let mut faded = RgbaImage::new(4, 4);
+        draw_command(&mut faded, &cmds[0], 2, 3, 0.5);
+        assert_eq!(rgba(faded.get_pixel(1, 1)), [255, 0, 0, 128]);
+
+        // ngoài canvas thì bỏ qua
+        let mut clipped = RgbaImage::new(4, 4);
+        draw_commands(&mut clipped, &cmds, -10, 20);
+        assert!(clipped.pixels().all(|p| p.0 == [0, 0, 0, 0]));
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn blend_is_source_over() {
+        let red = Rgba([255, 0, 0, 255]);
+        assert_eq!(blend(red, BLUE, 0.0), red);
+        assert_eq!(blend(red, BLUE, 1.0), Rgba([0, 0, 255, 255]));
+        assert_eq!(blend(Rgba([0, 0, 0, 0]), BLUE, 0.5), Rgba([0, 0, 255, 128]));
+        assert_eq!(blend(Rgba([0, 0, 0, 0]), BLUE, 0.0), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn timing_follows_body_layer() {
+        let dir = sprite_dir("timing");
+        let mut sprites = DirSpriteSource::new(&dir);
+        let composer = composer();
+        assert_eq!(
+            animation_timing(&composer, &parts(), ActionId::FreeStand1, &mut sprites),
+            (2, 3 * 1000 / GAME_FPS)
+        );
+        // không có body: một frame, một tick
+        let head_only: EquippedParts = parts()
+            .into_iter()
+            .filter(|(slot, _)| *slot != PartSlot::Body)
+            .collect();
+        assert_eq!(
+            animation_timing(&composer, &head_only, ActionId::FreeStand1, &mut sprites),
+            (1, 1000 / GAME_FPS)
+        );
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn animation_and_sheet_share_origin() {
+        let dir = sprite_dir("sheet");
+        let mut sprites = DirSpriteSource::new(&dir);
+        let composer = composer();
+        let rows: Vec<RenderedAnimation> = (0..2)
+            .map(|d| render_animation(&composer, &parts(), ActionId::FreeStand1, d, &mut sprites))
+            .collect();
+
+        let anim = &rows[0];
+        assert_eq!(anim.frames.len(), 2);
+        assert_eq!(anim.drawn, 4);
+        assert!(!anim.is_empty());
+        // khung bao x -1..2, y -2..0: chân nằm tại (1, 2) của ảnh 3x2
+        assert_eq!(anim.origin, (1, 2));
+        assert_eq!(anim.frames[0].dimensions(), (3, 2));
+        assert_eq!(rgba(anim.frames[0].get_pixel(0, 1)), [255, 0, 0, 255]);
+
+        // 2 hàng (hướng) x 2 cột (frame), mỗi ô 3x2
+        let sheet = sprite_sheet(&rows).unwrap();
+        assert_eq!(sheet.dimensions(), (6, 4));
+        assert_eq!(sheet.get_pixel(3, 3), anim.frames[0].get_pixel(0, 1));
+        assert!(sprite_sheet(&[]).is_err());
+
+        // thiếu spr: không vẽ được gì
+        let missing = render_animation(
+            &composer,
+            &parts(),
+            ActionId::FreeStand1,
+            0,
+            &mut DirSpriteSource::new(dir.join("none")),
+        );
+        assert!(missing.is_empty());
+        fs::remove_dir_all(dir).ok();
+    }
+}
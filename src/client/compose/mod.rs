@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::client::spr::SprFile;
+use crate::common::npc::{ActionId, NpcAction, PartSlot};
+use crate::common::npcres::{AttachPoint, NpcResEntry, RenderOrder};
+use crate::common::pak::PakSet;
+
+// --- NGUỒN SPRITE ---
+
+/// Nơi composer lấy SPR theo đường dẫn game (pak, thư mục giải nén, cache...)
+pub trait SpriteSource {
+    fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>>;
+}
+
+/// Đọc SPR từ các file PAK, cache lại cả kết quả lỗi để không đọc lại nhiều lần
+pub struct PakSpriteSource {
+    pub paks: PakSet,
+    cache: HashMap<String, Option<Arc<SprFile>>>,
+}
+
+impl PakSpriteSource {
+    pub fn new(paks: PakSet) -> Self {
+        PakSpriteSource {
+            paks,
+            cache: HashMap::new(),
+        }
+    }
+}
+
+impl SpriteSource for PakSpriteSource {
+    fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>> {
+        if let Some(cached) = self.cache.get(path) {
+            return cached.clone();
+        }
+        let loaded = match self.paks.read(path) {
+            Ok(Some(data)) => SprFile::from_bytes(&data).ok().map(Arc::new),
+            _ => None,
+        };
+        self.cache.insert(path.to_string(), loaded.clone());
+        loaded
+    }
+}
+
+/// Đọc SPR từ thư mục đã giải nén (vd "extracted/"), dùng cho CI không có file PAK
+pub struct DirSpriteSource {
+    pub root: PathBuf,
+    cache: HashMap<String, Option<Arc<SprFile>>>,
+}
+
+impl DirSpriteSource {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirSpriteSource {
+            root: root.into(),
+            cache: HashMap::new(),
+        }
+    }
+}
+
+impl SpriteSource for DirSpriteSource {
+    fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>> {
+        if let Some(cached) = self.cache.get(path) {
+            return cached.clone();
+        }
+        let clean = path.replace('\\', "/");
+        let loaded = SprFile::load(self.root.join(clean.trim_start_matches('/')))
+            .ok()
+            .map(Arc::new);
+        self.cache.insert(path.to_string(), loaded.clone());
+        loaded
+    }
+}
+
+// --- COMPOSER ---
+
+/// Một lệnh vẽ: frame `frame` (index tuyệt đối trong `sprite.frames`) đặt tại (x, y)
+/// tính từ điểm chân nhân vật (đã trừ anchor center và cộng offset của frame).
+/// `L` là loại layer: `Layer` cho nhân vật, `SceneLayer` trong `client::scene`.
+#[derive(Debug, Clone)]
+pub struct DrawCommand<L = Layer> {
+    pub layer: L,
+    pub spr_path: String,
+    pub sprite: Arc<SprFile>,
+    pub frame: usize,
+    pub x: i32,
+    pub y: i32,
+    // Độ mờ khi vẽ (1.0 = vẽ nguyên alpha của spr)
+    pub opacity: f32,
+}
+
+/// Loại layer của một lệnh vẽ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    // Bóng dưới chân, luôn vẽ đầu tiên
+    Shadow,
+    Part(PartSlot),
+    // Hiệu ứng trạng thái (choáng, trúng độc...) gắn tại vị trí tương ứng
+    Status(AttachPoint),
+}
+
+impl Layer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::Shadow => "Shadow",
+            Layer::Part(slot) => slot.column_name(),
+            Layer::Status(_) => "Status",
+        }
+    }
+}
+
+impl<L> DrawCommand<L> {
+    /// Cùng lệnh vẽ nhưng đổi loại layer (vd đưa lệnh của nhân vật vào `Scene`)
+    pub fn map_layer<M>(self, f: impl FnOnce(L) -> M) -> DrawCommand<M> {
+        DrawCommand {
+            layer: f(self.layer),
+            spr_path: self.spr_path,
+            sprite: self.sprite,
+            frame: self.frame,
+            x: self.x,
+            y: self.y,
+            opacity: self.opacity,
+        }
+    }
+}
+
+/// Bộ phận đang mặc: slot -> tên item trong bảng bộ phận (vd "Thân thể 01")
+pub type EquippedParts = BTreeMap<PartSlot, String>;
+
+/// Ghép các layer của nhân vật chính (MainMan/MainLady) theo `贴图顺序表.txt`
+pub struct CharacterComposer {
+    pub res: NpcResEntry,
+    pub order: RenderOrder,
+    // Độ mờ của bóng, `None` để tắt bóng
+    pub shadow_opacity: Option<f32>,
+}
+
+impl CharacterComposer {
+    pub fn new(res: NpcResEntry) -> Self {
+        let order = RenderOrder::from_sections(&res.render_order);
+        CharacterComposer {
+            res,
+            order,
+            shadow_opacity: Some(0.5),
+        }
+    }
+
+    /// Đường dẫn spr của bộ phận `slot` theo đồ đang mặc
+    pub fn part_path(
+        &self,
+        parts: &EquippedParts,
+        slot: PartSlot,
+        action: ActionId,
+    ) -> Option<&str> {
+        let item = parts.get(&slot)?;
+        self.res.part_spr_path(slot, item, action)
+    }
+
+    /// Layer body làm chuẩn cho số frame, interval và các dòng LineN
+    pub fn body_sprite(
+        &self,
+        parts: &EquippedParts,
+        action: ActionId,
+        sprites: &mut dyn SpriteSource,
+    ) -> Option<Arc<SprFile>> {
+        let path = self.part_path(parts, PartSlot::Body, action)?;
+        sprites.sprite(path)
+    }
+
+    /// Danh sách lệnh vẽ (dưới -> trên) của `action` ở hướng `dir` (0-based, theo
+    /// số hướng của spr), frame thứ `frame` của animation (quay vòng theo từng layer).
+    pub fn compose(
+        &self,
+        parts: &EquippedParts,
+        action: ActionId,
+        dir: u32,
+        frame: usize,
+        sprites: &mut dyn SpriteSource,
+    ) -> Vec<DrawCommand> {
+        let body = self.body_sprite(parts, action, sprites);
+        let body_abs_frame = body
+            .as_ref()
+            .and_then(|spr| spr.frame_index(dir as usize, frame))
+            .map(|i| i as u32)
+            .unwrap_or(u32::MAX);
+        // Bảng thứ tự vẽ có thể nhiều hướng hơn spr, quy đổi theo số hướng của body
+        let sprite_dirs = body
+            .map(|spr| spr.header.directions as u32)
+            .unwrap_or_else(|| self.order.table_dirs());
+
+        let mut commands = Vec::new();
+
+        // Bóng nằm dưới mọi bộ phận, dùng chung frame với body
+        if let Some(opacity) = self.shadow_opacity
+            && let Some(shadow) = self.res.shadow(action)
+            && let Some(cmd) = layer_command(Layer::Shadow, &shadow.spr, dir, frame, sprites)
+        {
+            commands.push(DrawCommand { opacity, ..cmd });
+        }
+
+        for &slot in self.order.layers(action, dir, sprite_dirs, body_abs_frame) {
+            let Some(path) = self.part_path(parts, slot, action) else {
+                continue;
+            };
+            if let Some(cmd) = layer_command(Layer::Part(slot), path, dir, frame, sprites) {
+                commands.push(cmd);
+            }
+        }
+        commands
+    }
+}
+
+/// Lệnh vẽ của NPC thường: mỗi action chỉ có một spr (layer Body)
+pub fn compose_npc(
+    res: &NpcResEntry,
+    action: NpcAction,
+    dir: u32,
+    frame: usize,
+    sprites: &mut dyn SpriteSource,
+) -> Vec<DrawCommand> {
+    res.npc_action_spr(action)
+        .and_then(|info| layer_command(Layer::Part(PartSlot::Body), &info.spr, dir, frame, sprites))
+        .into_iter()
+        .collect()
+}
+
+/// Lệnh vẽ frame `frame` ở hướng `dir` của spr `path`
+pub fn layer_command<L>(
+    layer: L,
+    path: &str,
+    dir: u32,
+    frame: usize,
+    sprites: &mut dyn SpriteSource,
+) -> Option<DrawCommand<L>> {
+    let sprite = sprites.sprite(path)?;
+    let index = sprite.frame_index(dir as usize, frame)?;
+
+    let info = &sprite.frames[index];
+    // Công thức vẽ chuẩn: Chân - Anchor + Offset
+    let x = info.offset_x as i32 - sprite.header.center_x as i32;
+    let y = info.offset_y as i32 - sprite.header.center_y as i32;
+
+    Some(DrawCommand {
+        layer,
+        spr_path: path.to_string(),
+        sprite,
+        frame: index,
+        x,
+        y,
+        opacity: 1.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::npcres::NpcResDb;
+    use image::{Rgba, RgbaImage};
+
+    // Nguồn spr trong bộ nhớ: đường dẫn -> spr
+    struct MapSource(HashMap<String, Arc<SprFile>>);
+
+    impl SpriteSource for MapSource {
+        fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>> {
+            self.0.get(path).cloned()
+        }
+    }
+
+    // Spr 8 hướng, mỗi hướng 2 frame 1x1
+    fn sprite() -> Arc<SprFile> {
+        let images: Vec<_> = (0..16)
+            .map(|i| (RgbaImage::from_pixel(1, 1, Rgba([i, 0, 0, 255])), 0, 0))
+            .collect();
+        Arc::new(SprFile::from_images(&images, (0, 0), 8, 1).unwrap())
+    }
+
+    fn composer() -> (CharacterComposer, MapSource) {
+        let mut dirs = String::new();
+        for n in 1..=16 {
+            // Dir3 (hướng 1 của spr 8 hướng): Head dưới Body
+            let ids = if n == 3 { "-1,0,1," } else { "-1,1,0," };
+            dirs.push_str(&format!(r#""Dir{}": "{}","#, n, ids));
+        }
+        let json = format!(
+            r#"{{"schema_version": 2, "characters": {{"MainMan": {{
+                "type": "SpecialNpc", "root_path": "/spr/npcres/man",
+                "components": {{
+                    "body": {{"Thân thể 01": {{"FreeStand1": "body.spr"}}}},
+                    "head": {{"Đầu 01": {{"FreeStand1": "head.spr"}}}}}},
+                "shadows": {{"FreeStand1": {{"spr": "shadow.spr",
+                    "frames": 16, "directions": 8, "interval": 1}}}},
+                "render_order": {{"DEFAULT": {{{} "DirNum": "18"}}}}
+            }}}}}}"#,
+            dirs
+        );
+        let db = NpcResDb::from_reader(json.as_bytes()).unwrap();
+        let source = MapSource(
+            ["body.spr", "head.spr", "shadow.spr"]
+                .into_iter()
+                .map(|p| (p.to_string(), sprite()))
+                .collect(),
+        );
+        (
+            CharacterComposer::new(db.get("MainMan").unwrap().clone()),
+            source,
+        )
+    }
+
+    fn parts() -> EquippedParts {
+        [
+            (PartSlot::Body, "Thân thể 01".to_string()),
+            (PartSlot::Head, "Đầu 01".to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn composer_orders_layers_per_sprite_direction() {
+        let (composer, mut source) = composer();
+        let layers = |dir, source: &mut MapSource| -> Vec<Layer> {
+            composer
+                .compose(&parts(), ActionId::FreeStand1, dir, 0, source)
+                .iter()
+                .map(|c| c.layer)
+                .collect()
+        };
+        assert_eq!(
+            layers(0, &mut source),
+            [
+                Layer::Shadow,
+                Layer::Part(PartSlot::Body),
+                Layer::Part(PartSlot::Head)
+            ]
+        );
+        assert_eq!(
+            layers(1, &mut source),
+            [
+                Layer::Shadow,
+                Layer::Part(PartSlot::Head),
+                Layer::Part(PartSlot::Body)
+            ]
+        );
+
+        // frame tuyệt đối của hướng 1 frame 1 là 3
+        let cmds = composer.compose(&parts(), ActionId::FreeStand1, 1, 1, &mut source);
+        assert!(cmds.iter().all(|c| c.frame == 3));
+    }
+
+    #[test]
+    fn shadow_is_first_with_opacity() {
+        let (mut composer, mut source) = composer();
+        composer.shadow_opacity = Some(0.25);
+        let cmds = composer.compose(&parts(), ActionId::FreeStand1, 0, 0, &mut source);
+        assert_eq!(cmds[0].layer, Layer::Shadow);
+        assert_eq!(cmds[0].spr_path, "shadow.spr");
+        assert_eq!(cmds[0].opacity, 0.25);
+        assert!(cmds[1..].iter().all(|c| c.opacity == 1.0));
+        // bóng dùng chung frame với body
+        let cmds = composer.compose(&parts(), ActionId::FreeStand1, 2, 1, &mut source);
+        assert_eq!(cmds[0].frame, cmds[1].frame);
+
+        composer.shadow_opacity = None;
+        let cmds = composer.compose(&parts(), ActionId::FreeStand1, 0, 0, &mut source);
+        assert!(cmds.iter().all(|c| c.layer != Layer::Shadow));
+    }
+}
@@ -1 +1,8 @@
+pub mod assets;
+pub mod audio;
+pub mod camera;
+pub mod canvas;
+pub mod compose;
+pub mod overlay;
+pub mod scene;
 pub mod spr;
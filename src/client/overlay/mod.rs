@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use crate::client::canvas::Bounds;
+use crate::client::compose::{DrawCommand, Layer, SpriteSource, layer_command};
+use crate::common::npc::Npc;
+use crate::common::npcres::{AttachPoint, PlayMode, StatusDepth, StatusEffectVisual};
+
+// --- HIỆU ỨNG TRẠNG THÁI GẮN TRÊN NPC ---
+
+/// Điểm gắn (so với chân nhân vật) của đầu / thân / chân
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachAnchors {
+    pub head: (i32, i32),
+    pub body: (i32, i32),
+    pub foot: (i32, i32),
+}
+
+impl Default for AttachAnchors {
+    // Chiều cao trung bình của nhân vật chính khi chưa có spr
+    fn default() -> Self {
+        AttachAnchors {
+            head: (0, -90),
+            body: (0, -45),
+            foot: (0, 0),
+        }
+    }
+}
+
+impl AttachAnchors {
+    /// Tính từ khung bao của các layer bộ phận (bỏ qua bóng và hiệu ứng)
+    pub fn from_commands(commands: &[DrawCommand]) -> Self {
+        let parts: Vec<DrawCommand> = commands
+            .iter()
+            .filter(|c| matches!(c.layer, Layer::Part(_)))
+            .cloned()
+            .collect();
+        match Bounds::of_commands(&parts) {
+            Some(b) => AttachAnchors {
+                head: (0, b.min_y),
+                body: (0, (b.min_y + b.max_y) / 2),
+                foot: (0, 0),
+            },
+            None => AttachAnchors::default(),
+        }
+    }
+
+    pub fn point(&self, attach: AttachPoint) -> (i32, i32) {
+        match attach {
+            AttachPoint::Head => self.head,
+            AttachPoint::Body => self.body,
+            AttachPoint::Foot | AttachPoint::MiniMap => self.foot,
+        }
+    }
+}
+
+struct ActiveStatus {
+    status: String,
+    started_at: u64,
+}
+
+/// Quản lý các hiệu ứng trạng thái đang gắn trên từng NPC (theo `Npc::id`).
+/// Thời gian tính bằng game frame (tick) để đồng bộ với logic.
+pub struct StatusOverlays {
+    table: HashMap<String, StatusEffectVisual>,
+    active: HashMap<u64, Vec<ActiveStatus>>,
+}
+
+impl StatusOverlays {
+    pub fn new(visuals: Vec<StatusEffectVisual>) -> Self {
+        StatusOverlays {
+            table: visuals.into_iter().map(|v| (v.id.clone(), v)).collect(),
+            active: HashMap::new(),
+        }
+    }
+
+    pub fn visual(&self, status: &str) -> Option<&StatusEffectVisual> {
+        self.table.get(status)
+    }
+
+    /// Gắn hiệu ứng lên NPC. Gắn lại hiệu ứng đang có sẽ chạy lại từ đầu.
+    pub fn attach(&mut self, npc: &Npc, status: &str, now: u64) -> bool {
+        if !self.table.contains_key(status) {
+            return false;
+        }
+        let list = self.active.entry(npc.id).or_default();
+        list.retain(|a| a.status != status);
+        list.push(ActiveStatus {
+            status: status.to_string(),
+            started_at: now,
+        });
+        true
+    }
+
+    pub fn detach(&mut self, npc: &Npc, status: &str) {
+        if let Some(list) = self.active.get_mut(&npc.id) {
+            list.retain(|a| a.status != status);
+        }
+    }
+
+    pub fn clear(&mut self, npc: &Npc) {
+        self.active.remove(&npc.id);
+    }
+
+    /// Các hiệu ứng đang gắn trên NPC (theo thứ tự gắn)
+    pub fn active(&self, npc: &Npc) -> Vec<&str> {
+        self.active
+            .get(&npc.id)
+            .map(|l| l.iter().map(|a| a.status.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Bỏ các hiệu ứng chạy một lần đã kết thúc
+    pub fn update(&mut self, now: u64) {
+        let table = &self.table;
+        for list in self.active.values_mut() {
+            list.retain(|a| match table.get(&a.status) {
+                Some(v) if v.mode == PlayMode::Once => {
+                    v.frame_at(now.saturating_sub(a.started_at)).is_some()
+                }
+                Some(_) => true,
+                None => false,
+            });
+        }
+        self.active.retain(|_, l| !l.is_empty());
+    }
+
+    /// Chèn lệnh vẽ hiệu ứng của NPC vào danh sách lệnh vẽ của nhân vật:
+    /// hiệu ứng `Back` nằm ngay trên bóng (dưới mọi bộ phận), `Front` nằm trên cùng.
+    pub fn apply(
+        &self,
+        npc: &Npc,
+        dir: u32,
+        now: u64,
+        commands: &mut Vec<DrawCommand>,
+        sprites: &mut dyn SpriteSource,
+    ) {
+        let Some(list) = self.active.get(&npc.id) else {
+            return;
+        };
+        let anchors = AttachAnchors::from_commands(commands);
+
+        let mut back = Vec::new();
+        let mut front = Vec::new();
+        for active in list {
+            let Some(visual) = self.table.get(&active.status) else {
+                continue;
+            };
+            let Some(path) = visual.spr.as_deref() else {
+                continue;
+            };
+            if visual.attach == AttachPoint::MiniMap {
+                continue;
+            }
+            let Some(frame) = visual.frame_at(now.saturating_sub(active.started_at)) else {
+                continue;
+            };
+            let Some(mut cmd) = layer_command(
+                Layer::Status(visual.attach),
+                path,
+                dir,
+                frame as usize,
+                sprites,
+            ) else {
+                continue;
+            };
+
+            let (ax, ay) = anchors.point(visual.attach);
+            cmd.x += ax;
+            cmd.y += ay;
+            match visual.depth {
+                StatusDepth::Back => back.push(cmd),
+                StatusDepth::Front => front.push(cmd),
+            }
+        }
+
+        let insert_at = commands
+            .iter()
+            .take_while(|c| c.layer == Layer::Shadow)
+            .count();
+        commands.splice(insert_at..insert_at, back);
+        commands.extend(front);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::spr::SprFile;
+    use crate::common::npc::{NpcKind, PartSlot};
+    use image::{Rgba, RgbaImage};
+    use std::sync::Arc;
+
+    struct MapSource(HashMap<String, Arc<SprFile>>);
+
+    impl SpriteSource for MapSource {
+        fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>> {
+            self.0.get(path).cloned()
+        }
+    }
+
+    // Spr 1 hướng, 4 frame 1x1, tâm (0, 0)
+    fn sprite() -> Arc<SprFile> {
+        let images: Vec<_> = (0..4)
+            .map(|i| (RgbaImage::from_pixel(1, 1, Rgba([i, 0, 0, 255])), 0, 0))
+            .collect();
+        Arc::new(SprFile::from_images(&images, (0, 0), 1, 1).unwrap())
+    }
+
+    fn visual(
+        id: &str,
+        mode: PlayMode,
+        attach: AttachPoint,
+        depth: StatusDepth,
+    ) -> StatusEffectVisual {
+        StatusEffectVisual {
+            id: id.to_string(),
+            spr: Some(format!("{}.spr", id)),
+            attach,
+            mode,
+            loop_start: 0,
+            loop_end: 0,
+            frames: 4,
+            directions: 1,
+            interval: 2,
+            depth,
+            name: String::new(),
+        }
+    }
+
+    fn overlays() -> StatusOverlays {
+        StatusOverlays::new(vec![
+            visual(
+                "Status1",
+                PlayMode::Loop,
+                AttachPoint::Head,
+                StatusDepth::Front,
+            ),
+            visual(
+                "Status2",
+                PlayMode::Once,
+                AttachPoint::Foot,
+                StatusDepth::Back,
+            ),
+        ])
+    }
+
+    fn command(layer: Layer, y: i32) -> DrawCommand {
+        DrawCommand {
+            layer,
+            spr_path: String::new(),
+            sprite: sprite(),
+            frame: 0,
+            x: 0,
+            y,
+            opacity: 1.0,
+        }
+    }
+
+    #[test]
+    fn frame_at_follows_play_mode() {
+        let mut v = visual(
+            "Status1",
+            PlayMode::Once,
+            AttachPoint::Body,
+            StatusDepth::Front,
+        );
+        // 4 frame, 2 game frame mỗi frame spr
+        let frames: Vec<_> = [0, 1, 2, 7, 8].iter().map(|&t| v.frame_at(t)).collect();
+        assert_eq!(frames, [Some(0), Some(0), Some(1), Some(3), None]);
+
+        v.mode = PlayMode::Loop;
+        assert_eq!(v.frame_at(8), Some(0));
+
+        // Lượt đầu chạy hết 0..=3, sau đó chỉ lặp đoạn 2..=3
+        v.loop_start = 2;
+        v.loop_end = 3;
+        let frames: Vec<_> = (0..8).map(|step| v.frame_at(step * 2).unwrap()).collect();
+        assert_eq!(frames, [0, 1, 2, 3, 2, 3, 2, 3]);
+    }
+
+    #[test]
+    fn attach_detach_and_expire() {
+        let mut overlays = overlays();
+        let a = Npc::new(1, "a", NpcKind::Normal);
+        let b = Npc::new(2, "b", NpcKind::Normal);
+
+        assert!(!overlays.attach(&a, "Status9", 0));
+        assert!(overlays.attach(&a, "Status1", 0));
+        assert!(overlays.attach(&a, "Status2", 0));
+        assert!(overlays.attach(&b, "Status2", 4));
+        // Gắn lại thì chuyển xuống cuối và chạy lại từ đầu
+        assert!(overlays.attach(&a, "Status1", 0));
+        assert_eq!(overlays.active(&a), ["Status2", "Status1"]);
+
+        // Status2 chạy một lần hết sau 8 game frame, Status1 lặp mãi
+        overlays.update(8);
+        assert_eq!(overlays.active(&a), ["Status1"]);
+        assert_eq!(overlays.active(&b), ["Status2"]);
+        overlays.update(12);
+        assert!(overlays.active(&b).is_empty());
+
+        overlays.detach(&a, "Status1");
+        assert!(overlays.active(&a).is_empty());
+        overlays.attach(&a, "Status2", 20);
+        overlays.clear(&a);
+        assert!(overlays.active(&a).is_empty());
+    }
+
+    #[test]
+    fn apply_places_back_over_shadow_and_front_on_top() {
+        let mut overlays = overlays();
+        let npc = Npc::new(1, "a", NpcKind::Normal);
+        let mut sprites = MapSource(
+            ["Status1.spr", "Status2.spr"]
+                .into_iter()
+                .map(|p| (p.to_string(), sprite()))
+                .collect(),
+        );
+        let mut commands = vec![
+            command(Layer::Shadow, 0),
+            command(Layer::Part(PartSlot::Body), -60),
+            command(Layer::Part(PartSlot::Head), -80),
+        ];
+
+        // Chưa gắn gì thì giữ nguyên
+        overlays.apply(&npc, 0, 0, &mut commands, &mut sprites);
+        assert_eq!(commands.len(), 3);
+
+        overlays.attach(&npc, "Status1", 0);
+        overlays.attach(&npc, "Status2", 0);
+        overlays.apply(&npc, 0, 5, &mut commands, &mut sprites);
+        let layers: Vec<Layer> = commands.iter().map(|c| c.layer).collect();
+        assert_eq!(
+            layers,
+            [
+                Layer::Shadow,
+                Layer::Status(AttachPoint::Foot),
+                Layer::Part(PartSlot::Body),
+                Layer::Part(PartSlot::Head),
+                Layer::Status(AttachPoint::Head),
+            ]
+        );
+        // Đầu = đỉnh khung bao các bộ phận, chân = gốc toạ độ; frame theo thời gian đã gắn
+        assert_eq!((commands[4].x, commands[4].y), (0, -80));
+        assert_eq!((commands[1].x, commands[1].y), (0, 0));
+        assert_eq!(commands[4].frame, 2);
+    }
+}
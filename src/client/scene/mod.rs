@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use image::{Rgba, RgbaImage};
+
+use crate::client::assets::AssetLoader;
+use crate::client::canvas::draw_command;
+use crate::client::compose::{DrawCommand, Layer, SpriteSource, layer_command};
+use crate::common::map::{GroundTile, RegionScenery, SceneryObject};
+use crate::common::world::WorldPos;
+
+// --- CHIẾU TOẠ ĐỘ ---
+
+/// Điểm logic -> pixel so với góc trên trái khung nhìn `view` (điểm logic).
+/// Trục y logic gấp đôi trục y màn hình.
+pub fn logic_to_view(x: i32, y: i32, view: (i32, i32)) -> (i32, i32) {
+    (x - view.0, (y - view.1).div_euclid(2))
+}
+
+// --- PHẦN TỬ CỦA SCENE ---
+
+/// Loại layer của lệnh vẽ trong scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneLayer {
+    // Ô nền và vật thể cảnh của bản đồ
+    Ground,
+    Scenery,
+    // Một layer của nhân vật / NPC (từ composer)
+    Character(Layer),
+}
+
+/// Thứ cần vẽ của một phần tử: frame spr, hoặc ảnh thường (ô nền jpg) đặt tại góc trên trái
+#[derive(Debug, Clone)]
+pub enum SceneDraw {
+    Sprite(DrawCommand<SceneLayer>),
+    Image(Arc<RgbaImage>),
+}
+
+/// Một phần tử đặt tại điểm logic (x, y): các lệnh vẽ tính từ điểm đó trên màn hình
+#[derive(Debug, Clone)]
+pub struct SceneItem {
+    pub x: i32,
+    pub y: i32,
+    // y logic dùng để xếp thứ tự vẽ (chân NPC, sort line của cảnh)
+    pub sort_y: i32,
+    pub draws: Vec<SceneDraw>,
+}
+
+/// Scene của một khung hình: lớp nền vẽ trước theo thứ tự thêm vào,
+/// sau đó cảnh và NPC xếp theo `sort_y` (cùng hàng thì theo x, rồi theo thứ tự thêm)
+/// để nhân vật đi trước / sau cây cối, nhà cửa đúng chỗ.
+#[derive(Default)]
+pub struct Scene {
+    ground: Vec<SceneItem>,
+    sorted: Vec<SceneItem>,
+    // Ảnh thường đã giải mã, giữ lại giữa các khung hình
+    images: HashMap<String, Option<Arc<RgbaImage>>>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Xoá phần tử để dựng khung hình mới (giữ cache ảnh)
+    pub fn clear(&mut self) {
+        self.ground.clear();
+        self.sorted.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.ground.len() + self.sorted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Thêm ô nền. Ảnh không phải spr (jpg...) đọc qua `loader`, bỏ qua nếu không có loader.
+    pub fn add_ground(
+        &mut self,
+        tile: &GroundTile,
+        sprites: &mut dyn SpriteSource,
+        loader: Option<&dyn AssetLoader>,
+    ) {
+        let draw = if is_spr(&tile.image) {
+            layer_command(
+                SceneLayer::Ground,
+                &tile.image,
+                0,
+                tile.frame as usize,
+                sprites,
+            )
+            .map(SceneDraw::Sprite)
+        } else {
+            loader
+                .and_then(|loader| self.image(&tile.image, loader))
+                .map(SceneDraw::Image)
+        };
+        if let Some(draw) = draw {
+            self.ground.push(SceneItem {
+                x: tile.x,
+                y: tile.y,
+                sort_y: tile.y,
+                draws: vec![draw],
+            });
+        }
+    }
+
+    /// Thêm vật thể cảnh ở game frame `tick` (cảnh có animation)
+    pub fn add_scenery(&mut self, obj: &SceneryObject, tick: u32, sprites: &mut dyn SpriteSource) {
+        let frame = obj.frame_at(tick) as usize;
+        if let Some(cmd) = layer_command(SceneLayer::Scenery, &obj.image, 0, frame, sprites) {
+            self.sorted.push(SceneItem {
+                x: obj.x,
+                y: obj.y,
+                sort_y: obj.sort_y(),
+                draws: vec![SceneDraw::Sprite(cmd)],
+            });
+        }
+    }
+
+    /// Thêm toàn bộ nền và cảnh của một region
+    pub fn add_region(
+        &mut self,
+        scenery: &RegionScenery,
+        tick: u32,
+        sprites: &mut dyn SpriteSource,
+        loader: Option<&dyn AssetLoader>,
+    ) {
+        for tile in &scenery.ground {
+            self.add_ground(tile, sprites, loader);
+        }
+        for obj in &scenery.objects {
+            self.add_scenery(obj, tick, sprites);
+        }
+    }
+
+    /// Thêm nhân vật / NPC đứng tại `foot`, `commands` lấy từ composer (tính từ chân)
+    pub fn add_sprite(&mut self, foot: WorldPos, commands: Vec<DrawCommand>) {
+        self.sorted.push(SceneItem {
+            x: foot.x,
+            y: foot.y,
+            sort_y: foot.y,
+            draws: commands
+                .into_iter()
+                .map(|cmd| SceneDraw::Sprite(cmd.map_layer(SceneLayer::Character)))
+                .collect(),
+        });
+    }
+
+    /// Các phần tử theo đúng thứ tự vẽ
+    pub fn draw_order(&self) -> Vec<&SceneItem> {
+        let mut sorted: Vec<&SceneItem> = self.sorted.iter().collect();
+        // sort_by_key ổn định nên phần tử trùng khoá giữ thứ tự thêm vào
+        sorted.sort_by_key(|item| (item.sort_y, item.x));
+        self.ground.iter().chain(sorted).collect()
+    }
+
+    /// Vẽ scene lên `canvas`, góc trên trái canvas là điểm logic `view`
+    pub fn render(&self, canvas: &mut RgbaImage, view: (i32, i32)) {
+        for item in self.draw_order() {
+            let (ox, oy) = logic_to_view(item.x, item.y, view);
+            for draw in &item.draws {
+                match draw {
+                    SceneDraw::Sprite(cmd) => draw_command(canvas, cmd, ox, oy, cmd.opacity),
+                    SceneDraw::Image(image) => {
+                        image::imageops::overlay(canvas, image.as_ref(), ox as i64, oy as i64)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Render không cần GPU ra ảnh `width` x `height` (nền đen), dùng cho test và công cụ
+    pub fn render_to_image(&self, width: u32, height: u32, view: (i32, i32)) -> RgbaImage {
+        let mut canvas = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+        self.render(&mut canvas, view);
+        canvas
+    }
+
+    fn image(&mut self, path: &str, loader: &dyn AssetLoader) -> Option<Arc<RgbaImage>> {
+        self.images
+            .entry(path.to_string())
+            .or_insert_with(|| {
+                let data = loader.load(path)?;
+                image::load_from_memory(&data)
+                    .ok()
+                    .map(|img| Arc::new(img.to_rgba8()))
+            })
+            .clone()
+    }
+}
+
+fn is_spr(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".spr")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::spr::SprFile;
+    use crate::common::npc::PartSlot;
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use std::io::Cursor;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    // Spr một frame w x h tô kín một màu, tâm ở giữa cạnh dưới (chân)
+    fn make_spr(w: u16, h: u16, color: Rgba<u8>) -> Vec<u8> {
+        let mut frame = Vec::new();
+        for v in [w, h, 0, 0] {
+            frame.write_u16::<LittleEndian>(v).unwrap();
+        }
+        for _ in 0..h {
+            frame.extend([w as u8, 255]);
+            frame.extend(std::iter::repeat_n(1u8, w as usize));
+        }
+
+        let mut data = b"SPR\0".to_vec();
+        for v in [w, h, w / 2, h, 1, 2, 1, 1, 0, 0, 0, 0, 0, 0] {
+            data.write_u16::<LittleEndian>(v).unwrap();
+        }
+        data.extend([0, 0, 0, color[0], color[1], color[2]]);
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(frame.len() as u32).unwrap();
+        data.extend(frame);
+        data
+    }
+
+    struct TestSprites;
+
+    impl SpriteSource for TestSprites {
+        fn sprite(&mut self, path: &str) -> Option<Arc<SprFile>> {
+            let data = match path {
+                "\\ground.spr" => make_spr(64, 32, BLUE),
+                "\\tree.spr" => make_spr(20, 40, GREEN),
+                "\\npc.spr" => make_spr(10, 20, RED),
+                _ => return None,
+            };
+            SprFile::from_bytes(&data).ok().map(Arc::new)
+        }
+    }
+
+    fn npc_commands() -> Vec<DrawCommand> {
+        layer_command(
+            Layer::Part(PartSlot::Body),
+            "\\npc.spr",
+            0,
+            0,
+            &mut TestSprites,
+        )
+        .into_iter()
+        .collect()
+    }
+
+    fn tree() -> SceneryObject {
+        SceneryObject {
+            image: "\\tree.spr".to_string(),
+            frame: 0,
+            frames: 1,
+            interval: 0,
+            x: 100,
+            y: 200,
+            sort_line: ((0, 0), (0, 0)),
+        }
+    }
+
+    #[test]
+    fn ground_is_drawn_below_sprites() {
+        let mut scene = Scene::new();
+        scene.add_sprite(WorldPos::new(0, 20, 16), npc_commands());
+        let tile = GroundTile {
+            image: "\\ground.spr".to_string(),
+            frame: 0,
+            x: 0,
+            y: 0,
+        };
+        scene.add_ground(&tile, &mut TestSprites, None);
+
+        // Ô nền có tâm ở đáy nên ảnh nằm phía trên điểm đặt: đặt view để thấy cả hai
+        let img = scene.render_to_image(64, 64, (0, -64));
+        assert_eq!(*img.get_pixel(20, 25), RED);
+        assert_eq!(*img.get_pixel(5, 5), BLUE);
+        let order = scene.draw_order();
+        let layer = |i: usize| match &order[i].draws[0] {
+            SceneDraw::Sprite(cmd) => cmd.layer,
+            SceneDraw::Image(_) => unreachable!(),
+        };
+        assert_eq!(layer(0), SceneLayer::Ground);
+        assert_eq!(layer(1), SceneLayer::Character(Layer::Part(PartSlot::Body)));
+    }
+
+    #[test]
+    fn sprites_sort_by_foot_position() {
+        let render = |npc_y: i32| {
+            let mut scene = Scene::new();
+            scene.add_sprite(WorldPos::new(0, 100, npc_y), npc_commands());
+            scene.add_scenery(&tree(), 0, &mut TestSprites);
+            scene.render_to_image(200, 200, (0, 0))
+        };
+
+        // NPC đứng sau gốc cây (y nhỏ hơn) bị cây che
+        let behind = render(190);
+        let (x, y) = logic_to_view(100, 185, (0, 0));
+        assert_eq!(*behind.get_pixel(x as u32, y as u32), GREEN);
+
+        // NPC đứng trước gốc cây che lên cây
+        let front = render(210);
+        assert_eq!(*front.get_pixel(x as u32, y as u32), RED);
+
+        // Sort line thay cho chân vật thể
+        let mut line_tree = tree();
+        line_tree.sort_line = ((80, 180), (120, 180));
+        let mut scene = Scene::new();
+        scene.add_scenery(&line_tree, 0, &mut TestSprites);
+        scene.add_sprite(WorldPos::new(0, 100, 190), npc_commands());
+        let order: Vec<i32> = scene.draw_order().iter().map(|i| i.sort_y).collect();
+        assert_eq!(order, vec![180, 190]);
+    }
+
+    #[test]
+    fn image_ground_tiles_use_loader() {
+        let mut png = Vec::new();
+        RgbaImage::from_pixel(8, 8, BLUE)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let loader = move |path: &str| (path == "\\ground.png").then(|| png.clone());
+
+        let mut scene = Scene::new();
+        let tile = |image: &str| GroundTile {
+            image: image.to_string(),
+            frame: 0,
+            x: 4,
+            y: 8,
+        };
+        scene.add_ground(&tile("\\ground.png"), &mut TestSprites, Some(&loader));
+        scene.add_ground(&tile("\\missing.jpg"), &mut TestSprites, Some(&loader));
+        assert_eq!(scene.len(), 1);
+
+        let img = scene.render_to_image(16, 16, (0, 0));
+        assert_eq!(*img.get_pixel(4, 4), BLUE);
+        assert_eq!(*img.get_pixel(3, 4), Rgba([0, 0, 0, 255]));
+    }
+}
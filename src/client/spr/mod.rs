@@ -1,7 +1,9 @@
 // src/common/spr.rs
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use image::RgbaImage;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 
 // --- CẤU TRÚC DỮ LIỆU ---
@@ -41,6 +43,7 @@ pub struct SprFrameInfo {
 
 // --- SPR PARSER CLASS ---
 
+#[derive(Debug, Clone)]
 pub struct SprFile {
     pub header: SprHeader,
     pub palette: Vec<SprColor>,
@@ -49,8 +52,15 @@ pub struct SprFile {
 
 impl SprFile {
     pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let mut f = File::open(path)?;
+        Self::from_reader(io::BufReader::new(File::open(path)?))
+    }
+
+    /// Parse từ bytes đã đọc ra từ PAK
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        Self::from_reader(Cursor::new(data))
+    }
 
+    pub fn from_reader<R: Read + Seek>(mut f: R) -> io::Result<Self> {
         // 1. Đọc Header (Size = 24 bytes + Reserved)
         // Lưu ý: Cấu trúc C++ có padding alignment, ta đọc từng field cho chắc
         let mut sig = [0u8; 4];
@@ -177,4 +187,216 @@ impl SprFile {
             frames,
         })
     }
+
+    /// Số frame của mỗi hướng (SPR lưu frame liên tiếp theo từng hướng)
+    pub fn frames_per_direction(&self) -> usize {
+        let dirs = (self.header.directions as usize).max(1);
+        self.frames.len() / dirs
+    }
+
+    /// Index tuyệt đối trong `frames` của frame `frame` ở hướng `dir`.
+    /// `frame` được quay vòng theo số frame mỗi hướng, `dir` theo số hướng.
+    pub fn frame_index(&self, dir: usize, frame: usize) -> Option<usize> {
+        let per_dir = self.frames_per_direction();
+        if per_dir == 0 {
+            return None;
+        }
+        let dirs = (self.header.directions as usize).max(1);
+        Some((dir % dirs) * per_dir + frame % per_dir)
+    }
+
+    /// Chuyển frame `index` sang RGBA8 (width * height * 4), dùng palette và alpha map của SPR
+    pub fn frame_rgba(&self, index: usize) -> Option<Vec<u8>> {
+        let frame = self.frames.get(index)?;
+        let mut rgba = vec![0u8; frame.decoded_indices.len() * 4];
+        for (i, &color_idx) in frame.decoded_indices.iter().enumerate() {
+            let alpha = frame.alpha_map[i];
+            if alpha == 0 {
+                continue;
+            }
+            if let Some(c) = self.palette.get(color_idx as usize) {
+                rgba[i * 4] = c.r;
+                rgba[i * 4 + 1] = c.g;
+                rgba[i * 4 + 2] = c.b;
+                rgba[i * 4 + 3] = alpha;
+            }
+        }
+        Some(rgba)
+    }
+
+    /// Tạo SPR từ các frame RGBA (theo thứ tự hướng 0 frame 0, hướng 0 frame 1...).
+    /// Mỗi phần tử là (ảnh, offset_x, offset_y). Pixel alpha 0 là trong suốt, SPR chỉ có
+    /// palette 256 màu nên ảnh phải được giảm màu trước.
+    pub fn from_images(
+        images: &[(RgbaImage, i16, i16)],
+        center: (u16, u16),
+        directions: u16,
+        interval: u16,
+    ) -> io::Result<Self> {
+        let mut palette: Vec<SprColor> = Vec::new();
+        let mut lookup: HashMap<[u8; 3], u8> = HashMap::new();
+        let mut frames = Vec::with_capacity(images.len());
+
+        for (image, offset_x, offset_y) in images {
+            let (w, h) = image.dimensions();
+            if w > u16::MAX as u32 || h > u16::MAX as u32 {
+                return Err(invalid_data("Frame quá lớn cho SPR"));
+            }
+            let mut indices = Vec::with_capacity((w * h) as usize);
+            let mut alphas = Vec::with_capacity((w * h) as usize);
+            for p in image.pixels() {
+                let [r, g, b, a] = p.0;
+                if a == 0 {
+                    indices.push(0);
+                    alphas.push(0);
+                    continue;
+                }
+                let index = match lookup.get(&[r, g, b]) {
+                    Some(&i) => i,
+                    None => {
+                        if palette.len() == 256 {
+                            return Err(invalid_data("Ảnh có hơn 256 màu, cần giảm màu trước"));
+                        }
+                        let i = palette.len() as u8;
+                        palette.push(SprColor { r, g, b });
+                        lookup.insert([r, g, b], i);
+                        i
+                    }
+                };
+                indices.push(index);
+                alphas.push(a);
+            }
+            frames.push(SprFrameInfo {
+                width: w as u16,
+                height: h as u16,
+                offset_x: *offset_x,
+                offset_y: *offset_y,
+                decoded_indices: indices,
+                alpha_map: alphas,
+            });
+        }
+
+        let header = SprHeader {
+            signature: *b"SPR\0",
+            width: frames.iter().map(|f| f.width).max().unwrap_or(0),
+            height: frames.iter().map(|f| f.height).max().unwrap_or(0),
+            center_x: center.0,
+            center_y: center.1,
+            frames: frames.len() as u16,
+            colors: palette.len() as u16,
+            directions: directions.max(1),
+            interval,
+            reserved: [0; 6],
+        };
+        Ok(SprFile {
+            header,
+            palette,
+            frames,
+        })
+    }
+
+    /// Mã hoá lại thành file .spr: mỗi dòng pixel được nén RLE riêng
+    /// ([số pixel][alpha] rồi các index màu nếu alpha > 0, tối đa 255 pixel mỗi đoạn)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut frame_data: Vec<Vec<u8>> = Vec::with_capacity(self.frames.len());
+        for frame in &self.frames {
+            let mut data = Vec::new();
+            for v in [frame.width, frame.height] {
+                data.write_u16::<LittleEndian>(v).unwrap();
+            }
+            for v in [frame.offset_x, frame.offset_y] {
+                data.write_i16::<LittleEndian>(v).unwrap();
+            }
+            let width = frame.width as usize;
+            for row in 0..frame.height as usize {
+                let range = row * width..(row + 1) * width;
+                encode_row(
+                    &frame.decoded_indices[range.clone()],
+                    &frame.alpha_map[range],
+                    &mut data,
+                );
+            }
+            frame_data.push(data);
+        }
+
+        let h = &self.header;
+        let mut out = h.signature.to_vec();
+        let fields = [
+            h.width,
+            h.height,
+            h.center_x,
+            h.center_y,
+            self.frames.len() as u16,
+            self.palette.len() as u16,
+            h.directions,
+            h.interval,
+        ];
+        for v in fields.iter().chain(h.reserved.iter()) {
+            out.write_u16::<LittleEndian>(*v).unwrap();
+        }
+        for c in &self.palette {
+            out.extend([c.r, c.g, c.b]);
+        }
+        let mut offset = 0u32;
+        for data in &frame_data {
+            out.write_u32::<LittleEndian>(offset).unwrap();
+            out.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+            offset += data.len() as u32;
+        }
+        for data in frame_data {
+            out.extend(data);
+        }
+        out
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// Một dòng pixel -> các đoạn RLE cùng alpha
+fn encode_row(indices: &[u8], alphas: &[u8], out: &mut Vec<u8>) {
+    let mut x = 0;
+    while x < alphas.len() {
+        let alpha = alphas[x];
+        let mut end = x + 1;
+        while end < alphas.len() && alphas[end] == alpha && end - x < 255 {
+            end += 1;
+        }
+        out.extend([(end - x) as u8, alpha]);
+        if alpha > 0 {
+            out.extend_from_slice(&indices[x..end]);
+        }
+        x = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn encoded_sprite_decodes_to_same_pixels() {
+        // 300 pixel mỗi dòng để thử đoạn RLE dài hơn 255, có pixel trong suốt và bán trong suốt
+        let mut a = RgbaImage::from_pixel(300, 2, Rgba([10, 20, 30, 255]));
+        a.put_pixel(5, 0, Rgba([0, 0, 0, 0]));
+        a.put_pixel(6, 1, Rgba([200, 0, 0, 128]));
+        let b = RgbaImage::from_pixel(3, 4, Rgba([0, 0, 0, 0]));
+
+        let spr = SprFile::from_images(&[(a.clone(), -150, -2), (b.clone(), 1, 2)], (150, 2), 2, 3)
+            .unwrap();
+        assert_eq!(spr.palette.len(), 2);
+
+        let decoded = SprFile::from_bytes(&spr.to_bytes()).unwrap();
+        assert_eq!(decoded.header.frames, 2);
+        assert_eq!(decoded.header.directions, 2);
+        assert_eq!((decoded.header.width, decoded.header.height), (300, 4));
+        assert_eq!(decoded.frames[0].offset_x, -150);
+        assert_eq!(decoded.frame_rgba(0).unwrap(), a.into_raw());
+        assert_eq!(decoded.frame_rgba(1).unwrap(), b.into_raw());
+
+        let many: RgbaImage = RgbaImage::from_fn(20, 20, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        assert!(SprFile::from_images(&[(many, 0, 0)], (0, 0), 1, 1).is_err());
+    }
 }
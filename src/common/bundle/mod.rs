@@ -0,0 +1,1091 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::common::npc::{
+    ActionId, ActionSet, ActionTiming, EquipCategory, NpcAction, PartSlot, UNARMED_WEAPON,
+};
+use crate::common::npcres::{ActionSprInfo, NpcResDb, NpcResEntry, RenderOrder};
+use crate::common::world::GAME_FPS;
+
+// --- BUNDLE NHỊ PHÂN CỦA NPCRES ---
+// npcres.json (~3MB, thụt lề) đọc bằng serde mất thời gian mỗi lần khởi động. Bundle chứa cùng
+// dữ liệu `NpcResDb` ở dạng gọn: mọi chuỗi gom vào một bảng (mỗi chuỗi một lần), tên action
+// thành 1 byte, bảng bộ phận thành mảng theo `ActionId::ALL`.
+//
+// Header 32 byte: magic "JXNB", version u16, flags u16 (bit lạ bị từ chối), số chuỗi u32,
+// kích thước bảng chuỗi u32, kích thước phần dữ liệu u32, crc32 (bảng chuỗi + dữ liệu) u32,
+// reserved[8].
+// Sau header: bảng chuỗi ([len u16][utf-8]...) rồi dữ liệu nhân vật. Số nguyên little-endian.
+// Mọi map được ghi theo key đã sắp xếp nên cùng dữ liệu thì cùng bundle từng byte.
+// Đo bằng `cargo bench --bench npcres_load`.
+
+pub const BUNDLE_MAGIC: [u8; 4] = *b"JXNB";
+pub const BUNDLE_VERSION: u16 = 2;
+pub const BUNDLE_HEADER_SIZE: usize = 32;
+// Chưa có cờ nào: bundle có bit lạ là do bản ghi mới hơn, đọc tiếp sẽ hiểu sai dữ liệu
+pub const BUNDLE_KNOWN_FLAGS: u16 = 0;
+
+// Tên action: < 0x80 là index trong `ActionId::ALL`, 0x80 + index trong `NpcAction::ALL`,
+// còn lại (tên không chuẩn như cột "Jump") ghi nguyên chuỗi sau byte KEY_STRING
+const KEY_NPC_ACTION: u8 = 0x80;
+const KEY_STRING: u8 = 0xFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleHeader {
+    pub version: u16,
+    pub flags: u16,
+    pub string_count: u32,
+    pub strings_len: u32,
+    pub body_len: u32,
+    pub checksum: u32,
+}
+
+impl BundleHeader {
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        if !is_bundle(data) || data.len() < BUNDLE_HEADER_SIZE {
+            return Err(invalid("Không phải bundle npcres (sai magic)"));
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        Ok(BundleHeader {
+            version: u16_at(4),
+            flags: u16_at(6),
+            string_count: u32_at(8),
+            strings_len: u32_at(12),
+            body_len: u32_at(16),
+            checksum: u32_at(20),
+        })
+    }
+}
+
+pub fn is_bundle(data: &[u8]) -> bool {
+    data.starts_with(&BUNDLE_MAGIC)
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// --- GHI ---
+
+/// Mã hoá `db` thành bundle. Lỗi khi có chuỗi dài quá 65535 byte hoặc quá 4GB dữ liệu.
+pub fn encode_npcres(db: &NpcResDb) -> io::Result<Vec<u8>> {
+    let mut w = Writer::default();
+    w.u32(db.chars.len() as u32);
+    for (name, entry) in &db.chars {
+        w.str(name);
+        w.entry(entry);
+    }
+
+    let mut strings = Vec::new();
+    for s in &w.strings {
+        let len = u16::try_from(s.len()).map_err(|_| {
+            let head: String = s.chars().take(32).collect();
+            invalid(format!("Chuỗi quá dài cho bundle: {}...", head))
+        })?;
+        strings.extend_from_slice(&len.to_le_bytes());
+        strings.extend_from_slice(s.as_bytes());
+    }
+    let too_big = |_| invalid("Bundle quá 4GB");
+    let strings_len = u32::try_from(strings.len()).map_err(too_big)?;
+    let body_len = u32::try_from(w.body.len()).map_err(too_big)?;
+
+    let mut checksum = Crc32::new();
+    checksum.update(&strings);
+    checksum.update(&w.body);
+
+    let mut out = Vec::with_capacity(BUNDLE_HEADER_SIZE + strings.len() + w.body.len());
+    out.extend_from_slice(&BUNDLE_MAGIC);
+    out.extend_from_slice(&BUNDLE_VERSION.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&(w.strings.len() as u32).to_le_bytes());
+    out.extend_from_slice(&strings_len.to_le_bytes());
+    out.extend_from_slice(&body_len.to_le_bytes());
+    out.extend_from_slice(&checksum.finish().to_le_bytes());
+    out.extend_from_slice(&[0u8; 8]);
+    out.extend_from_slice(&strings);
+    out.extend_from_slice(&w.body);
+    Ok(out)
+}
+
+#[derive(Default)]
+struct Writer {
+    strings: Vec<String>,
+    ids: HashMap<String, u32>,
+    body: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.body.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.body.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.body.extend_from_slice(&v.to_le_bytes());
+    }
+
+    /// Chuỗi ghi bằng id trong bảng chuỗi
+    fn str(&mut self, s: &str) {
+        let id = match self.ids.get(s) {
+            Some(&id) => id,
+            None => {
+                let id = self.strings.len() as u32;
+                self.strings.push(s.to_string());
+                self.ids.insert(s.to_string(), id);
+                id
+            }
+        };
+        self.u32(id);
+    }
+
+    /// Tên action (key của shadows/sounds/weapon_logic...), 1 byte nếu là tên chuẩn
+    fn key(&mut self, name: &str) {
+        if let Some(i) = ActionId::ALL.iter().position(|a| a.name() == name) {
+            self.u8(i as u8);
+        } else if let Some(i) = NpcAction::ALL.iter().position(|a| a.name() == name) {
+            self.u8(KEY_NPC_ACTION + i as u8);
+        } else {
+            self.u8(KEY_STRING);
+            self.str(name);
+        }
+    }
+
+    /// Map theo key đã sắp xếp
+    fn map<V>(&mut self, map: &HashMap<String, V>, mut write: impl FnMut(&mut Self, &str, &V)) {
+        let mut keys: Vec<&String> = map.keys().collect();
+        keys.sort();
+        self.u32(keys.len() as u32);
+        for key in keys {
+            write(self, key, &map[key]);
+        }
+    }
+
+    fn spr_info(&mut self, info: &ActionSprInfo) {
+        self.str(&info.spr);
+        self.u32(info.frames);
+        self.u32(info.directions);
+        self.u32(info.interval);
+    }
+
+    fn entry(&mut self, e: &NpcResEntry) {
+        self.str(&e.kind);
+        self.str(&e.root_path);
+
+        self.map(&e.weapon_logic, |w, mode, weapons| {
+            w.str(mode);
+            w.map(weapons, |w, weapon, commands| {
+                w.str(weapon);
+                w.map(commands, |w, command, action| {
+                    w.key(command);
+                    w.key(action);
+                });
+            });
+        });
+
+        // Mỗi item: mask u64 các ActionId có spr, rồi các spr theo thứ tự ActionId::ALL,
+        // rồi các cột tên không chuẩn ("Jump") dạng (tên, spr)
+        self.map(&e.components, |w, slot, items| {
+            w.str(slot);
+            w.map(items, |w, item, actions| {
+                w.str(item);
+                let mut mask = 0u64;
+                for (i, action) in ActionId::ALL.iter().enumerate() {
+                    if actions.contains_key(action.name()) {
+                        mask |= 1 << i;
+                    }
+                }
+                w.u64(mask);
+                for action in ActionId::ALL {
+                    if let Some(spr) = actions.get(action.name()) {
+                        w.str(spr);
+                    }
+                }
+                let extra: HashMap<String, &String> = actions
+                    .iter()
+                    .filter(|(name, _)| !ActionId::ALL.iter().any(|a| a.name() == *name))
+                    .map(|(name, spr)| (name.clone(), spr))
+                    .collect();
+                w.map(&extra, |w, name, spr| {
+                    w.str(name);
+                    w.str(spr);
+                });
+            });
+        });
+
+        self.map(&e.part_rows, |w, slot, rows| {
+            w.str(slot);
+            w.u32(rows.len() as u32);
+            for row in rows {
+                w.str(row);
+            }
+        });
+
+        self.map(&e.render_order, |w, section, keys| {
+            w.str(section);
+            w.map(keys, |w, key, value| {
+                w.str(key);
+                w.str(value);
+            });
+        });
+        self.map(&e.shadows, |w, action, info| {
+            w.key(action);
+            w.spr_info(info);
+        });
+        self.map(&e.actions, |w, action, info| {
+            w.key(action);
+            w.spr_info(info);
+        });
+        self.map(&e.sounds, |w, action, wav| {
+            w.key(action);
+            w.str(wav);
+        });
+
+        self.u32(e.part_list.len() as u32);
+        for category in &e.part_list {
+            self.u32(category.index);
+            self.str(&category.name);
+            self.u8(category.slots.len() as u8);
+            for slot in &category.slots {
+                let i = PartSlot::ALL.iter().position(|s| s == slot).unwrap_or(0);
+                self.u8(i as u8);
+            }
+        }
+    }
+}
+
+// --- ĐỌC ---
+
+/// Kiểm tra header (version, cờ, độ dài, checksum) rồi tách bảng chuỗi, chuỗi mượn thẳng từ
+/// `data`. Trả về bảng chuỗi và phần dữ liệu nhân vật.
+fn open(data: &[u8]) -> io::Result<(Vec<&str>, &[u8])> {
+    let header = BundleHeader::parse(data)?;
+    if header.version != BUNDLE_VERSION {
+        return Err(invalid(format!(
+            "Bundle phiên bản {}, cần {}: tạo lại bằng `jx npcres bundle`",
+            header.version, BUNDLE_VERSION
+        )));
+    }
+    if header.flags & !BUNDLE_KNOWN_FLAGS != 0 {
+        return Err(invalid(format!(
+            "Bundle có cờ lạ {:#06x}: tạo lại bằng `jx npcres bundle`",
+            header.flags & !BUNDLE_KNOWN_FLAGS
+        )));
+    }
+    let strings_end = BUNDLE_HEADER_SIZE + header.strings_len as usize;
+    let body_end = strings_end + header.body_len as usize;
+    if data.len() != body_end {
+        return Err(invalid(format!(
+            "Bundle dài {} byte, header ghi {}",
+            data.len(),
+            body_end
+        )));
+    }
+    let mut checksum = Crc32::new();
+    checksum.update(&data[BUNDLE_HEADER_SIZE..]);
+    if checksum.finish() != header.checksum {
+        return Err(invalid("Bundle hỏng (sai checksum)"));
+    }
+
+    let mut r = Reader {
+        data: &data[BUNDLE_HEADER_SIZE..strings_end],
+        pos: 0,
+        strings: &[],
+    };
+    // string_count lấy từ file: không cấp phát trước quá số byte của bảng chuỗi
+    let mut strings = Vec::with_capacity((header.string_count as usize).min(r.data.len() / 2));
+    for _ in 0..header.string_count {
+        let len = u16::from_le_bytes([r.u8()?, r.u8()?]) as usize;
+        let bytes = r.bytes(len)?;
+        strings.push(std::str::from_utf8(bytes).map_err(|e| invalid(e.to_string()))?);
+    }
+    if r.pos != r.data.len() {
+        return Err(invalid("Bảng chuỗi dài hơn số chuỗi trong header"));
+    }
+    Ok((strings, &data[strings_end..]))
+}
+
+/// Đọc bundle thành `NpcResDb` đầy đủ (mọi cột, kể cả tên action không chuẩn) cho dump JSON
+/// và công cụ. Engine chỉ cần tra cứu thì dùng `NpcResView`, không phải dựng lại các HashMap.
+pub fn decode_npcres(data: &[u8]) -> io::Result<NpcResDb> {
+    let (strings, body) = open(data)?;
+    let mut r = Reader {
+        data: body,
+        pos: 0,
+        strings: &strings,
+    };
+    let mut db = NpcResDb::default();
+    for _ in 0..r.u32()? {
+        let name = r.str()?;
+        let entry = r.entry()?;
+        db.chars.insert(name, entry);
+    }
+    r.finish()?;
+    Ok(db)
+}
+
+// bộ phận -> item -> tên action -> spr
+type ComponentTable = HashMap<String, HashMap<String, HashMap<String, String>>>;
+
+// Tên action -> ô trong bảng dày. Tên chuẩn thắng tên phụ ("Jump" cho JumpFly) khi cả hai
+// cùng có trong bảng
+fn fill<T>(cell: &mut Option<T>, value: T, exact: bool) {
+    if exact || cell.is_none() {
+        *cell = Some(value);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    strings: &'a [&'a str],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Bundle bị cắt cụt"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        if self.pos != self.data.len() {
+            return Err(invalid("Bundle thừa dữ liệu sau nhân vật cuối"));
+        }
+        Ok(())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    /// Id chuỗi, đã kiểm tra nằm trong bảng chuỗi
+    fn id(&mut self) -> io::Result<StrId> {
+        let id = self.u32()?;
+        if id as usize >= self.strings.len() {
+            return Err(invalid(format!("Id chuỗi {} ngoài bảng chuỗi", id)));
+        }
+        Ok(id)
+    }
+
+    fn str(&mut self) -> io::Result<String> {
+        let id = self.id()?;
+        Ok(self.strings[id as usize].to_string())
+    }
+
+    fn key_name(&mut self) -> io::Result<&'a str> {
+        let tag = self.u8()?;
+        let name = match tag {
+            KEY_STRING => return Ok(self.strings[self.id()? as usize]),
+            KEY_NPC_ACTION.. => NpcAction::ALL
+                .get((tag - KEY_NPC_ACTION) as usize)
+                .map(|a| a.name()),
+            _ => ActionId::from_index(tag).map(|a| a.name()),
+        };
+        name.ok_or_else(|| invalid(format!("Mã action lạ {:#04x}", tag)))
+    }
+
+    fn key(&mut self) -> io::Result<String> {
+        Ok(self.key_name()?.to_string())
+    }
+
+    fn map<V>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> io::Result<(String, V)>,
+    ) -> io::Result<HashMap<String, V>> {
+        let count = self.u32()? as usize;
+        // count lấy từ file: không cấp phát trước quá số byte còn lại
+        let mut map = HashMap::with_capacity(count.min(self.data.len() - self.pos));
+        for _ in 0..count {
+            let (key, value) = read(self)?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+
+    /// Duyệt map không dựng HashMap (cho `NpcResView`)
+    fn each(&mut self, mut read: impl FnMut(&mut Self) -> io::Result<()>) -> io::Result<()> {
+        for _ in 0..self.u32()? {
+            read(self)?;
+        }
+        Ok(())
+    }
+
+    fn spr_info(&mut self) -> io::Result<ActionSprInfo> {
+        Ok(ActionSprInfo {
+            spr: self.str()?,
+            frames: self.u32()?,
+            directions: self.u32()?,
+            interval: self.u32()?,
+        })
+    }
+
+    fn spr_ref(&mut self) -> io::Result<SprRef> {
+        Ok(SprRef {
+            spr: self.id()?,
+            frames: self.u32()?,
+            directions: self.u32()?,
+            interval: self.u32()?,
+        })
+    }
+
+    fn components(&mut self) -> io::Result<ComponentTable> {
+        self.map(|r| {
+            let slot = r.str()?;
+            let items = r.map(|r| {
+                let item = r.str()?;
+                let mask = r.u64()?;
+                let mut actions = HashMap::new();
+                for (i, action) in ActionId::ALL.iter().enumerate() {
+                    if mask & (1 << i) != 0 {
+                        actions.insert(action.name().to_string(), r.str()?);
+                    }
+                }
+                for _ in 0..r.u32()? {
+                    actions.insert(r.str()?, r.str()?);
+                }
+                Ok((item, actions))
+            })?;
+            Ok((slot, items))
+        })
+    }
+
+    fn part_list(&mut self) -> io::Result<Vec<EquipCategory>> {
+        let mut part_list = Vec::new();
+        for _ in 0..self.u32()? {
+            let index = self.u32()?;
+            let name = self.str()?;
+            let mut slots = Vec::new();
+            for _ in 0..self.u8()? {
+                let i = self.u8()?;
+                let slot = PartSlot::ALL
+                    .get(i as usize)
+                    .ok_or_else(|| invalid(format!("Mã bộ phận lạ {}", i)))?;
+                slots.push(*slot);
+            }
+            part_list.push(EquipCategory { index, name, slots });
+        }
+        Ok(part_list)
+    }
+
+    fn entry(&mut self) -> io::Result<NpcResEntry> {
+        let kind = self.str()?;
+        let root_path = self.str()?;
+
+        let weapon_logic = self.map(|r| {
+            let mode = r.str()?;
+            let weapons = r.map(|r| {
+                let weapon = r.str()?;
+                let commands = r.map(|r| Ok((r.key()?, r.key()?)))?;
+                Ok((weapon, commands))
+            })?;
+            Ok((mode, weapons))
+        })?;
+
+        let components = self.components()?;
+
+        let part_rows = self.map(|r| {
+            let slot = r.str()?;
+            let count = r.u32()?;
+            let rows = (0..count).map(|_| r.str()).collect::<io::Result<_>>()?;
+            Ok((slot, rows))
+        })?;
+
+        let render_order = self.map(|r| {
+            let section = r.str()?;
+            let keys = r.map(|r| Ok((r.str()?, r.str()?)))?;
+            Ok((section, keys))
+        })?;
+        let shadows = self.map(|r| Ok((r.key()?, r.spr_info()?)))?;
+        let actions = self.map(|r| Ok((r.key()?, r.spr_info()?)))?;
+        let sounds = self.map(|r| Ok((r.key()?, r.str()?)))?;
+        let part_list = self.part_list()?;
+
+        Ok(NpcResEntry {
+            kind,
+            root_path,
+            weapon_logic,
+            components,
+            part_rows,
+            render_order,
+            shadows,
+            actions,
+            sounds,
+            part_list,
+        })
+    }
+
+    /// Bảng dày của một nhân vật: cùng thứ tự đọc với `entry` nhưng chỉ giữ id chuỗi.
+    /// Tên action tra bằng `from_name` như `NpcResEntry`; bộ phận / chế độ / tên action lạ
+    /// bị bỏ qua (chỉ `decode_npcres` giữ).
+    fn tables(&mut self, name: StrId) -> io::Result<CharTables> {
+        let strings = self.strings;
+        let slot_of = |id: StrId| {
+            PartSlot::ALL
+                .into_iter()
+                .find(|s| s.component_key() == strings[id as usize])
+        };
+        let mut t = CharTables {
+            name,
+            kind: self.id()?,
+            root_path: self.id()?,
+            weapons: [None, None],
+            parts: std::array::from_fn(|_| Vec::new()),
+            part_rows: std::array::from_fn(|_| Vec::new()),
+            render_order: Vec::new(),
+            shadows: [None; ActionId::ALL.len()],
+            npc_sprs: [None; NpcAction::ALL.len()],
+            sounds: [None; ActionId::ALL.len()],
+            npc_sounds: [None; NpcAction::ALL.len()],
+        };
+
+        self.each(|r| {
+            let mode = match strings[r.id()? as usize] {
+                "unmounted" => Some(0),
+                "mounted" => Some(1),
+                _ => None,
+            };
+            let mut rows = Vec::new();
+            r.each(|r| {
+                let mut row = WeaponRow {
+                    weapon: r.id()?,
+                    actions: [None; NpcAction::ALL.len()],
+                };
+                r.each(|r| {
+                    let (command, action) = (r.key_name()?, r.key_name()?);
+                    if let (Some(c), Some(a)) =
+                        (NpcAction::from_name(command), ActionId::from_name(action))
+                    {
+                        fill(&mut row.actions[c as usize], a, c.name() == command);
+                    }
+                    Ok(())
+                })?;
+                rows.push(row);
+                Ok(())
+            })?;
+            if let Some(mode) = mode {
+                t.weapons[mode] = Some(rows);
+            }
+            Ok(())
+        })?;
+
+        self.each(|r| {
+            let slot = slot_of(r.id()?);
+            r.each(|r| {
+                let mut item = PartItem {
+                    name: r.id()?,
+                    sprs: [None; ActionId::ALL.len()],
+                };
+                let mask = r.u64()?;
+                for (i, spr) in item.sprs.iter_mut().enumerate() {
+                    if mask & (1 << i) != 0 {
+                        *spr = Some(r.id()?);
+                    }
+                }
+                // cột tên không chuẩn ("Jump") ghi sau các cột chuẩn nên chỉ lấp ô còn trống
+                r.each(|r| {
+                    let (name, spr) = (r.id()?, r.id()?);
+                    if let Some(a) = ActionId::from_name(strings[name as usize]) {
+                        fill(&mut item.sprs[a as usize], spr, false);
+                    }
+                    Ok(())
+                })?;
+                if let Some(slot) = slot {
+                    t.parts[slot as usize].push(item);
+                }
+                Ok(())
+            })
+        })?;
+
+        self.each(|r| {
+            let slot = slot_of(r.id()?);
+            let rows = (0..r.u32()?).map(|_| r.id()).collect::<io::Result<_>>()?;
+            if let Some(slot) = slot {
+                t.part_rows[slot as usize] = rows;
+            }
+            Ok(())
+        })?;
+
+        self.each(|r| {
+            let section = r.id()?;
+            let mut keys = Vec::new();
+            r.each(|r| {
+                keys.push((r.id()?, r.id()?));
+                Ok(())
+            })?;
+            t.render_order.push((section, keys));
+            Ok(())
+        })?;
+        self.each(|r| {
+            let (name, info) = (r.key_name()?, r.spr_ref()?);
+            if let Some(a) = ActionId::from_name(name) {
+                fill(&mut t.shadows[a as usize], info, a.name() == name);
+            }
+            Ok(())
+        })?;
+        self.each(|r| {
+            let (name, info) = (r.key_name()?, r.spr_ref()?);
+            if let Some(a) = NpcAction::from_name(name) {
+                fill(&mut t.npc_sprs[a as usize], info, a.name() == name);
+            }
+            Ok(())
+        })?;
+        // NormalWalk, SitDown... vừa là ActionId vừa là NpcAction: tra được theo cả hai
+        self.each(|r| {
+            let (name, wav) = (r.key_name()?, r.id()?);
+            if let Some(a) = ActionId::from_name(name) {
+                fill(&mut t.sounds[a as usize], wav, a.name() == name);
+            }
+            if let Some(a) = NpcAction::from_name(name) {
+                fill(&mut t.npc_sounds[a as usize], wav, a.name() == name);
+            }
+            Ok(())
+        })?;
+        // danh sách loại trang bị chỉ dùng cho UI/công cụ
+        for _ in 0..self.u32()? {
+            self.u32()?;
+            self.id()?;
+            let n = self.u8()?;
+            self.bytes(n as usize)?;
+        }
+        Ok(t)
+    }
+}
+
+// --- VIEW MƯỢN DỮ LIỆU BUNDLE ---
+// Engine mở bundle bằng `NpcResView::parse`: chuỗi là `&str` trỏ thẳng vào bảng chuỗi của
+// file, mỗi nhân vật là các bảng dày đánh index theo `ActionId` / `NpcAction` / `PartSlot`
+// chứa id chuỗi. Chỉ một lượt đọc tuần tự, không cấp phát chuỗi hay HashMap nào.
+
+/// Id trong bảng chuỗi của bundle
+pub type StrId = u32;
+
+#[derive(Debug, Clone, Copy)]
+struct SprRef {
+    spr: StrId,
+    frames: u32,
+    directions: u32,
+    interval: u32,
+}
+
+// Một dòng bảng vũ khí: NpcAction -> ActionId
+struct WeaponRow {
+    weapon: StrId,
+    actions: [Option<ActionId>; NpcAction::ALL.len()],
+}
+
+// Một item của bộ phận: ActionId -> spr
+struct PartItem {
+    name: StrId,
+    sprs: [Option<StrId>; ActionId::ALL.len()],
+}
+
+struct CharTables {
+    name: StrId,
+    kind: StrId,
+    root_path: StrId,
+    // [unmounted, mounted], None khi nhân vật không có bảng vũ khí cho chế độ đó;
+    // dòng theo tên vũ khí đã sắp xếp
+    weapons: [Option<Vec<WeaponRow>>; 2],
+    // theo `PartSlot`, item theo tên đã sắp xếp
+    parts: [Vec<PartItem>; PartSlot::ALL.len()],
+    part_rows: [Vec<StrId>; PartSlot::ALL.len()],
+    render_order: Vec<(StrId, Vec<(StrId, StrId)>)>,
+    shadows: [Option<SprRef>; ActionId::ALL.len()],
+    npc_sprs: [Option<SprRef>; NpcAction::ALL.len()],
+    sounds: [Option<StrId>; ActionId::ALL.len()],
+    npc_sounds: [Option<StrId>; NpcAction::ALL.len()],
+}
+
+/// Bundle npcres đã kiểm tra, mượn dữ liệu từ `&'a [u8]` của file
+pub struct NpcResView<'a> {
+    strings: Vec<&'a str>,
+    // theo tên đã sắp xếp (thứ tự ghi của `encode_npcres`)
+    chars: Vec<CharTables>,
+}
+
+impl<'a> NpcResView<'a> {
+    pub fn parse(data: &'a [u8]) -> io::Result<Self> {
+        let (strings, body) = open(data)?;
+        let mut r = Reader {
+            data: body,
+            pos: 0,
+            strings: &strings,
+        };
+        let count = r.u32()? as usize;
+        let mut chars = Vec::with_capacity(count.min(body.len()));
+        for _ in 0..count {
+            let name = r.id()?;
+            chars.push(r.tables(name)?);
+        }
+        r.finish()?;
+        Ok(NpcResView { strings, chars })
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Tên các nhân vật theo thứ tự đã sắp xếp
+    pub fn names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.chars.iter().map(|c| self.strings[c.name as usize])
+    }
+
+    pub fn get(&self, name: &str) -> Option<CharacterView<'_, 'a>> {
+        let i = self
+            .chars
+            .binary_search_by(|c| self.strings[c.name as usize].cmp(name))
+            .ok()?;
+        Some(CharacterView {
+            strings: &self.strings,
+            tables: &self.chars[i],
+        })
+    }
+}
+
+/// Spr của một action đọc từ bundle (giống `ActionSprInfo`, đường dẫn mượn từ bundle)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SprView<'a> {
+    pub spr: &'a str,
+    pub frames: u32,
+    pub directions: u32,
+    pub interval: u32,
+}
+
+impl SprView<'_> {
+    pub fn frames_per_direction(&self) -> u32 {
+        (self.frames / self.directions.max(1)).max(1)
+    }
+}
+
+/// Một nhân vật trong `NpcResView`, cùng các hàm tra cứu như `NpcResEntry`
+#[derive(Clone, Copy)]
+pub struct CharacterView<'v, 'a> {
+    strings: &'v [&'a str],
+    tables: &'v CharTables,
+}
+
+impl<'v, 'a> CharacterView<'v, 'a> {
+    fn str(&self, id: StrId) -> &'a str {
+        self.strings[id as usize]
+    }
+
+    fn spr(&self, info: Option<SprRef>) -> Option<SprView<'a>> {
+        info.map(|info| SprView {
+            spr: self.str(info.spr),
+            frames: info.frames,
+            directions: info.directions,
+            interval: info.interval,
+        })
+    }
+
+    fn item(&self, slot: PartSlot, item: &str) -> Option<&'v PartItem> {
+        let items = &self.tables.parts[slot as usize];
+        let i = items
+            .binary_search_by(|it| self.str(it.name).cmp(item))
+            .ok()?;
+        Some(&items[i])
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.str(self.tables.name)
+    }
+
+    pub fn kind(&self) -> &'a str {
+        self.str(self.tables.kind)
+    }
+
+    pub fn root_path(&self) -> &'a str {
+        self.str(self.tables.root_path)
+    }
+
+    pub fn is_special(&self) -> bool {
+        self.kind() == "SpecialNpc"
+    }
+
+    /// Danh sách item của một bộ phận (đã sắp xếp)
+    pub fn part_items(&self, slot: PartSlot) -> impl Iterator<Item = &'a str> + 'v {
+        let strings = self.strings;
+        self.tables.parts[slot as usize]
+            .iter()
+            .map(move |it| strings[it.name as usize])
+    }
+
+    /// Tên item ở dòng `res_id` của bảng bộ phận `slot`
+    pub fn part_by_res_id(&self, slot: PartSlot, res_id: u32) -> Option<&'a str> {
+        let id = *self.tables.part_rows[slot as usize].get(res_id as usize)?;
+        Some(self.str(id)).filter(|s| !s.is_empty())
+    }
+
+    pub fn has_part_item(&self, slot: PartSlot, item: &str) -> bool {
+        self.item(slot, item).is_some()
+    }
+
+    /// Đường dẫn spr của `item` thuộc bộ phận `slot` khi đang diễn `action`
+    pub fn part_spr_path(&self, slot: PartSlot, item: &str, action: ActionId) -> Option<&'a str> {
+        let spr = self.item(slot, item)?.sprs[action as usize]?;
+        Some(self.str(spr))
+    }
+
+    pub fn action_sound(&self, action: ActionId) -> Option<&'a str> {
+        self.tables.sounds[action as usize].map(|id| self.str(id))
+    }
+
+    pub fn npc_action_sound(&self, action: NpcAction) -> Option<&'a str> {
+        self.tables.npc_sounds[action as usize].map(|id| self.str(id))
+    }
+
+    pub fn shadow(&self, action: ActionId) -> Option<SprView<'a>> {
+        self.spr(self.tables.shadows[action as usize])
+    }
+
+    pub fn npc_action_spr(&self, action: NpcAction) -> Option<SprView<'a>> {
+        self.spr(self.tables.npc_sprs[action as usize])
+    }
+
+    /// Thứ tự vẽ layer; bảng nhỏ nên dựng `RenderOrder` một lần khi nạp nhân vật
+    pub fn render_order(&self) -> RenderOrder {
+        let sections = self
+            .tables
+            .render_order
+            .iter()
+            .map(|(section, keys)| {
+                let keys = keys
+                    .iter()
+                    .map(|&(k, v)| (self.str(k).to_string(), self.str(v).to_string()))
+                    .collect();
+                (self.str(*section).to_string(), keys)
+            })
+            .collect();
+        RenderOrder::from_sections(&sections)
+    }
+}
+
+impl ActionSet for CharacterView<'_, '_> {
+    /// Giống `NpcResEntry::resolve`: vũ khí lạ coi như tay không, không có bảng vũ khí thì
+    /// dùng dòng tay không mặc định
+    fn resolve(&self, weapon: &str, mounted: bool, action: NpcAction) -> Option<ActionId> {
+        let Some(rows) = &self.tables.weapons[mounted as usize] else {
+            return action.default_action(mounted);
+        };
+        let find = |name: &str| {
+            rows.binary_search_by(|row| self.str(row.weapon).cmp(name))
+                .ok()
+        };
+        let i = find(weapon).or_else(|| find(UNARMED_WEAPON))?;
+        rows[i].actions[action as usize]
+    }
+
+    fn timing(&self, action: NpcAction, state: ActionId) -> Option<ActionTiming> {
+        if self.is_special() {
+            let shadow = self.shadow(state)?;
+            return Some(ActionTiming {
+                frames: shadow.frames_per_direction(),
+                interval: shadow.interval.max(1),
+            });
+        }
+        let info = self.npc_action_spr(action)?;
+        Some(ActionTiming {
+            frames: info.frames_per_direction(),
+            interval: ((info.interval * GAME_FPS + 500) / 1000).max(1),
+        })
+    }
+}
+
+// --- CRC32 (IEEE, giống zlib) ---
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32(0xFFFF_FFFF)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.0 = CRC32_TABLE[((self.0 ^ b as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_db() -> NpcResDb {
+        let json = r#"{"schema_version": 2, "characters": {
+            "MainLady": {"type": "SpecialNpc", "root_path": "/spr/npcres/woman",
+                "weapon_logic": {"unmounted": {"Tay không": {"FightStand": "FreeStand1"}}},
+                "components": {"body": {"Thân thể 01": {
+                    "FreeStand1": "/spr/npcres/woman/a.spr", "Jump": "/spr/npcres/woman/j.spr"}}},
+                "part_rows": {"body": ["Thân thể 01", ""]},
+                "render_order": {"DEFAULT": {"Dir1": "-1,14,13,"}},
+                "shadows": {"FreeStand1": {"spr": "/spr/npcres/woman/s.spr",
+                    "frames": 8, "directions": 8, "interval": 1}},
+                "sounds": {"FreeStand1": "sound_m1.wav"},
+                "part_list": [{"index": 0, "name": "Đầu khôi", "slots": ["Head", "RightHead"]}]},
+            "ani001": {"type": "NormalNpc", "root_path": "/spr/npcres/animal/ani001",
+                "actions": {"Die": {"spr": "/spr/npcres/animal/ani001/die.spr",
+                    "frames": 8, "directions": 8, "interval": 100},
+                    "NormalWalk": {"spr": "/spr/npcres/animal/ani001/walk.spr",
+                    "frames": 16, "directions": 8, "interval": 85}},
+                "sounds": {"Die": "sound_a001_die.wav"}}
+        }}"#;
+        NpcResDb::from_reader(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn bundle_round_trips_and_is_deterministic() {
+        let db = sample_db();
+        let bytes = encode_npcres(&db).unwrap();
+        assert_eq!(encode_npcres(&db).unwrap(), bytes);
+        assert_eq!(decode_npcres(&bytes).unwrap(), db);
+
+        let header = BundleHeader::parse(&bytes).unwrap();
+        assert_eq!(header.version, BUNDLE_VERSION);
+        assert_eq!(
+            BUNDLE_HEADER_SIZE + (header.strings_len + header.body_len) as usize,
+            bytes.len()
+        );
+    }
+
+    #[test]
+    fn view_answers_like_the_decoded_entry() {
+        let db = sample_db();
+        let bytes = encode_npcres(&db).unwrap();
+        let view = NpcResView::parse(&bytes).unwrap();
+        assert_eq!(view.names().collect::<Vec<_>>(), ["MainLady", "ani001"]);
+        assert!(view.get("Không có").is_none());
+
+        let entry = db.get("MainLady").unwrap();
+        let lady = view.get("MainLady").unwrap();
+        assert_eq!(lady.root_path(), "/spr/npcres/woman");
+        assert!(lady.is_special());
+        assert_eq!(
+            lady.part_items(PartSlot::Body).collect::<Vec<_>>(),
+            ["Thân thể 01"]
+        );
+        assert_eq!(lady.part_by_res_id(PartSlot::Body, 0), Some("Thân thể 01"));
+        assert_eq!(lady.part_by_res_id(PartSlot::Body, 1), None);
+        for action in ActionId::ALL {
+            assert_eq!(
+                lady.part_spr_path(PartSlot::Body, "Thân thể 01", action),
+                entry.part_spr_path(PartSlot::Body, "Thân thể 01", action)
+            );
+            assert_eq!(lady.action_sound(action), entry.action_sound(action));
+            assert_eq!(
+                lady.shadow(action).map(|s| s.spr),
+                entry.shadow(action).map(|s| s.spr.as_str())
+            );
+        }
+        for action in NpcAction::ALL {
+            for weapon in ["Tay không", "Kiếm lạ"] {
+                for mounted in [false, true] {
+                    assert_eq!(
+                        lady.resolve(weapon, mounted, action),
+                        entry.resolve(weapon, mounted, action)
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            lady.render_order(),
+            RenderOrder::from_sections(&entry.render_order)
+        );
+
+        let entry = db.get("ani001").unwrap();
+        let npc = view.get("ani001").unwrap();
+        for action in NpcAction::ALL {
+            assert_eq!(npc.npc_action_sound(action), entry.npc_action_sound(action));
+            assert_eq!(
+                npc.timing(action, ActionId::FreeStand1),
+                entry.timing(action, ActionId::FreeStand1)
+            );
+        }
+        assert_eq!(
+            npc.timing(NpcAction::Die, ActionId::FreeStand1)
+                .unwrap()
+                .frames,
+            1
+        );
+    }
+
+    #[test]
+    fn bundle_rejects_corruption_and_other_versions() {
+        let bytes = encode_npcres(&sample_db()).unwrap();
+
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(decode_npcres(&corrupt).is_err());
+
+        let mut future = bytes.clone();
+        future[4] = 99;
+        assert!(decode_npcres(&future).is_err());
+
+        assert!(decode_npcres(&bytes[..bytes.len() - 1]).is_err());
+        assert!(NpcResView::parse(&corrupt).is_err());
+
+        // cờ lạ trong header: từ chối dù checksum vẫn đúng (checksum không phủ header)
+        let mut flagged = bytes.clone();
+        flagged[6] = 0x01;
+        assert!(decode_npcres(&flagged).is_err());
+        assert!(NpcResView::parse(&flagged).is_err());
+        assert!(decode_npcres(b"{}").is_err());
+
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+}
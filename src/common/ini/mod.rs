@@ -0,0 +1,504 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::common::pak::PakSet;
+use crate::common::tabfile::parse_int_list;
+use crate::common::text::{self, Encoding};
+
+// --- FILE INI (tương đương KIniFile) ---
+// `[Section]` rồi các dòng `key=value`. Dòng chú thích (`;`, `#`, `//`), dòng trống và dòng
+// không hiểu được giữ nguyên để ghi lại file không làm mất gì. Tra section / key không phân
+// biệt hoa thường như client ("MAIN" và "Main" trong file .wor là một).
+// Key trùng bị bỏ qua được giữ lại thành dòng thô, section lặp lại giữ nguyên header và vị
+// trí của nó, nên đọc rồi ghi lại không làm mất dòng nào.
+
+/// Xử lý key trùng trong cùng section khi đọc file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Giữ giá trị đầu tiên như KIniFile (tra từ đầu danh sách)
+    #[default]
+    First,
+    /// Giá trị sau ghi đè giá trị trước
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    Entry(String, String),
+    // Chú thích, dòng trống, key trùng bị bỏ qua...: chỉ dùng khi ghi lại
+    Raw(String),
+}
+
+/// Một section, các dòng theo đúng thứ tự trong file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IniSection {
+    name: String,
+    lines: Vec<Line>,
+}
+
+impl IniSection {
+    fn new(name: &str) -> Self {
+        IniSection {
+            name: name.to_string(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Tên section như trong file ("" là phần trước section đầu tiên)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Các cặp key / value theo thứ tự trong file
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry(key, value) => Some((key.as_str(), value.as_str())),
+            Line::Raw(_) => None,
+        })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries().map(|(key, _)| key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().next().is_none()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i32> {
+        self.get(key)?.parse().ok()
+    }
+
+    pub fn get_float(&self, key: &str) -> Option<f32> {
+        self.get(key)?.parse().ok()
+    }
+
+    /// "1" / "true" / "yes" là bật, mọi giá trị khác là tắt
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        let value = self.get(key)?;
+        Some(
+            value == "1" || value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("yes"),
+        )
+    }
+
+    /// "a,b,c," -> [a, b, c] (bỏ phần tử trống / không phải số, kể cả dấu phẩy thừa cuối dòng)
+    pub fn get_int_list(&self, key: &str) -> Vec<i32> {
+        self.get(key).map(parse_int_list).unwrap_or_default()
+    }
+
+    /// "trái,trên,phải,dưới"
+    pub fn get_rect(&self, key: &str) -> Option<(i32, i32, i32, i32)> {
+        match self.get_int_list(key)[..] {
+            [left, top, right, bottom] => Some((left, top, right, bottom)),
+            _ => None,
+        }
+    }
+
+    /// Màu RGBA: "r,g,b", "r,g,b,a" hoặc hex "#rrggbb" / "0xrrggbb" (alpha mặc định 255)
+    pub fn get_color(&self, key: &str) -> Option<[u8; 4]> {
+        parse_color(self.get(key)?)
+    }
+
+    /// Đặt giá trị: sửa key đã có, không thì thêm vào cuối section
+    pub fn set(&mut self, key: &str, value: &str) {
+        for line in &mut self.lines {
+            if let Line::Entry(k, v) = line
+                && k.eq_ignore_ascii_case(key)
+            {
+                *v = value.to_string();
+                return;
+            }
+        }
+        // Chèn trước các dòng trống cuối section để file ghi ra vẫn cách đoạn như cũ
+        let at = self
+            .lines
+            .iter()
+            .rposition(|line| !matches!(line, Line::Raw(raw) if raw.is_empty()))
+            .map_or(0, |i| i + 1);
+        self.lines
+            .insert(at, Line::Entry(key.to_string(), value.to_string()));
+    }
+
+    /// Xoá mọi dòng của `key`, `true` nếu có xoá
+    pub fn remove(&mut self, key: &str) -> bool {
+        let before = self.lines.len();
+        self.lines
+            .retain(|line| !matches!(line, Line::Entry(k, _) if k.eq_ignore_ascii_case(key)));
+        self.lines.len() != before
+    }
+}
+
+fn parse_color(value: &str) -> Option<[u8; 4]> {
+    let value = value.trim();
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .or_else(|| value.strip_prefix("0X"));
+    if let Some(hex) = hex {
+        if hex.len() != 6 {
+            return None;
+        }
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        return Some([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255]);
+    }
+
+    let parts: Vec<u8> = value
+        .split(',')
+        .map(|s| s.trim().parse::<u8>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match parts[..] {
+        [r, g, b] => Some([r, g, b, 255]),
+        [r, g, b, a] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+/// File ini đã đọc vào bộ nhớ, giữ thứ tự section / key để ghi lại
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IniFile {
+    // sections[0] luôn là phần trước section đầu tiên (tên rỗng)
+    sections: Vec<IniSection>,
+}
+
+impl Default for IniFile {
+    fn default() -> Self {
+        IniFile {
+            sections: vec![IniSection::new("")],
+        }
+    }
+}
+
+impl IniFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        Self::from_text_with(text, DuplicateKeys::default())
+    }
+
+    /// Đọc với cách xử lý key trùng tuỳ chọn. Section lặp lại (kể cả khác hoa thường) được
+    /// giữ riêng với header của nó, key trong đó tính chung với lần xuất hiện đầu.
+    pub fn from_text_with(text: &str, duplicates: DuplicateKeys) -> Self {
+        let mut ini = IniFile::new();
+
+        for raw in text.lines() {
+            let line = raw.trim();
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim();
+                ini.sections.push(IniSection::new(name));
+                continue;
+            }
+
+            let current = ini.sections.len() - 1;
+            let is_comment =
+                line.starts_with(';') || line.starts_with('#') || line.starts_with("//");
+            let entry = match line.split_once('=') {
+                Some((key, value)) if !is_comment && !key.trim().is_empty() => {
+                    Line::Entry(key.trim().to_string(), value.trim().to_string())
+                }
+                _ => Line::Raw(raw.trim_end().to_string()),
+            };
+            if let Line::Entry(key, _) = &entry
+                && let Some((i, at)) = ini.find_entry(&ini.sections[current].name, key)
+            {
+                match duplicates {
+                    // Dòng sau không có tác dụng nhưng vẫn được ghi lại
+                    DuplicateKeys::First => {
+                        ini.sections[current]
+                            .lines
+                            .push(Line::Raw(raw.trim_end().to_string()));
+                        continue;
+                    }
+                    // Dòng trước thành dòng thô, dòng sau là giá trị dùng
+                    DuplicateKeys::Last => {
+                        let old = &mut ini.sections[i].lines[at];
+                        if let Line::Entry(k, v) = old {
+                            *old = Line::Raw(format!("{}={}", k, v));
+                        }
+                    }
+                }
+            }
+            ini.sections[current].lines.push(entry);
+        }
+        ini
+    }
+
+    /// Bảng mã tự nhận diện (UTF-8 / GBK, không chắc thì Windows-1258), xem `text::detect`
+    pub fn from_bytes(data: &[u8]) -> Self {
+        Self::from_text(&text::decode_auto(data))
+    }
+
+    /// Bảng mã chỉ định cho nguồn dữ liệu (client Big5, TCVN3...)
+    pub fn from_bytes_as(data: &[u8], encoding: Encoding) -> Self {
+        Self::from_text(&text::decode(data, encoding))
+    }
+
+    /// Đọc từ đĩa
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    /// Đọc từ đĩa với bảng mã chỉ định
+    pub fn load_as<P: AsRef<Path>>(path: P, encoding: Encoding) -> io::Result<Self> {
+        Ok(Self::from_bytes_as(&fs::read(path)?, encoding))
+    }
+
+    /// Đọc từ pak theo đường dẫn game (`\settings\...`), theo bảng mã của `paks`
+    pub fn load_pak(paks: &mut PakSet, path: &str) -> io::Result<Self> {
+        let data = paks.read(path)?.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("Không tìm thấy {}", path))
+        })?;
+        Ok(Self::from_text(&text::decode_with(&data, paks.encoding)))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .position(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    // (section, dòng) của key trong mọi lần xuất hiện của section `name`
+    fn find_entry(&self, name: &str, key: &str) -> Option<(usize, usize)> {
+        self.sections.iter().enumerate().find_map(|(i, s)| {
+            if !s.name.eq_ignore_ascii_case(name) {
+                return None;
+            }
+            s.lines
+                .iter()
+                .position(|line| matches!(line, Line::Entry(k, _) if k.eq_ignore_ascii_case(key)))
+                .map(|at| (i, at))
+        })
+    }
+
+    /// Các section có tên theo thứ tự trong file (section lặp lại xuất hiện nhiều lần)
+    pub fn sections(&self) -> impl Iterator<Item = &IniSection> {
+        self.sections.iter().skip(1)
+    }
+
+    /// Lần xuất hiện đầu của section; "" là các key nằm trước section đầu tiên.
+    /// Các getter của `IniFile` tra cả những lần section lặp lại.
+    pub fn section(&self, name: &str) -> Option<&IniSection> {
+        self.position(name).map(|i| &self.sections[i])
+    }
+
+    /// Section theo tên, tạo mới ở cuối file nếu chưa có
+    pub fn section_mut(&mut self, name: &str) -> &mut IniSection {
+        let index = match self.position(name) {
+            Some(i) => i,
+            None => {
+                self.sections.push(IniSection::new(name));
+                self.sections.len() - 1
+            }
+        };
+        &mut self.sections[index]
+    }
+
+    /// Xoá mọi lần xuất hiện của section
+    pub fn remove_section(&mut self, name: &str) -> bool {
+        let before = self.sections.len();
+        let head = self.sections[0].name.eq_ignore_ascii_case(name);
+        if head {
+            self.sections[0].lines.clear();
+        }
+        let mut first = true;
+        self.sections.retain(|s| {
+            // Giữ phần trước section đầu tiên (luôn là sections[0])
+            std::mem::take(&mut first) || !s.name.eq_ignore_ascii_case(name)
+        });
+        head || self.sections.len() != before
+    }
+
+    // Section chứa key (lần xuất hiện đầu của section nếu chưa có key)
+    fn holder(&self, section: &str, key: &str) -> Option<&IniSection> {
+        match self.find_entry(section, key) {
+            Some((i, _)) => Some(&self.sections[i]),
+            None => self.section(section),
+        }
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.holder(section, key)?.get(key)
+    }
+
+    pub fn get_int(&self, section: &str, key: &str) -> Option<i32> {
+        self.holder(section, key)?.get_int(key)
+    }
+
+    pub fn get_float(&self, section: &str, key: &str) -> Option<f32> {
+        self.holder(section, key)?.get_float(key)
+    }
+
+    pub fn get_bool(&self, section: &str, key: &str) -> Option<bool> {
+        self.holder(section, key)?.get_bool(key)
+    }
+
+    pub fn get_int_list(&self, section: &str, key: &str) -> Vec<i32> {
+        self.holder(section, key)
+            .map(|s| s.get_int_list(key))
+            .unwrap_or_default()
+    }
+
+    pub fn get_rect(&self, section: &str, key: &str) -> Option<(i32, i32, i32, i32)> {
+        self.holder(section, key)?.get_rect(key)
+    }
+
+    pub fn get_color(&self, section: &str, key: &str) -> Option<[u8; 4]> {
+        self.holder(section, key)?.get_color(key)
+    }
+
+    /// Sửa key ở nơi nó đang nằm (kể cả section lặp lại), không thì thêm vào lần đầu
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        match self.find_entry(section, key) {
+            Some((i, _)) => self.sections[i].set(key, value),
+            None => self.section_mut(section).set(key, value),
+        }
+    }
+
+    /// Nội dung file (xuống dòng CRLF như file của client), giữ thứ tự và chú thích
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                out.push_str(&format!("[{}]\r\n", section.name));
+            }
+            for line in &section.lines {
+                match line {
+                    Line::Entry(key, value) => out.push_str(&format!("{}={}\r\n", key, value)),
+                    Line::Raw(raw) => out.push_str(&format!("{}\r\n", raw)),
+                }
+            }
+        }
+        out
+    }
+
+    /// Nội dung file theo bảng mã của client để ghi lại vào dữ liệu gốc
+    pub fn to_bytes(&self, encoding: Encoding) -> Vec<u8> {
+        text::encode(&self.to_text(), encoding)
+    }
+
+    /// Ghi ra đĩa (UTF-8)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "; cấu hình giao diện\r\n\
+                          [Main]\r\n\
+                          Rect=10,20,300,200\r\n\
+                          Color=255,128,0\r\n\
+                          Dir1=-1,14,13,\r\n\
+                          \r\n\
+                          [Font]\r\n\
+                          // cỡ chữ\r\n\
+                          Size=12\r\n\
+                          Size=16\r\n\
+                          Shadow=true\r\n";
+
+    #[test]
+    fn typed_getters_are_case_insensitive() {
+        let ini = IniFile::from_text(SAMPLE);
+        let names: Vec<&str> = ini.sections().map(|s| s.name()).collect();
+        assert_eq!(names, vec!["Main", "Font"]);
+
+        assert_eq!(ini.get_rect("MAIN", "rect"), Some((10, 20, 300, 200)));
+        assert_eq!(ini.get_color("Main", "Color"), Some([255, 128, 0, 255]));
+        assert_eq!(ini.get_int_list("Main", "Dir1"), vec![-1, 14, 13]);
+        assert_eq!(ini.get_bool("Font", "Shadow"), Some(true));
+        assert_eq!(ini.get_int("Font", "Missing"), None);
+        assert_eq!(ini.get("Nope", "Size"), None);
+
+        // Chú thích không thành key
+        assert_eq!(ini.section("Font").unwrap().len(), 2);
+        assert_eq!(parse_color("#00FF7f"), Some([0, 255, 127, 255]));
+        assert_eq!(parse_color("1,2,3,4"), Some([1, 2, 3, 4]));
+        assert_eq!(parse_color("1,2,300"), None);
+    }
+
+    #[test]
+    fn duplicate_keys_follow_policy() {
+        assert_eq!(IniFile::from_text(SAMPLE).get_int("Font", "Size"), Some(12));
+        let last = IniFile::from_text_with(SAMPLE, DuplicateKeys::Last);
+        assert_eq!(last.get_int("Font", "Size"), Some(16));
+        assert_eq!(
+            last.section("Font").unwrap().keys().collect::<Vec<_>>(),
+            ["Size", "Shadow"]
+        );
+
+        // Section lặp lại giữ riêng, key tra chung với lần đầu
+        let ini = IniFile::from_text("[A]\nx=1\n[B]\n[a]\ny=2\n");
+        assert_eq!(ini.sections().count(), 3);
+        assert_eq!(ini.get_int("A", "y"), Some(2));
+    }
+
+    #[test]
+    fn duplicates_survive_round_trip() {
+        let text = "[A]\r\nx=1\r\n[B]\r\nz=3\r\n[a]\r\nx=2\r\ny=2\r\n";
+        let first = IniFile::from_text(text);
+        assert_eq!(first.get_int("A", "x"), Some(1));
+        assert_eq!(first.get_int("a", "y"), Some(2));
+        assert_eq!(first.to_text(), text);
+        assert_eq!(IniFile::from_text(SAMPLE).to_text(), SAMPLE);
+
+        let last = IniFile::from_text_with(text, DuplicateKeys::Last);
+        assert_eq!(last.get_int("A", "x"), Some(2));
+        assert_eq!(last.to_text(), text);
+        let font = IniFile::from_text_with(SAMPLE, DuplicateKeys::Last);
+        assert_eq!(font.to_text(), SAMPLE);
+
+        // set sửa đúng dòng đang có tác dụng, đọc lại vẫn ra giá trị đó
+        let mut edited = last.clone();
+        edited.set("A", "x", "5");
+        assert!(edited.to_text().ends_with("[a]\r\nx=5\r\ny=2\r\n"));
+        let again = IniFile::from_text_with(&edited.to_text(), DuplicateKeys::Last);
+        assert_eq!(again.get_int("a", "X"), Some(5));
+
+        assert!(edited.remove_section("a"));
+        assert_eq!(edited.to_text(), "[B]\r\nz=3\r\n");
+    }
+
+    #[test]
+    fn write_back_keeps_order_and_comments() {
+        let mut ini = IniFile::from_text(SAMPLE);
+        ini.set("Main", "Color", "#FFFFFF");
+        ini.set("Main", "Visible", "1");
+        ini.set("New", "Key", "v");
+        assert!(ini.section_mut("Font").remove("Shadow"));
+
+        let text = ini.to_text();
+        assert!(text.starts_with("; cấu hình giao diện\r\n[Main]\r\nRect=10,20,300,200\r\n"));
+        assert!(text.contains("Dir1=-1,14,13,\r\nVisible=1\r\n\r\n[Font]\r\n// cỡ chữ\r\n"));
+        assert!(text.ends_with("[New]\r\nKey=v\r\n"));
+        assert!(!text.contains("Shadow"));
+
+        let again = IniFile::from_text(&text);
+        assert_eq!(again.get_color("main", "color"), Some([255, 255, 255, 255]));
+        assert_eq!(again.to_text(), text);
+
+        // Ghi lại bằng GBK cho client rồi đọc tự nhận diện
+        let mut gbk = IniFile::new();
+        gbk.set("List", "1_name", "西山村");
+        let bytes = gbk.to_bytes(Encoding::Gbk);
+        assert!(std::str::from_utf8(&bytes).is_err());
+        assert_eq!(
+            IniFile::from_bytes(&bytes).get("list", "1_NAME"),
+            Some("西山村")
+        );
+    }
+}
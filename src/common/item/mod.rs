@@ -0,0 +1,923 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::common::npc::{EquipError, Equipment, PartSlot, UNARMED_WEAPON};
+use crate::common::npcres::NpcResEntry;
+use crate::common::pak::PakSet;
+use crate::common::tabfile::{RowError, TabFile, TabRow};
+
+// --- BẢNG VẬT PHẨM (settings\item\*.txt) ---
+// Mỗi loại trang bị một bảng (meleeweapon.txt, armor.txt...), mỗi dòng một item.
+// Item được engine xác định bằng (genre, detail type, particular type, level); cột ResID là
+// dòng trong bảng bộ phận của nhân vật chính (0 = dòng đầu, vd "Tay không" / "Thân thể 01").
+//
+// Cột được tra theo tên ở dòng header của bảng client, không theo vị trí, nên bảng của các
+// bản game có thêm / bớt cột vẫn đọc được; cột thiếu coi như ô trống. Các cột dùng tới:
+// Name, ImageName, ItemGenre, DetailType, ParticularType, Level, Series, ResID và bộ ba
+// BA<n>Type / BA<n>Min / BA<n>Max cho thuộc tính cơ bản (n = 1..BASE_ATTRIB_COUNT).
+
+/// Thư mục bảng item trong pak
+pub const ITEM_TABLE_DIR: &str = "\\settings\\item\\";
+
+/// ItemGenre trong bảng item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemGenre {
+    Equipment,
+    Medicine,
+    Mine,
+    Material,
+    Task,
+    TownPortal,
+}
+
+impl ItemGenre {
+    pub const ALL: [ItemGenre; 6] = [
+        ItemGenre::Equipment,
+        ItemGenre::Medicine,
+        ItemGenre::Mine,
+        ItemGenre::Material,
+        ItemGenre::Task,
+        ItemGenre::TownPortal,
+    ];
+
+    pub fn from_index(index: i32) -> Option<Self> {
+        by_index(&Self::ALL, index)
+    }
+}
+
+/// DetailType của trang bị (genre = Equipment), đúng thứ tự enum của engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EquipDetail {
+    MeleeWeapon,
+    RangeWeapon,
+    Armor,
+    Ring,
+    Amulet,
+    Boots,
+    Belt,
+    Helm,
+    Cuff,
+    Pendant,
+    Horse,
+}
+
+impl EquipDetail {
+    pub const ALL: [EquipDetail; 11] = [
+        EquipDetail::MeleeWeapon,
+        EquipDetail::RangeWeapon,
+        EquipDetail::Armor,
+        EquipDetail::Ring,
+        EquipDetail::Amulet,
+        EquipDetail::Boots,
+        EquipDetail::Belt,
+        EquipDetail::Helm,
+        EquipDetail::Cuff,
+        EquipDetail::Pendant,
+        EquipDetail::Horse,
+    ];
+
+    pub fn from_index(index: i32) -> Option<Self> {
+        by_index(&Self::ALL, index)
+    }
+
+    /// Tên loại trang bị, cũng là tên cột "có thể xuất hiện" trong magicattrib.txt
+    pub fn name(self) -> &'static str {
+        match self {
+            EquipDetail::MeleeWeapon => "meleeweapon",
+            EquipDetail::RangeWeapon => "rangeweapon",
+            EquipDetail::Armor => "armor",
+            EquipDetail::Ring => "ring",
+            EquipDetail::Amulet => "amulet",
+            EquipDetail::Boots => "boots",
+            EquipDetail::Belt => "belt",
+            EquipDetail::Helm => "helm",
+            EquipDetail::Cuff => "cuff",
+            EquipDetail::Pendant => "pendant",
+            EquipDetail::Horse => "horse",
+        }
+    }
+
+    /// Tên file bảng trong `ITEM_TABLE_DIR`
+    pub fn table_file(self) -> &'static str {
+        match self {
+            EquipDetail::MeleeWeapon => "meleeweapon.txt",
+            EquipDetail::RangeWeapon => "rangeweapon.txt",
+            EquipDetail::Armor => "armor.txt",
+            EquipDetail::Ring => "ring.txt",
+            EquipDetail::Amulet => "amulet.txt",
+            EquipDetail::Boots => "boot.txt",
+            EquipDetail::Belt => "belt.txt",
+            EquipDetail::Helm => "helm.txt",
+            EquipDetail::Cuff => "cuff.txt",
+            EquipDetail::Pendant => "pendant.txt",
+            EquipDetail::Horse => "horse.txt",
+        }
+    }
+
+    /// Bộ phận có hình mà trang bị này thay đổi. Nhẫn, dây chuyền, giày... không hiện trên người.
+    /// Mũ đổi cả Head + Hair, áo đổi cả Shoulder + Body + tay theo loại trang bị của bộ phận này.
+    pub fn visual_slot(self) -> Option<PartSlot> {
+        match self {
+            EquipDetail::Helm => Some(PartSlot::Head),
+            EquipDetail::Armor => Some(PartSlot::Body),
+            EquipDetail::MeleeWeapon | EquipDetail::RangeWeapon => Some(PartSlot::RightWeapon),
+            EquipDetail::Horse => Some(PartSlot::HorseFront),
+            _ => None,
+        }
+    }
+
+    pub fn is_weapon(self) -> bool {
+        matches!(self, EquipDetail::MeleeWeapon | EquipDetail::RangeWeapon)
+    }
+}
+
+/// Ngũ hành (cột Series), -1 hoặc trống là không thuộc hệ nào
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Series {
+    Metal,
+    Wood,
+    Water,
+    Fire,
+    Earth,
+}
+
+impl Series {
+    pub const ALL: [Series; 5] = [
+        Series::Metal,
+        Series::Wood,
+        Series::Water,
+        Series::Fire,
+        Series::Earth,
+    ];
+
+    pub fn from_index(index: i32) -> Option<Self> {
+        by_index(&Self::ALL, index)
+    }
+}
+
+// Giá trị enum theo số trong bảng (thứ tự enum của engine), số âm / quá lớn là `None`
+fn by_index<T: Copy>(all: &[T], index: i32) -> Option<T> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|i| all.get(i).copied())
+}
+
+/// Một dòng bảng item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemDef {
+    pub name: String,
+    // spr hình item trong túi đồ
+    pub image: String,
+    pub genre: i32,
+    pub detail_type: i32,
+    pub particular: i32,
+    pub level: i32,
+    pub series: Option<Series>,
+    // Dòng trong bảng bộ phận, `None` với item không có hình trên người
+    pub res_id: Option<u32>,
+    // Thuộc tính cơ bản (BA1..BA6), giá trị roll trong [min, max] khi tạo item
+    pub base: Vec<AttribRange>,
+}
+
+impl ItemDef {
+    fn from_row(row: &TabRow) -> Self {
+        let int = |column: &str| row.get_int(column).unwrap_or(0);
+        ItemDef {
+            name: row.get("Name").to_string(),
+            image: row.get("ImageName").to_string(),
+            genre: int("ItemGenre"),
+            detail_type: int("DetailType"),
+            particular: int("ParticularType"),
+            level: int("Level"),
+            series: row.get_int("Series").and_then(Series::from_index),
+            res_id: row.get_int("ResID").and_then(|id| u32::try_from(id).ok()),
+            base: (1..=BASE_ATTRIB_COUNT)
+                .filter_map(|n| {
+                    let attrib = row.get(&format!("BA{}Type", n));
+                    let min = row.get_int(&format!("BA{}Min", n)).unwrap_or(0);
+                    let max = row.get_int(&format!("BA{}Max", n)).unwrap_or(min);
+                    AttribRange::new(attrib, [(min, max), (0, 0), (0, 0)])
+                })
+                .collect(),
+        }
+    }
+
+    pub fn genre(&self) -> Option<ItemGenre> {
+        ItemGenre::from_index(self.genre)
+    }
+
+    /// Loại trang bị, `None` nếu không phải trang bị
+    pub fn equip_detail(&self) -> Option<EquipDetail> {
+        (self.genre() == Some(ItemGenre::Equipment))
+            .then(|| EquipDetail::from_index(self.detail_type))
+            .flatten()
+    }
+}
+
+/// Mọi item đã đọc (từ một hay nhiều bảng)
+#[derive(Debug, Clone, Default)]
+pub struct ItemDb {
+    pub items: Vec<ItemDef>,
+}
+
+impl ItemDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Thêm các dòng của một bảng item (bỏ dòng không có tên)
+    pub fn add_table(&mut self, table: &TabFile) {
+        self.items.extend(
+            table
+                .rows()
+                .map(|row| ItemDef::from_row(&row))
+                .filter(|item| !item.name.is_empty()),
+        );
+    }
+
+    /// Đọc bảng của mọi loại trang bị trong thư mục đã giải nén, bỏ qua bảng không có
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut db = ItemDb::new();
+        for detail in EquipDetail::ALL {
+            let path = dir.as_ref().join(detail.table_file());
+            if path.exists() {
+                db.add_table(&TabFile::load(path)?);
+            }
+        }
+        Ok(db)
+    }
+
+    /// Như `load_dir` nhưng đọc từ `ITEM_TABLE_DIR` trong pak
+    pub fn load_pak(paks: &mut PakSet) -> io::Result<Self> {
+        let mut db = ItemDb::new();
+        for detail in EquipDetail::ALL {
+            let path = format!("{}{}", ITEM_TABLE_DIR, detail.table_file());
+            match TabFile::load_pak(paks, &path) {
+                Ok(table) => db.add_table(&table),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(db)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Trang bị theo khoá của engine (detail, particular, level)
+    pub fn find_equipment(
+        &self,
+        detail: EquipDetail,
+        particular: i32,
+        level: i32,
+    ) -> Option<&ItemDef> {
+        self.items.iter().find(|item| {
+            item.equip_detail() == Some(detail)
+                && item.particular == particular
+                && item.level == level
+        })
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&ItemDef> {
+        self.items.iter().find(|item| item.name == name)
+    }
+}
+
+// --- THUỘC TÍNH MA PHÁP (settings\item\magicattrib.txt) ---
+// Thuộc tính đặt theo tên của engine ("lifemax_v", "fireres_p"...), mỗi thuộc tính có 3 giá trị
+// (vd sát thương: min / - / max). Bảng cấp (magicattriblevel.txt) cho biết item cấp N được roll
+// bao nhiêu thuộc tính và cấp thuộc tính trong khoảng nào.
+//
+// Cũng tra cột theo tên header như bảng item:
+// - magicattrib.txt: Name, Pos (1 = hậu tố), Series, Level, Attrib, Value<n>Min / Value<n>Max
+//   (n = 1..3), và một cột cho mỗi loại trang bị đặt tên theo `EquipDetail::name`
+//   (meleeweapon, armor...): khác 0 là loại đó có thể mang thuộc tính.
+// - magicattriblevel.txt: Level (cấp item), MinMagicLevel / MaxMagicLevel (khoảng cấp
+//   thuộc tính được chọn), MagicCount (số thuộc tính roll).
+
+/// Số giá trị của một thuộc tính
+pub const ATTRIB_VALUES: usize = 3;
+/// Số cột thuộc tính cơ bản BA1..BAn trong bảng item
+pub const BASE_ATTRIB_COUNT: usize = 6;
+pub const MAGIC_ATTRIB_TABLE: &str = "magicattrib.txt";
+pub const MAGIC_LEVEL_TABLE: &str = "magicattriblevel.txt";
+
+/// Một thuộc tính đã roll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attrib {
+    pub attrib: String,
+    pub values: [i32; ATTRIB_VALUES],
+}
+
+/// Khoảng giá trị của một thuộc tính trong bảng
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttribRange {
+    pub attrib: String,
+    pub ranges: [(i32, i32); ATTRIB_VALUES],
+}
+
+impl AttribRange {
+    /// `None` nếu ô tên thuộc tính trống; min > max trong bảng được đổi chỗ
+    pub fn new(attrib: &str, ranges: [(i32, i32); ATTRIB_VALUES]) -> Option<Self> {
+        let attrib = attrib.trim();
+        (!attrib.is_empty()).then(|| AttribRange {
+            attrib: attrib.to_string(),
+            ranges: ranges.map(|(a, b)| (a.min(b), a.max(b))),
+        })
+    }
+
+    pub fn roll<R: Rng>(&self, rng: &mut R) -> Attrib {
+        Attrib {
+            attrib: self.attrib.clone(),
+            values: self.ranges.map(|(min, max)| rng.gen_range(min..=max)),
+        }
+    }
+}
+
+/// Một dòng magicattrib.txt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagicAttribDef {
+    // tên hiển thị (tiền tố / hậu tố của tên item)
+    pub name: String,
+    pub suffix: bool,
+    // `None`: mọi hệ
+    pub series: Option<Series>,
+    pub level: i32,
+    pub range: AttribRange,
+    // loại trang bị có thể mang thuộc tính này
+    pub details: Vec<EquipDetail>,
+}
+
+impl MagicAttribDef {
+    fn from_row(row: &TabRow) -> Result<Self, String> {
+        let int = |column: String| row.get_int(&column).unwrap_or(0);
+        let ranges = [1, 2, 3].map(|n| {
+            let min = int(format!("Value{}Min", n));
+            (min, row.get_int(&format!("Value{}Max", n)).unwrap_or(min))
+        });
+        Ok(MagicAttribDef {
+            name: row.get("Name").to_string(),
+            suffix: row.get_int("Pos") == Some(1),
+            series: row.get_int("Series").and_then(Series::from_index),
+            level: row.get_int("Level").unwrap_or(1),
+            range: AttribRange::new(row.get("Attrib"), ranges)
+                .ok_or_else(|| "cột Attrib trống".to_string())?,
+            details: EquipDetail::ALL
+                .into_iter()
+                .filter(|d| row.get_int(d.name()).is_some_and(|v| v != 0))
+                .collect(),
+        })
+    }
+
+    fn allows(&self, item: &ItemDef, detail: EquipDetail, level: &MagicLevel) -> bool {
+        self.details.contains(&detail)
+            && (level.min..=level.max).contains(&self.level)
+            && (self.series.is_none() || self.series == item.series)
+    }
+}
+
+/// Một dòng magicattriblevel.txt: item cấp `Level` trở lên
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MagicLevel {
+    pub item_level: i32,
+    pub min: i32,
+    pub max: i32,
+    pub count: usize,
+}
+
+/// Bảng thuộc tính ma pháp + bảng cấp
+#[derive(Debug, Clone, Default)]
+pub struct MagicDb {
+    pub attribs: Vec<MagicAttribDef>,
+    // sắp theo `item_level` tăng dần
+    pub levels: Vec<MagicLevel>,
+    // Dòng bị bỏ qua của hai bảng, để công cụ / log báo cho người sửa bảng
+    pub errors: Vec<RowError>,
+}
+
+impl MagicDb {
+    pub fn from_tables(attribs: &TabFile, levels: &TabFile) -> Self {
+        let mut errors = Vec::new();
+        let attribs = attribs.parse_rows(MAGIC_ATTRIB_TABLE, &mut errors, MagicAttribDef::from_row);
+        let mut levels = levels.parse_rows(MAGIC_LEVEL_TABLE, &mut errors, |row| {
+            Ok(MagicLevel {
+                item_level: row.require_int("Level")?,
+                min: row.get_int("MinMagicLevel").unwrap_or(1),
+                max: row.get_int("MaxMagicLevel").unwrap_or(1),
+                count: row
+                    .get_int("MagicCount")
+                    .and_then(|c| usize::try_from(c).ok())
+                    .unwrap_or(0),
+            })
+        });
+        levels.sort_by_key(|l| l.item_level);
+        MagicDb {
+            attribs,
+            levels,
+            errors,
+        }
+    }
+
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        Ok(Self::from_tables(
+            &TabFile::load(dir.join(MAGIC_ATTRIB_TABLE))?,
+            &TabFile::load(dir.join(MAGIC_LEVEL_TABLE))?,
+        ))
+    }
+
+    pub fn load_pak(paks: &mut PakSet) -> io::Result<Self> {
+        let attribs =
+            TabFile::load_pak(paks, &format!("{}{}", ITEM_TABLE_DIR, MAGIC_ATTRIB_TABLE))?;
+        let levels = TabFile::load_pak(paks, &format!("{}{}", ITEM_TABLE_DIR, MAGIC_LEVEL_TABLE))?;
+        Ok(Self::from_tables(&attribs, &levels))
+    }
+
+    /// Dòng cấp của item: dòng có `item_level` lớn nhất không vượt quá `level`;
+    /// item thấp hơn mọi dòng không có thuộc tính ma pháp
+    pub fn level_for(&self, level: i32) -> MagicLevel {
+        self.levels
+            .iter()
+            .rev()
+            .find(|l| l.item_level <= level)
+            .copied()
+            .unwrap_or(MagicLevel {
+                item_level: level,
+                min: 0,
+                max: 0,
+                count: 0,
+            })
+    }
+
+    /// Tạo item từ định nghĩa: roll thuộc tính cơ bản rồi thuộc tính ma pháp.
+    /// Cùng `seed` luôn ra cùng item. Thuộc tính lẻ là tiền tố, chẵn là hậu tố (hết loại
+    /// nào thì lấy loại kia), không roll trùng tên thuộc tính.
+    pub fn generate(&self, item: &ItemDef, seed: u64) -> ItemInstance {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let base = item.base.iter().map(|b| b.roll(&mut rng)).collect();
+
+        let mut magic: Vec<MagicRoll> = Vec::new();
+        if let Some(detail) = item.equip_detail() {
+            let level = self.level_for(item.level);
+            let pool: Vec<&MagicAttribDef> = self
+                .attribs
+                .iter()
+                .filter(|m| m.allows(item, detail, &level))
+                .collect();
+            for i in 0..level.count {
+                let unused =
+                    |m: &&&MagicAttribDef| !magic.iter().any(|r| r.attrib.attrib == m.range.attrib);
+                let want_suffix = i % 2 == 1;
+                let mut choices: Vec<&MagicAttribDef> = pool
+                    .iter()
+                    .filter(unused)
+                    .filter(|m| m.suffix == want_suffix)
+                    .copied()
+                    .collect();
+                if choices.is_empty() {
+                    choices = pool.iter().filter(unused).copied().collect();
+                }
+                let Some(def) = choices.choose(&mut rng) else {
+                    break;
+                };
+                magic.push(MagicRoll {
+                    name: def.name.clone(),
+                    attrib: def.range.roll(&mut rng),
+                });
+            }
+        }
+
+        ItemInstance {
+            name: item.name.clone(),
+            detail: item.equip_detail(),
+            level: item.level,
+            series: item.series,
+            seed,
+            base,
+            magic,
+        }
+    }
+}
+
+/// Thuộc tính ma pháp đã roll cùng tên dòng magicattrib
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagicRoll {
+    pub name: String,
+    pub attrib: Attrib,
+}
+
+/// Một món đồ cụ thể (đã roll)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemInstance {
+    pub name: String,
+    pub detail: Option<EquipDetail>,
+    pub level: i32,
+    pub series: Option<Series>,
+    // seed đã dùng, đủ để tạo lại đúng món đồ từ ItemDef
+    pub seed: u64,
+    pub base: Vec<Attrib>,
+    pub magic: Vec<MagicRoll>,
+}
+
+impl ItemInstance {
+    pub fn attribs(&self) -> impl Iterator<Item = &Attrib> {
+        self.base.iter().chain(self.magic.iter().map(|m| &m.attrib))
+    }
+}
+
+/// Tổng thuộc tính của các trang bị đang mặc, cộng theo từng giá trị
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemStats {
+    values: BTreeMap<String, [i32; ATTRIB_VALUES]>,
+}
+
+impl ItemStats {
+    pub fn from_items<'a>(items: impl IntoIterator<Item = &'a ItemInstance>) -> Self {
+        let mut stats = ItemStats::default();
+        for attrib in items.into_iter().flat_map(|i| i.attribs()) {
+            stats.add(attrib);
+        }
+        stats
+    }
+
+    pub fn add(&mut self, attrib: &Attrib) {
+        let values = self.values.entry(attrib.attrib.clone()).or_default();
+        for (total, v) in values.iter_mut().zip(attrib.values) {
+            *total += v;
+        }
+    }
+
+    /// Giá trị đầu của thuộc tính, 0 nếu không có
+    pub fn get(&self, attrib: &str) -> i32 {
+        self.values(attrib)[0]
+    }
+
+    pub fn values(&self, attrib: &str) -> [i32; ATTRIB_VALUES] {
+        self.values.get(attrib).copied().unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[i32; ATTRIB_VALUES])> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Chỉ số cuối của nhân vật: `(base + <stat>_v) * (100 + <stat>_p) / 100`,
+    /// vd `apply("lifemax", 500)` với lifemax_v = 100, lifemax_p = 10 -> 660
+    pub fn apply(&self, stat: &str, base: i32) -> i32 {
+        let flat = self.get(&format!("{}_v", stat));
+        let percent = self.get(&format!("{}_p", stat));
+        (base + flat) * (100 + percent) / 100
+    }
+}
+
+// --- ITEM -> HÌNH TRÊN NGƯỜI ---
+
+/// Hình của một trang bị trên nhân vật chính
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemVisual {
+    // index loại trang bị trong `part_list` (cũng là index của `Equipment`)
+    pub category: usize,
+    // tên item trong các bảng bộ phận của loại trang bị đó
+    pub part: String,
+    // dòng của bảng vũ khí (未骑马关联表 / 骑马关联表), chỉ có với vũ khí
+    pub weapon: Option<String>,
+}
+
+impl ItemVisual {
+    /// Mặc lên `equipment` (kiểm tra item có trong bảng của mọi bộ phận liên kết)
+    pub fn equip(&self, equipment: &mut Equipment, res: &NpcResEntry) -> Result<(), EquipError> {
+        equipment.equip(res, self.category, &self.part)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VisualError {
+    NotEquipment,
+    // Trang bị không hiện trên người (nhẫn, dây chuyền...)
+    NoVisual(EquipDetail),
+    NoResId,
+    // Nhân vật không có loại trang bị chứa bộ phận này (NPC thường)
+    NoCategory(PartSlot),
+    UnknownResId { slot: PartSlot, res_id: u32 },
+}
+
+impl fmt::Display for VisualError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VisualError::NotEquipment => write!(f, "Không phải trang bị"),
+            VisualError::NoVisual(detail) => {
+                write!(f, "Trang bị {:?} không hiện trên người", detail)
+            }
+            VisualError::NoResId => write!(f, "Item không có ResID"),
+            VisualError::NoCategory(slot) => {
+                write!(
+                    f,
+                    "Nhân vật không có loại trang bị cho {}",
+                    slot.column_name()
+                )
+            }
+            VisualError::UnknownResId { slot, res_id } => write!(
+                f,
+                "ResID {} vượt quá bảng bộ phận {}",
+                res_id,
+                slot.column_name()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VisualError {}
+
+/// Tìm hình của `item` trên nhân vật `res`: loại trang bị, tên dòng trong bảng bộ phận và
+/// (với vũ khí) dòng bảng vũ khí để chọn action qua `ActionSet::resolve`
+pub fn resolve_visual(item: &ItemDef, res: &NpcResEntry) -> Result<ItemVisual, VisualError> {
+    let detail = item.equip_detail().ok_or(VisualError::NotEquipment)?;
+    let slot = detail.visual_slot().ok_or(VisualError::NoVisual(detail))?;
+    let res_id = item.res_id.ok_or(VisualError::NoResId)?;
+
+    let category = res
+        .part_list
+        .iter()
+        .position(|c| c.slots.contains(&slot))
+        .ok_or(VisualError::NoCategory(slot))?;
+    let part = res
+        .part_by_res_id(slot, res_id)
+        .ok_or(VisualError::UnknownResId { slot, res_id })?;
+
+    Ok(ItemVisual {
+        category,
+        part: part.to_string(),
+        weapon: detail.is_weapon().then(|| part.to_string()),
+    })
+}
+
+/// Dòng bảng vũ khí khi cầm `weapon`; tay không khi không cầm gì hoặc vũ khí không có hình
+pub fn weapon_category(weapon: Option<&ItemDef>, res: &NpcResEntry) -> String {
+    weapon
+        .and_then(|item| resolve_visual(item, res).ok())
+        .and_then(|visual| visual.weapon)
+        .unwrap_or_else(|| UNARMED_WEAPON.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::npcres::NpcResDb;
+
+    const ITEM_TABLE: &str = "Name\tItemGenre\tDetailType\tParticularType\tImageName\tSeries\tLevel\tResID\n\
+        Thanh Cương Kiếm\t0\t0\t0\t\\spr\\item\\sword01.spr\t0\t1\t1\n\
+        Bố Y\t0\t2\t0\t\\spr\\item\\armor01.spr\t-1\t1\t1\n\
+        Ngọc Bội\t0\t9\t0\t\\spr\\item\\pendant01.spr\t2\t1\t\n\
+        \t\t\t\t\t\t\t\n\
+        Kim Sang Dược\t1\t0\t0\t\\spr\\item\\potion01.spr\t\t1\t\n";
+
+    const SWORD_TABLE: &str = "Name\tItemGenre\tDetailType\tParticularType\tSeries\tLevel\tBA1Type\tBA1Min\tBA1Max\tBA2Type\tBA2Min\tBA2Max\n\
+        Kiếm\t0\t0\t0\t3\t5\tdamage_physics\t10\t20\tdurability\t30\t30\n\
+        Nhẫn\t0\t3\t0\t\t1\t\t\t\t\t\t\n";
+
+    const MAGIC_TABLE: &str = "Name\tPos\tSeries\tLevel\tAttrib\tValue1Min\tValue1Max\tValue2Min\tValue2Max\tValue3Min\tValue3Max\tmeleeweapon\tring\n\
+        Sinh Lực\t0\t\t1\tlifemax_v\t10\t20\t\t\t\t\t1\t1\n\
+        Hỏa Công\t0\t3\t1\tfiredamage_v\t5\t10\t\t\t15\t20\t1\t0\n\
+        Băng Công\t0\t2\t1\tcolddamage_v\t5\t10\t\t\t15\t20\t1\t0\n\
+        Kháng Hỏa\t1\t\t1\tfireres_p\t1\t5\t\t\t\t\t1\t1\n\
+        Cường Thân\t1\t\t2\tlifemax_p\t5\t10\t\t\t\t\t1\t1\n\
+        Thần Lực\t1\t\t9\tstrength_v\t50\t60\t\t\t\t\t1\t1\n";
+
+    const LEVEL_TABLE: &str = "Level\tMinMagicLevel\tMaxMagicLevel\tMagicCount\n\
+        1\t1\t1\t1\n\
+        5\t1\t2\t4\n";
+
+    fn magic_db() -> MagicDb {
+        MagicDb::from_tables(
+            &TabFile::from_text(MAGIC_TABLE),
+            &TabFile::from_text(LEVEL_TABLE),
+        )
+    }
+
+    fn swords() -> ItemDb {
+        let mut db = ItemDb::new();
+        db.add_table(&TabFile::from_text(SWORD_TABLE));
+        db
+    }
+
+    fn main_lady() -> NpcResEntry {
+        let json = r#"{"schema_version": 2, "characters": {"MainLady": {
+            "type": "SpecialNpc", "root_path": "/spr/npcres/woman",
+            "components": {
+                "body": {"Thân thể 01": {}, "Thân thể 02": {}},
+                "shoulder": {"Thân thể 01": {}, "Thân thể 02": {}},
+                "rightweapon": {"Tay không": {}, "đơn thủ kiếm 1": {}},
+                "leftweapon": {"Tay không": {}, "đơn thủ kiếm 1": {}}},
+            "part_rows": {
+                "body": ["Thân thể 01", "Thân thể 02"],
+                "rightweapon": ["Tay không", "đơn thủ kiếm 1"]},
+            "part_list": [
+                {"index": 0, "name": "Trang bị", "slots": ["Shoulder", "Body"]},
+                {"index": 1, "name": "Vũ khí", "slots": ["LeftWeapon", "RightWeapon"]}]
+        }}}"#;
+        let db = NpcResDb::from_reader(json.as_bytes()).unwrap();
+        db.get("MainLady").unwrap().clone()
+    }
+
+    #[test]
+    fn parses_item_tables() {
+        let mut db = ItemDb::new();
+        db.add_table(&TabFile::from_text(ITEM_TABLE));
+        assert_eq!(db.len(), 4);
+
+        let sword = db.find_equipment(EquipDetail::MeleeWeapon, 0, 1).unwrap();
+        assert_eq!(sword.name, "Thanh Cương Kiếm");
+        assert_eq!(sword.series, Some(Series::Metal));
+        assert_eq!(sword.res_id, Some(1));
+
+        assert_eq!(db.find_by_name("Bố Y").unwrap().series, None);
+        let potion = db.find_by_name("Kim Sang Dược").unwrap();
+        assert_eq!(potion.genre(), Some(ItemGenre::Medicine));
+        assert_eq!(potion.equip_detail(), None);
+    }
+
+    #[test]
+    fn resolves_item_visuals_and_weapon_rows() {
+        let mut db = ItemDb::new();
+        db.add_table(&TabFile::from_text(ITEM_TABLE));
+        let res = main_lady();
+        let item = |name: &str| db.find_by_name(name).unwrap();
+
+        let armor = resolve_visual(item("Bố Y"), &res).unwrap();
+        assert_eq!((armor.category, armor.part.as_str()), (0, "Thân thể 02"));
+        assert_eq!(armor.weapon, None);
+
+        let sword = resolve_visual(item("Thanh Cương Kiếm"), &res).unwrap();
+        assert_eq!(sword.weapon.as_deref(), Some("đơn thủ kiếm 1"));
+        let mut equipment = Equipment::for_character(&res);
+        sword.equip(&mut equipment, &res).unwrap();
+        assert_eq!(equipment.item(1), Some("đơn thủ kiếm 1"));
+
+        assert_eq!(
+            resolve_visual(item("Ngọc Bội"), &res),
+            Err(VisualError::NoVisual(EquipDetail::Pendant))
+        );
+        assert_eq!(
+            resolve_visual(item("Kim Sang Dược"), &res),
+            Err(VisualError::NotEquipment)
+        );
+        let far = ItemDef {
+            res_id: Some(40),
+            ..item("Bố Y").clone()
+        };
+        assert_eq!(
+            resolve_visual(&far, &res),
+            Err(VisualError::UnknownResId {
+                slot: PartSlot::Body,
+                res_id: 40
+            })
+        );
+
+        assert_eq!(weapon_category(None, &res), UNARMED_WEAPON);
+        assert_eq!(weapon_category(Some(item("Bố Y")), &res), UNARMED_WEAPON);
+        assert_eq!(
+            weapon_category(Some(item("Thanh Cương Kiếm")), &res),
+            "đơn thủ kiếm 1"
+        );
+    }
+
+    #[test]
+    fn parses_magic_tables() {
+        let db = magic_db();
+        assert_eq!(db.attribs.len(), 6);
+
+        let fire = &db.attribs[1];
+        assert_eq!(fire.series, Some(Series::Fire));
+        assert!(!fire.suffix);
+        assert_eq!(fire.range.ranges, [(5, 10), (0, 0), (15, 20)]);
+        assert_eq!(fire.details, vec![EquipDetail::MeleeWeapon]);
+        assert!(db.attribs[3].suffix);
+
+        assert_eq!(db.level_for(0).count, 0);
+        assert_eq!(db.level_for(3).count, 1);
+        assert_eq!(db.level_for(99).max, 2);
+        assert!(db.errors.is_empty());
+
+        // Dòng thiếu thuộc tính / cấp không phải số được báo, không âm thầm biến mất
+        let broken = MagicDb::from_tables(
+            &TabFile::from_text("Name\tAttrib\nRỗng\t\nHỏa Công\tfiredamage_v\n"),
+            &TabFile::from_text("Level\tMagicCount\nx\t1\n5\t2\n"),
+        );
+        assert_eq!((broken.attribs.len(), broken.levels.len()), (1, 1));
+        let messages: Vec<String> = broken.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "magicattrib.txt: dòng 2: cột Attrib trống",
+                "magicattriblevel.txt: dòng 2: cột Level \"x\" không phải số",
+            ]
+        );
+
+        let sword = swords().find_by_name("Kiếm").unwrap().clone();
+        assert_eq!(sword.base.len(), 2);
+        assert_eq!(sword.base[0].ranges[0], (10, 20));
+        assert!(swords().find_by_name("Nhẫn").unwrap().base.is_empty());
+    }
+
+    #[test]
+    fn generation_is_seeded_and_respects_tables() {
+        let db = magic_db();
+        let items = swords();
+        let sword = items.find_by_name("Kiếm").unwrap();
+
+        assert_eq!(db.generate(sword, 7), db.generate(sword, 7));
+        let differs = (0..20).any(|seed| db.generate(sword, seed) != db.generate(sword, 7));
+        assert!(differs);
+
+        for seed in 0..50 {
+            let item = db.generate(sword, seed);
+            assert_eq!(item.base[1].values, [30, 0, 0]);
+            assert!((10..=20).contains(&item.base[0].values[0]));
+
+            // Kiếm hệ Hỏa cấp 5: 4 thuộc tính cấp 1..2, không có Băng Công (hệ Thủy),
+            // không có Thần Lực (cấp 9), không trùng nhau
+            let names: Vec<&str> = item.magic.iter().map(|m| m.name.as_str()).collect();
+            assert_eq!(names.len(), 4);
+            assert!(!names.contains(&"Băng Công") && !names.contains(&"Thần Lực"));
+            let mut attribs: Vec<&str> = item
+                .magic
+                .iter()
+                .map(|m| m.attrib.attrib.as_str())
+                .collect();
+            attribs.sort();
+            attribs.dedup();
+            assert_eq!(attribs.len(), 4);
+
+            let fire = item.magic.iter().find(|m| m.name == "Hỏa Công").unwrap();
+            assert!((5..=10).contains(&fire.attrib.values[0]));
+            assert!((15..=20).contains(&fire.attrib.values[2]));
+        }
+
+        // nhẫn cấp 1: một thuộc tính, chỉ từ các dòng cho phép ring
+        let ring = db.generate(items.find_by_name("Nhẫn").unwrap(), 3);
+        assert_eq!(ring.magic.len(), 1);
+        assert!(["Sinh Lực", "Kháng Hỏa"].contains(&ring.magic[0].name.as_str()));
+
+        // không phải trang bị: chỉ có thuộc tính cơ bản
+        let potion = ItemDef {
+            genre: 1,
+            ..sword.clone()
+        };
+        assert!(db.generate(&potion, 1).magic.is_empty());
+    }
+
+    #[test]
+    fn aggregates_equipped_stats() {
+        let attrib = |attrib: &str, values| Attrib {
+            attrib: attrib.to_string(),
+            values,
+        };
+        let item = |base: Vec<Attrib>, magic: Vec<Attrib>| ItemInstance {
+            name: String::new(),
+            detail: None,
+            level: 1,
+            series: None,
+            seed: 0,
+            base,
+            magic: magic
+                .into_iter()
+                .map(|attrib| MagicRoll {
+                    name: String::new(),
+                    attrib,
+                })
+                .collect(),
+        };
+        let equipped = [
+            item(
+                vec![attrib("damage_physics", [10, 0, 20])],
+                vec![attrib("lifemax_v", [100, 0, 0])],
+            ),
+            item(
+                vec![],
+                vec![
+                    attrib("lifemax_p", [10, 0, 0]),
+                    attrib("damage_physics", [5, 0, 5]),
+                ],
+            ),
+        ];
+
+        let stats = ItemStats::from_items(&equipped);
+        assert_eq!(stats.values("damage_physics"), [15, 0, 25]);
+        assert_eq!(stats.get("lifemax_v"), 100);
+        assert_eq!(stats.get("manamax_v"), 0);
+        assert_eq!(stats.apply("lifemax", 500), 660);
+        assert_eq!(stats.apply("manamax", 300), 300);
+        assert_eq!(stats.iter().count(), 3);
+        assert_eq!(ItemStats::from_items(&[]), ItemStats::default());
+    }
+}
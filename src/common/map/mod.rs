@@ -0,0 +1,694 @@
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::common::ini::IniFile;
+use crate::common::pak::PakSet;
+use crate::common::text::{Encoding, decode, decode_with};
+use crate::common::world::{REGION_CELLS_X, REGION_CELLS_Y};
+
+// --- ĐƯỜNG DẪN ---
+
+pub const MAPLIST_FILE: &str = "\\settings\\maplist.ini";
+
+/// File region của client (`Region_C.dat`) hoặc server (`Region_S.dat`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegionSide {
+    Client,
+    Server,
+}
+
+/// `\maps\<map>\v_<y>\<x>_Region_C.dat` (thư mục theo hàng y, file theo cột x)
+pub fn region_file_path(map_path: &str, region: (i32, i32), side: RegionSide) -> String {
+    let name = match side {
+        RegionSide::Client => "Region_C.dat",
+        RegionSide::Server => "Region_S.dat",
+    };
+    format!(
+        "\\maps\\{}\\v_{:03}\\{:03}_{}",
+        map_path.trim_matches('\\'),
+        region.1,
+        region.0,
+        name
+    )
+}
+
+/// `\maps\<map>.wor`
+pub fn world_file_path(map_path: &str) -> String {
+    format!("\\maps\\{}.wor", map_path.trim_matches('\\'))
+}
+
+// --- DANH SÁCH BẢN ĐỒ (maplist.ini) ---
+
+/// Một bản đồ trong `[List]` của maplist.ini: `<id>=<đường dẫn>`, `<id>_name=<tên>`...
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapEntry {
+    pub id: u32,
+    // Tương đối so với \maps, vd "西山村\西山村"
+    pub path: String,
+    pub name: String,
+    // Các key `<id>_xxx` còn lại (MapType...)
+    pub attrs: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapList {
+    pub maps: BTreeMap<u32, MapEntry>,
+}
+
+impl MapList {
+    pub fn from_text(text: &str) -> Self {
+        let ini = IniFile::from_text(text);
+        let mut maps: BTreeMap<u32, MapEntry> = BTreeMap::new();
+        let Some(list) = ini.section("List") else {
+            return MapList { maps };
+        };
+
+        for (key, value) in list.entries() {
+            if let Ok(id) = key.parse::<u32>() {
+                maps.entry(id).or_insert_with(|| MapEntry::empty(id)).path =
+                    value.replace('/', "\\");
+            }
+        }
+        for (key, value) in list.entries() {
+            let Some((id, attr)) = key.split_once('_') else {
+                continue;
+            };
+            let Some(entry) = id.parse::<u32>().ok().and_then(|id| maps.get_mut(&id)) else {
+                continue;
+            };
+            if attr.eq_ignore_ascii_case("name") {
+                entry.name = value.to_string();
+            } else {
+                entry.attrs.insert(attr.to_string(), value.to_string());
+            }
+        }
+        maps.retain(|_, m| !m.path.is_empty());
+        MapList { maps }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Self {
+        Self::from_text(&decode_with(data, None))
+    }
+
+    pub fn from_bytes_as(data: &[u8], encoding: Encoding) -> Self {
+        Self::from_text(&decode(data, encoding))
+    }
+
+    pub fn load(paks: &mut PakSet) -> io::Result<Self> {
+        let data = paks
+            .read(MAPLIST_FILE)?
+            .ok_or_else(|| not_found(MAPLIST_FILE))?;
+        Ok(Self::from_text(&decode_with(&data, paks.encoding)))
+    }
+
+    pub fn get(&self, id: u32) -> Option<&MapEntry> {
+        self.maps.get(&id)
+    }
+}
+
+impl MapEntry {
+    fn empty(id: u32) -> Self {
+        MapEntry {
+            id,
+            path: String::new(),
+            name: String::new(),
+            attrs: BTreeMap::new(),
+        }
+    }
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("Không tìm thấy {}", path))
+}
+
+// --- FILE .wor ---
+
+/// Thông tin chung của bản đồ trong `[MAIN]` của file .wor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldInfo {
+    // Khoảng region (trái, trên, phải, dưới), tính cả hai đầu
+    pub region_rect: (i32, i32, i32, i32),
+    pub indoor: bool,
+}
+
+impl WorldInfo {
+    pub fn from_text(text: &str) -> io::Result<Self> {
+        let ini = IniFile::from_text(text);
+        let main = ini
+            .section("MAIN")
+            .ok_or_else(|| invalid("File .wor thiếu section [MAIN]"))?;
+        let region_rect = main
+            .get_rect("rect")
+            .ok_or_else(|| invalid("File .wor thiếu rect=trái,trên,phải,dưới"))?;
+        Ok(WorldInfo {
+            region_rect,
+            indoor: main.get_int("IsIndoor") == Some(1),
+        })
+    }
+
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        Self::from_text(&decode_with(data, None))
+    }
+
+    pub fn from_bytes_as(data: &[u8], encoding: Encoding) -> io::Result<Self> {
+        Self::from_text(&decode(data, encoding))
+    }
+
+    pub fn load(paks: &mut PakSet, map_path: &str) -> io::Result<Self> {
+        let path = world_file_path(map_path);
+        let data = paks.read(&path)?.ok_or_else(|| not_found(&path))?;
+        Self::from_text(&decode_with(&data, paks.encoding))
+    }
+
+    pub fn width(&self) -> i32 {
+        self.region_rect.2 - self.region_rect.0 + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.region_rect.3 - self.region_rect.1 + 1
+    }
+
+    /// Mọi region của bản đồ theo thứ tự hàng
+    pub fn regions(&self) -> impl Iterator<Item = (i32, i32)> + use<> {
+        let (left, top, right, bottom) = self.region_rect;
+        (top..=bottom).flat_map(move |y| (left..=right).map(move |x| (x, y)))
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// --- FILE REGION (Region_C.dat / Region_S.dat) ---
+// File gộp: u32 số element, rồi từng cặp (offset, length) u32;
+// offset tính từ cuối phần header. Thứ tự element theo KRegion của client gốc.
+//
+// THỬ NGHIỆM: bố cục bên trong các element (lưới ô, bản ghi NPC / vật thể / nền / cảnh)
+// chỉ là suy đoán, chưa đối chiếu được offset với struct gốc của client (KSPNpc, KSPObj...).
+// Xem trước bằng `jx map render`; các trường có thể sai hoặc lệch giữa các bản game,
+// đừng dựa vào chúng cho logic server.
+
+pub const REGION_OBSTACLE_SECTION: usize = 0;
+pub const REGION_TRAP_SECTION: usize = 1;
+pub const REGION_NPC_SECTION: usize = 2;
+pub const REGION_OBJECT_SECTION: usize = 3;
+// Chỉ có trong Region_C.dat
+pub const REGION_GROUND_SECTION: usize = 4;
+pub const REGION_BUILDIN_SECTION: usize = 5;
+
+const REGION_CELL_COUNT: usize = (REGION_CELLS_X * REGION_CELLS_Y) as usize;
+
+/// Tách file gộp thành các element (element thiếu hoặc rỗng là slice rỗng)
+pub fn region_sections(data: &[u8]) -> io::Result<Vec<&[u8]>> {
+    let mut cursor = Cursor::new(data);
+    let count = cursor.read_u32::<LittleEndian>()? as usize;
+    if count > 64 {
+        return Err(invalid("Số element của file region không hợp lệ"));
+    }
+    let mut table = Vec::with_capacity(count);
+    for _ in 0..count {
+        let offset = cursor.read_u32::<LittleEndian>()? as usize;
+        let length = cursor.read_u32::<LittleEndian>()? as usize;
+        table.push((offset, length));
+    }
+
+    let base = 4 + count * 8;
+    table
+        .into_iter()
+        .map(|(offset, length)| {
+            let start = base + offset;
+            data.get(start..start + length)
+                .ok_or_else(|| invalid("Element của file region vượt quá cuối file"))
+        })
+        .collect()
+}
+
+/// Lưới 16 x 32 ô của một region, lưu theo cột (x ngoài, y trong) như mảng C `[16][32]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellGrid {
+    pub cells: Vec<u32>,
+}
+
+impl Default for CellGrid {
+    fn default() -> Self {
+        CellGrid {
+            cells: vec![0; REGION_CELL_COUNT],
+        }
+    }
+}
+
+impl CellGrid {
+    fn from_section(data: &[u8]) -> io::Result<Self> {
+        if data.is_empty() {
+            return Ok(CellGrid::default());
+        }
+        if data.len() < REGION_CELL_COUNT * 4 {
+            return Err(invalid("Lưới ô của region quá ngắn"));
+        }
+        let mut cursor = Cursor::new(data);
+        let mut cells = Vec::with_capacity(REGION_CELL_COUNT);
+        for _ in 0..REGION_CELL_COUNT {
+            cells.push(cursor.read_u32::<LittleEndian>()?);
+        }
+        Ok(CellGrid { cells })
+    }
+
+    /// Giá trị ô (x, y) trong region, 0 nếu ngoài lưới
+    pub fn get(&self, x: i32, y: i32) -> u32 {
+        if !(0..REGION_CELLS_X).contains(&x) || !(0..REGION_CELLS_Y).contains(&y) {
+            return 0;
+        }
+        self.cells[(x * REGION_CELLS_Y + y) as usize]
+    }
+}
+
+/// Vật cản của một ô: 4 bit thấp là loại, 4 bit kế là hình dạng (cả ô, nửa ô chéo...)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Obstacle(pub u32);
+
+impl Obstacle {
+    pub fn is_blocked(self) -> bool {
+        self.kind() != 0
+    }
+
+    pub fn kind(self) -> u32 {
+        self.0 & 0x0f
+    }
+
+    pub fn shape(self) -> u32 {
+        (self.0 >> 4) & 0x0f
+    }
+}
+
+/// NPC đặt sẵn trong region (bản ghi KSPNpc, chỉ đọc phần đầu dùng chung giữa các bản).
+/// Thử nghiệm: thứ tự và kích thước trường chưa đối chiếu với struct gốc.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionNpc {
+    pub template_id: i32,
+    // Toạ độ logic trong bản đồ
+    pub x: i32,
+    pub y: i32,
+    pub special: bool,
+    pub name: String,
+    pub level: i16,
+}
+
+/// Vật thể đặt sẵn trong region (rương, cửa...). Thử nghiệm như `RegionNpc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionObject {
+    pub template_id: i32,
+    pub state: i32,
+    pub x: i32,
+    pub y: i32,
+    pub dir: i32,
+}
+
+// Kích thước phần đầu bản ghi đọc được của NPC / vật thể (suy đoán, xem đầu mục)
+const NPC_RECORD_PREFIX: usize = 4 * 3 + 4 + 32 + 2;
+const OBJECT_RECORD_PREFIX: usize = 4 * 5;
+
+/// Tách element dạng `u32 số bản ghi` + các bản ghi cùng kích thước.
+/// Kích thước bản ghi suy ra từ độ dài element vì các bản game khác nhau thêm trường vào cuối.
+fn split_records(data: &[u8], min_size: usize) -> io::Result<Vec<&[u8]>> {
+    if data.len() < 4 {
+        return Ok(Vec::new());
+    }
+    let count = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let body = &data[4..];
+    let size = body.len() / count;
+    if size < min_size || size * count != body.len() {
+        return Err(invalid("Kích thước bản ghi của region không khớp"));
+    }
+    Ok(body.chunks_exact(size).collect())
+}
+
+// Chuỗi C cố định `len` byte; `encoding` là bảng mã của nguồn, `None` là tự nhận diện
+fn read_c_string(
+    cursor: &mut Cursor<&[u8]>,
+    len: usize,
+    encoding: Option<Encoding>,
+) -> io::Result<String> {
+    let mut buf = vec![0u8; len];
+    cursor.read_exact(&mut buf)?;
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(len);
+    Ok(decode_with(&buf[..end], encoding))
+}
+
+/// Dữ liệu logic của một region (bố cục thử nghiệm, xem đầu mục)
+#[derive(Debug, Clone, Default)]
+pub struct RegionData {
+    pub obstacles: CellGrid,
+    pub traps: CellGrid,
+    pub npcs: Vec<RegionNpc>,
+    pub objects: Vec<RegionObject>,
+}
+
+impl RegionData {
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        Self::parse(data, None)
+    }
+
+    /// Tên NPC theo bảng mã chỉ định
+    pub fn from_bytes_as(data: &[u8], encoding: Encoding) -> io::Result<Self> {
+        Self::parse(data, Some(encoding))
+    }
+
+    fn parse(data: &[u8], encoding: Option<Encoding>) -> io::Result<Self> {
+        let sections = region_sections(data)?;
+        let section = |i: usize| sections.get(i).copied().unwrap_or(&[]);
+
+        let mut npcs = Vec::new();
+        for record in split_records(section(REGION_NPC_SECTION), NPC_RECORD_PREFIX)? {
+            let mut c = Cursor::new(record);
+            let template_id = c.read_i32::<LittleEndian>()?;
+            let x = c.read_i32::<LittleEndian>()?;
+            let y = c.read_i32::<LittleEndian>()?;
+            let special = c.read_u8()? != 0;
+            c.set_position(c.position() + 3);
+            let name = read_c_string(&mut c, 32, encoding)?;
+            let level = c.read_i16::<LittleEndian>()?;
+            npcs.push(RegionNpc {
+                template_id,
+                x,
+                y,
+                special,
+                name,
+                level,
+            });
+        }
+
+        let mut objects = Vec::new();
+        for record in split_records(section(REGION_OBJECT_SECTION), OBJECT_RECORD_PREFIX)? {
+            let mut c = Cursor::new(record);
+            objects.push(RegionObject {
+                template_id: c.read_i32::<LittleEndian>()?,
+                state: c.read_i32::<LittleEndian>()?,
+                x: c.read_i32::<LittleEndian>()?,
+                y: c.read_i32::<LittleEndian>()?,
+                dir: c.read_i32::<LittleEndian>()?,
+            });
+        }
+
+        Ok(RegionData {
+            obstacles: CellGrid::from_section(section(REGION_OBSTACLE_SECTION))?,
+            traps: CellGrid::from_section(section(REGION_TRAP_SECTION))?,
+            npcs,
+            objects,
+        })
+    }
+
+    /// Đọc region từ pak, `Ok(None)` nếu region không có file (ngoài bản đồ)
+    pub fn load(
+        paks: &mut PakSet,
+        map_path: &str,
+        region: (i32, i32),
+        side: RegionSide,
+    ) -> io::Result<Option<Self>> {
+        match paks.read(&region_file_path(map_path, region, side))? {
+            Some(data) => Self::parse(&data, paks.encoding).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn obstacle(&self, x: i32, y: i32) -> Obstacle {
+        Obstacle(self.obstacles.get(x, y))
+    }
+
+    /// Script id của bẫy tại ô (x, y), 0 nếu không có
+    pub fn trap(&self, x: i32, y: i32) -> u32 {
+        self.traps.get(x, y)
+    }
+}
+
+// --- CẢNH VẬT (CHỈ CÓ TRONG Region_C.dat) ---
+
+/// Ô nền: frame `frame` của ảnh `image` (spr hoặc jpg) vẽ tại điểm logic (x, y)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroundTile {
+    pub image: String,
+    pub frame: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Vật thể cảnh (cây, nhà, đá...) đặt tại điểm logic (x, y).
+/// `sort_line` là đoạn thẳng trên mặt đất dùng để xếp trước/sau với NPC,
+/// `frames` > 1 là cảnh có animation (mỗi frame `interval` game frame).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneryObject {
+    pub image: String,
+    pub frame: u32,
+    pub frames: u32,
+    pub interval: u32,
+    pub x: i32,
+    pub y: i32,
+    pub sort_line: ((i32, i32), (i32, i32)),
+}
+
+impl SceneryObject {
+    /// Toạ độ y logic dùng để sắp xếp: giữa đoạn sort_line, hoặc chân vật thể nếu không có
+    pub fn sort_y(&self) -> i32 {
+        let ((x1, y1), (x2, y2)) = self.sort_line;
+        if (x1, y1) == (x2, y2) && (x1, y1) == (0, 0) {
+            self.y
+        } else {
+            (y1 + y2) / 2
+        }
+    }
+
+    /// Frame cần vẽ ở game frame `tick`
+    pub fn frame_at(&self, tick: u32) -> u32 {
+        if self.frames <= 1 {
+            return self.frame;
+        }
+        self.frame + tick / self.interval.max(1) % self.frames
+    }
+}
+
+// Bố cục bản ghi nền / cảnh cũng là suy đoán như bản ghi NPC.
+// Bản ghi ô nền: x, y, frame + đường dẫn ảnh 128 byte
+const GROUND_RECORD_PREFIX: usize = 4 * 3 + 128;
+// Bản ghi cảnh: x, y, sort_line (4 số), frame, frames, interval + đường dẫn ảnh 128 byte
+const SCENERY_RECORD_PREFIX: usize = 4 * 9 + 128;
+
+/// Lớp nền và vật thể cảnh của một region, dữ liệu riêng của client (bố cục thử nghiệm)
+#[derive(Debug, Clone, Default)]
+pub struct RegionScenery {
+    pub ground: Vec<GroundTile>,
+    pub objects: Vec<SceneryObject>,
+}
+
+impl RegionScenery {
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        Self::parse(data, None)
+    }
+
+    /// Đường dẫn ảnh theo bảng mã chỉ định
+    pub fn from_bytes_as(data: &[u8], encoding: Encoding) -> io::Result<Self> {
+        Self::parse(data, Some(encoding))
+    }
+
+    fn parse(data: &[u8], encoding: Option<Encoding>) -> io::Result<Self> {
+        let sections = region_sections(data)?;
+        let section = |i: usize| sections.get(i).copied().unwrap_or(&[]);
+
+        let mut ground = Vec::new();
+        for record in split_records(section(REGION_GROUND_SECTION), GROUND_RECORD_PREFIX)? {
+            let mut c = Cursor::new(record);
+            let x = c.read_i32::<LittleEndian>()?;
+            let y = c.read_i32::<LittleEndian>()?;
+            let frame = c.read_u32::<LittleEndian>()?;
+            ground.push(GroundTile {
+                image: read_c_string(&mut c, 128, encoding)?,
+                frame,
+                x,
+                y,
+            });
+        }
+
+        let mut objects = Vec::new();
+        for record in split_records(section(REGION_BUILDIN_SECTION), SCENERY_RECORD_PREFIX)? {
+            let mut c = Cursor::new(record);
+            let mut v = [0i32; 9];
+            for slot in v.iter_mut() {
+                *slot = c.read_i32::<LittleEndian>()?;
+            }
+            objects.push(SceneryObject {
+                image: read_c_string(&mut c, 128, encoding)?,
+                frame: v[6].max(0) as u32,
+                frames: v[7].max(0) as u32,
+                interval: v[8].max(0) as u32,
+                x: v[0],
+                y: v[1],
+                sort_line: ((v[2], v[3]), (v[4], v[5])),
+            });
+        }
+
+        Ok(RegionScenery { ground, objects })
+    }
+
+    /// Đọc cảnh của region từ pak, `Ok(None)` nếu region không có file
+    pub fn load(paks: &mut PakSet, map_path: &str, region: (i32, i32)) -> io::Result<Option<Self>> {
+        match paks.read(&region_file_path(map_path, region, RegionSide::Client))? {
+            Some(data) => Self::parse(&data, paks.encoding).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+
+    fn combine(sections: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.write_u32::<LittleEndian>(sections.len() as u32)
+            .unwrap();
+        let mut offset = 0u32;
+        for s in sections {
+            data.write_u32::<LittleEndian>(offset).unwrap();
+            data.write_u32::<LittleEndian>(s.len() as u32).unwrap();
+            offset += s.len() as u32;
+        }
+        for s in sections {
+            data.extend(s);
+        }
+        data
+    }
+
+    fn grid(set: &[(i32, i32, u32)]) -> Vec<u8> {
+        let mut cells = vec![0u32; REGION_CELL_COUNT];
+        for &(x, y, v) in set {
+            cells[(x * REGION_CELLS_Y + y) as usize] = v;
+        }
+        cells.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn parses_map_list_and_world() {
+        let list = MapList::from_text(
+            "; comment\n[List]\nCount=2\n1=west\\xishancun\n1_name=Tây Sơn Thôn\n1_MapType=City\n11=chengdu\n",
+        );
+        assert_eq!(list.maps.len(), 2);
+        let map = list.get(1).unwrap();
+        assert_eq!(map.path, "west\\xishancun");
+        assert_eq!(map.name, "Tây Sơn Thôn");
+        assert_eq!(map.attrs.get("MapType").map(|s| s.as_str()), Some("City"));
+        assert_eq!(
+            region_file_path(&map.path, (3, 12), RegionSide::Client),
+            "\\maps\\west\\xishancun\\v_012\\003_Region_C.dat"
+        );
+
+        let world = WorldInfo::from_text("[MAIN]\nrect=10,20,12,21\nIsIndoor=0\n").unwrap();
+        assert_eq!((world.width(), world.height()), (3, 2));
+        assert_eq!(world.regions().count(), 6);
+        assert_eq!(world.regions().next(), Some((10, 20)));
+        assert!(WorldInfo::from_text("[MAIN]\n").is_err());
+    }
+
+    #[test]
+    fn parses_region_sections() {
+        let mut npc = Vec::new();
+        npc.write_u32::<LittleEndian>(1).unwrap();
+        for v in [42, 5000, 9000] {
+            npc.write_i32::<LittleEndian>(v).unwrap();
+        }
+        npc.extend([1, 0, 0, 0]);
+        let mut name = b"Tieu nhi".to_vec();
+        name.resize(32, 0);
+        npc.extend(name);
+        npc.write_i16::<LittleEndian>(10).unwrap();
+        // Phần đuôi bản ghi (script...) bỏ qua
+        npc.extend([0u8; 6]);
+
+        let mut obj = Vec::new();
+        obj.write_u32::<LittleEndian>(2).unwrap();
+        for v in [7, 0, 100, 200, 3, 8, 1, 300, 400, 5] {
+            obj.write_i32::<LittleEndian>(v).unwrap();
+        }
+
+        let data = combine(&[
+            grid(&[(0, 0, 0x11), (15, 31, 0x21)]),
+            grid(&[(2, 3, 9)]),
+            npc,
+            obj,
+        ]);
+        let region = RegionData::from_bytes(&data).unwrap();
+
+        assert!(region.obstacle(0, 0).is_blocked());
+        assert_eq!(region.obstacle(15, 31).shape(), 2);
+        assert!(!region.obstacle(1, 0).is_blocked());
+        assert!(!region.obstacle(16, 0).is_blocked());
+        assert_eq!(region.trap(2, 3), 9);
+
+        assert_eq!(region.npcs.len(), 1);
+        assert_eq!(region.npcs[0].name, "Tieu nhi");
+        assert_eq!((region.npcs[0].x, region.npcs[0].y), (5000, 9000));
+        assert!(region.npcs[0].special);
+        assert_eq!(region.npcs[0].level, 10);
+
+        assert_eq!(region.objects.len(), 2);
+        assert_eq!(region.objects[1].template_id, 8);
+        assert_eq!(region.objects[1].dir, 5);
+    }
+
+    #[test]
+    fn parses_region_scenery() {
+        let path = |name: &str| {
+            let mut buf = name.as_bytes().to_vec();
+            buf.resize(128, 0);
+            buf
+        };
+        let mut ground = Vec::new();
+        ground.write_u32::<LittleEndian>(1).unwrap();
+        for v in [512, 1024, 3] {
+            ground.write_i32::<LittleEndian>(v).unwrap();
+        }
+        ground.extend(path("\\maps\\ground.spr"));
+
+        let mut buildin = Vec::new();
+        buildin.write_u32::<LittleEndian>(2).unwrap();
+        for values in [
+            [600, 1200, 0, 0, 0, 0, 0, 1, 0],
+            [700, 1300, 650, 1280, 750, 1320, 2, 4, 3],
+        ] {
+            for v in values {
+                buildin.write_i32::<LittleEndian>(v).unwrap();
+            }
+            buildin.extend(path("\\spr\\tree.spr"));
+        }
+
+        let empty = Vec::new();
+        let sections = [grid(&[]), grid(&[]), empty.clone(), empty, ground, buildin];
+        let scenery = RegionScenery::from_bytes(&combine(&sections)).unwrap();
+
+        assert_eq!(scenery.ground.len(), 1);
+        assert_eq!(scenery.ground[0].image, "\\maps\\ground.spr");
+        assert_eq!((scenery.ground[0].x, scenery.ground[0].frame), (512, 3));
+
+        assert_eq!(scenery.objects.len(), 2);
+        assert_eq!(scenery.objects[0].sort_y(), 1200);
+        assert_eq!(scenery.objects[1].sort_y(), 1300);
+        assert_eq!(scenery.objects[0].frame_at(100), 0);
+        assert_eq!(scenery.objects[1].frame_at(0), 2);
+        assert_eq!(scenery.objects[1].frame_at(7), 4);
+
+        // Region_S.dat không có element cảnh
+        let server = combine(&[grid(&[]), grid(&[])]);
+        let scenery = RegionScenery::from_bytes(&server).unwrap();
+        assert!(scenery.ground.is_empty() && scenery.objects.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_region() {
+        let mut data = combine(&[grid(&[])]);
+        data.truncate(data.len() - 10);
+        assert!(RegionData::from_bytes(&data).is_err());
+    }
+}
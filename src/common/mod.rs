@@ -1,2 +1,13 @@
+pub mod bundle;
+pub mod ini;
+pub mod item;
+pub mod map;
 pub mod npc;
+pub mod npcres;
 pub mod pak;
+pub mod pathfind;
+pub mod sim;
+pub mod skill;
+pub mod tabfile;
+pub mod text;
+pub mod world;
@@ -1,8 +1,12 @@
-use std::{collections::HashMap, hash::Hash};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+use crate::common::npcres::NpcResEntry;
+use crate::common::world::{RUN_SPEED, WALK_SPEED, WorldPos, dir64_from_vector, sprite_dir};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ActionId {
     FreeStand1 = 0,
     FreeStand2 = 1,
@@ -54,6 +58,336 @@ pub enum ActionId {
     RideStand2 = 47,
 }
 
+impl ActionId {
+    /// Toàn bộ action theo đúng thứ tự cột trong `动作编号表.txt`
+    pub const ALL: [ActionId; 48] = [
+        ActionId::FreeStand1,
+        ActionId::FreeStand2,
+        ActionId::FreeStand3,
+        ActionId::MeleeWStand,
+        ActionId::RangeWStand,
+        ActionId::DoubleWStand,
+        ActionId::FreeWalk,
+        ActionId::NormalWalk,
+        ActionId::MeleeWWalk,
+        ActionId::RangeWWalk,
+        ActionId::DoubleWWalk,
+        ActionId::FreeRun,
+        ActionId::NormalRun,
+        ActionId::MeleeWRun,
+        ActionId::RangeWRun,
+        ActionId::DoubleWRun,
+        ActionId::FreeWound,
+        ActionId::MeleeWWound,
+        ActionId::RangeWWound,
+        ActionId::DoubleWWound,
+        ActionId::FreeDie,
+        ActionId::MeleeWDie,
+        ActionId::RangeWDie,
+        ActionId::DoubleWDie,
+        ActionId::FreeAttack,
+        ActionId::MeleeWPuncture,
+        ActionId::MeleeWCut,
+        ActionId::RangeWPuncture,
+        ActionId::RangeWCut,
+        ActionId::DoubleWPull,
+        ActionId::DoubleWPound,
+        ActionId::DartThrow,
+        ActionId::FreeMagic,
+        ActionId::MeleeWMagic,
+        ActionId::RangeWMagic,
+        ActionId::DoubleWMagic,
+        ActionId::SitDown,
+        ActionId::JumpFly,
+        ActionId::RideStand,
+        ActionId::RideWalk,
+        ActionId::RideRun,
+        ActionId::RideCut,
+        ActionId::RidePuncture,
+        ActionId::RideMagic,
+        ActionId::RideWound,
+        ActionId::RideDie,
+        ActionId::RideStand1,
+        ActionId::RideStand2,
+    ];
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Tên action đúng như header trong các bảng npcres
+    pub fn name(self) -> &'static str {
+        match self {
+            ActionId::FreeStand1 => "FreeStand1",
+            ActionId::FreeStand2 => "FreeStand2",
+            ActionId::FreeStand3 => "FreeStand3",
+            ActionId::MeleeWStand => "MeleeWStand",
+            ActionId::RangeWStand => "RangeWStand",
+            ActionId::DoubleWStand => "DoubleWStand",
+            ActionId::FreeWalk => "FreeWalk",
+            ActionId::NormalWalk => "NormalWalk",
+            ActionId::MeleeWWalk => "MeleeWWalk",
+            ActionId::RangeWWalk => "RangeWWalk",
+            ActionId::DoubleWWalk => "DoubleWWalk",
+            ActionId::FreeRun => "FreeRun",
+            ActionId::NormalRun => "NormalRun",
+            ActionId::MeleeWRun => "MeleeWRun",
+            ActionId::RangeWRun => "RangeWRun",
+            ActionId::DoubleWRun => "DoubleWRun",
+            ActionId::FreeWound => "FreeWound",
+            ActionId::MeleeWWound => "MeleeWWound",
+            ActionId::RangeWWound => "RangeWWound",
+            ActionId::DoubleWWound => "DoubleWWound",
+            ActionId::FreeDie => "FreeDie",
+            ActionId::MeleeWDie => "MeleeWDie",
+            ActionId::RangeWDie => "RangeWDie",
+            ActionId::DoubleWDie => "DoubleWDie",
+            ActionId::FreeAttack => "FreeAttack",
+            ActionId::MeleeWPuncture => "MeleeWPuncture",
+            ActionId::MeleeWCut => "MeleeWCut",
+            ActionId::RangeWPuncture => "RangeWPuncture",
+            ActionId::RangeWCut => "RangeWCut",
+            ActionId::DoubleWPull => "DoubleWPull",
+            ActionId::DoubleWPound => "DoubleWPound",
+            ActionId::DartThrow => "DartThrow",
+            ActionId::FreeMagic => "FreeMagic",
+            ActionId::MeleeWMagic => "MeleeWMagic",
+            ActionId::RangeWMagic => "RangeWMagic",
+            ActionId::DoubleWMagic => "DoubleWMagic",
+            ActionId::SitDown => "SitDown",
+            ActionId::JumpFly => "JumpFly",
+            ActionId::RideStand => "RideStand",
+            ActionId::RideWalk => "RideWalk",
+            ActionId::RideRun => "RideRun",
+            ActionId::RideCut => "RideCut",
+            ActionId::RidePuncture => "RidePuncture",
+            ActionId::RideMagic => "RideMagic",
+            ActionId::RideWound => "RideWound",
+            ActionId::RideDie => "RideDie",
+            ActionId::RideStand1 => "RideStand1",
+            ActionId::RideStand2 => "RideStand2",
+        }
+    }
+
+    /// Parse tên action từ header bảng.
+    /// Các bảng bộ phận dùng cột "Jump" thay cho "JumpFly" nên chấp nhận cả hai.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name == "Jump" {
+            return Some(ActionId::JumpFly);
+        }
+        Self::ALL.iter().copied().find(|a| a.name() == name)
+    }
+}
+
+/// Hành động của NPC thường theo `npc动作表.txt`. Với nhân vật chính, đây cũng là
+/// các "lệnh" (cột) của bảng vũ khí `未骑马关联表`/`骑马关联表` để ra `ActionId`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NpcAction {
+    FightStand = 0,
+    NormalStand1 = 1,
+    NormalStand2 = 2,
+    FightWalk = 3,
+    NormalWalk = 4,
+    FightRun = 5,
+    NormalRun = 6,
+    Wound = 7,
+    Die = 8,
+    Attack1 = 9,
+    Attack2 = 10,
+    Magic = 11,
+    SitDown = 12,
+    JumpFly = 13,
+}
+
+impl NpcAction {
+    pub const ALL: [NpcAction; 14] = [
+        NpcAction::FightStand,
+        NpcAction::NormalStand1,
+        NpcAction::NormalStand2,
+        NpcAction::FightWalk,
+        NpcAction::NormalWalk,
+        NpcAction::FightRun,
+        NpcAction::NormalRun,
+        NpcAction::Wound,
+        NpcAction::Die,
+        NpcAction::Attack1,
+        NpcAction::Attack2,
+        NpcAction::Magic,
+        NpcAction::SitDown,
+        NpcAction::JumpFly,
+    ];
+
+    /// Tên cột trong bảng (bảng gốc ghi nhầm "JunpFly")
+    pub fn name(self) -> &'static str {
+        match self {
+            NpcAction::FightStand => "FightStand",
+            NpcAction::NormalStand1 => "NormalStand1",
+            NpcAction::NormalStand2 => "NormalStand2",
+            NpcAction::FightWalk => "FightWalk",
+            NpcAction::NormalWalk => "NormalWalk",
+            NpcAction::FightRun => "FightRun",
+            NpcAction::NormalRun => "NormalRun",
+            NpcAction::Wound => "Wound",
+            NpcAction::Die => "Die",
+            NpcAction::Attack1 => "Attack1",
+            NpcAction::Attack2 => "Attack2",
+            NpcAction::Magic => "Magic",
+            NpcAction::SitDown => "SitDown",
+            NpcAction::JumpFly => "JunpFly",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name == "JumpFly" {
+            return Some(NpcAction::JumpFly);
+        }
+        Self::ALL.iter().copied().find(|a| a.name() == name)
+    }
+
+    /// Action chạy hết animation thì tự quay về đứng (Die thì dừng ở frame cuối)
+    pub fn is_one_shot(self) -> bool {
+        matches!(
+            self,
+            NpcAction::Wound
+                | NpcAction::Die
+                | NpcAction::Attack1
+                | NpcAction::Attack2
+                | NpcAction::Magic
+        )
+    }
+
+    /// ActionId khi tay không (dòng "Tay không" của bảng vũ khí),
+    /// dùng khi NPC không có bảng vũ khí. `None` nếu không diễn được khi cưỡi ngựa.
+    pub fn default_action(self, mounted: bool) -> Option<ActionId> {
+        let action = match (self, mounted) {
+            (NpcAction::FightStand, false) => ActionId::FreeStand3,
+            (NpcAction::NormalStand1, false) => ActionId::FreeStand2,
+            (NpcAction::NormalStand2, false) => ActionId::FreeStand1,
+            (NpcAction::FightWalk, false) => ActionId::FreeWalk,
+            (NpcAction::NormalWalk, false) => ActionId::NormalWalk,
+            (NpcAction::FightRun, false) => ActionId::FreeRun,
+            (NpcAction::NormalRun, false) => ActionId::NormalRun,
+            (NpcAction::Wound, false) => ActionId::FreeWound,
+            (NpcAction::Die, false) => ActionId::FreeDie,
+            (NpcAction::Attack1 | NpcAction::Attack2, false) => ActionId::FreeAttack,
+            (NpcAction::Magic, false) => ActionId::FreeMagic,
+            (NpcAction::SitDown, false) => ActionId::SitDown,
+            (NpcAction::JumpFly, false) => ActionId::JumpFly,
+            (NpcAction::FightStand | NpcAction::NormalStand1, true) => ActionId::RideStand,
+            (NpcAction::NormalStand2, true) => ActionId::RideStand2,
+            (NpcAction::FightWalk | NpcAction::NormalWalk, true) => ActionId::RideWalk,
+            (NpcAction::FightRun | NpcAction::NormalRun, true) => ActionId::RideRun,
+            (NpcAction::Wound, true) => ActionId::RideWound,
+            (NpcAction::Die, true) => ActionId::RideDie,
+            (NpcAction::Attack1, true) => ActionId::RideCut,
+            (NpcAction::Attack2, true) => ActionId::RidePuncture,
+            (NpcAction::Magic, true) => ActionId::RideMagic,
+            (NpcAction::SitDown | NpcAction::JumpFly, true) => return None,
+        };
+        Some(action)
+    }
+}
+
+/// Các bộ phận (part) ghép nên nhân vật chính.
+/// ID là số dùng trong `贴图顺序表.txt` (Dir1=-1,14,13,...), các ID 2, 3, 10, 11 không dùng.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PartSlot {
+    Head,
+    Hair,
+    Shoulder,
+    Body,
+    LeftHand,
+    #[serde(rename = "RightHead")]
+    RightHand,
+    LeftWeapon,
+    RightWeapon,
+    HorseFront,
+    HorseMiddle,
+    HorseBack,
+}
+
+impl PartSlot {
+    /// Thứ tự đúng như các cột bộ phận trong `人物类型.txt`
+    pub const ALL: [PartSlot; 11] = [
+        PartSlot::Head,
+        PartSlot::Hair,
+        PartSlot::Shoulder,
+        PartSlot::Body,
+        PartSlot::LeftHand,
+        PartSlot::RightHand,
+        PartSlot::LeftWeapon,
+        PartSlot::RightWeapon,
+        PartSlot::HorseFront,
+        PartSlot::HorseMiddle,
+        PartSlot::HorseBack,
+    ];
+
+    pub fn from_part_id(id: i32) -> Option<Self> {
+        match id {
+            0 => Some(PartSlot::Head),
+            1 => Some(PartSlot::Body),
+            4 => Some(PartSlot::LeftWeapon),
+            5 => Some(PartSlot::Shoulder),
+            6 => Some(PartSlot::HorseMiddle),
+            7 => Some(PartSlot::HorseFront),
+            8 => Some(PartSlot::HorseBack),
+            9 => Some(PartSlot::RightHand),
+            12 => Some(PartSlot::Hair),
+            13 => Some(PartSlot::LeftHand),
+            14 => Some(PartSlot::RightWeapon),
+            _ => None,
+        }
+    }
+
+    pub fn part_id(self) -> i32 {
+        match self {
+            PartSlot::Head => 0,
+            PartSlot::Body => 1,
+            PartSlot::LeftWeapon => 4,
+            PartSlot::Shoulder => 5,
+            PartSlot::HorseMiddle => 6,
+            PartSlot::HorseFront => 7,
+            PartSlot::HorseBack => 8,
+            PartSlot::RightHand => 9,
+            PartSlot::Hair => 12,
+            PartSlot::LeftHand => 13,
+            PartSlot::RightWeapon => 14,
+        }
+    }
+
+    /// Tên cột trong `人物类型.txt` / `部件列表.txt` (bảng gốc ghi nhầm "RightHead")
+    pub fn column_name(self) -> &'static str {
+        match self {
+            PartSlot::Head => "Head",
+            PartSlot::Hair => "Hair",
+            PartSlot::Shoulder => "Shoulder",
+            PartSlot::Body => "Body",
+            PartSlot::LeftHand => "LeftHand",
+            PartSlot::RightHand => "RightHead",
+            PartSlot::LeftWeapon => "LeftWeapon",
+            PartSlot::RightWeapon => "RightWeapon",
+            PartSlot::HorseFront => "HorseFront",
+            PartSlot::HorseMiddle => "HorseMiddle",
+            PartSlot::HorseBack => "HorseBack",
+        }
+    }
+
+    pub fn from_column_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|s| s.column_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Key của bộ phận trong `components` của npcres.json (cột viết thường)
+    pub fn component_key(self) -> String {
+        self.column_name().to_lowercase()
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Sex {
     Man,
@@ -70,17 +404,510 @@ pub enum NpcKind {
     Normal,
 }
 
+// --- MÁY TRẠNG THÁI ---
+
+/// Tên vũ khí "tay không" trong bảng `未骑马关联表`/`骑马关联表`
+pub const UNARMED_WEAPON: &str = "Tay không";
+
+/// Lệnh điều khiển NPC (từ input người chơi hoặc AI)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NpcCommand {
+    Stand,
+    Walk,
+    Run,
+    Attack,
+    Cast,
+    Sit,
+    Hurt,
+    Die,
+    Mount,
+    Dismount,
+}
+
+/// Số frame mỗi hướng và số tick (game frame 1/18 giây) mỗi frame của một action
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ActionTiming {
+    pub frames: u32,
+    pub interval: u32,
+}
+
+impl ActionTiming {
+    // Dùng khi không có thông tin animation: một frame, một tick
+    pub const STILL: ActionTiming = ActionTiming {
+        frames: 1,
+        interval: 1,
+    };
+}
+
+/// Dữ liệu máy trạng thái cần từ tài nguyên nhân vật (xem `NpcResEntry`)
+pub trait ActionSet {
+    /// ActionId của `action` khi cầm `weapon`, `None` nếu không diễn được (vd ngồi khi cưỡi ngựa)
+    fn resolve(&self, weapon: &str, mounted: bool, action: NpcAction) -> Option<ActionId>;
+    /// Timing của action đang diễn (`state` là ActionId đã tra từ `action`).
+    /// Cần cả hai: nhân vật chính tra bảng bóng theo ActionId, NPC thường không có
+    /// ActionId riêng mà tra bảng spr theo NpcAction.
+    fn timing(&self, action: NpcAction, state: ActionId) -> Option<ActionTiming>;
+}
+
+/// Sự kiện animation, `Npc::tick` trả về `Looped` / `Finished`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimEvent {
+    // Vừa bắt đầu diễn action (`Simulation` báo khi lệnh / đường đi đổi action), kèm ActionId
+    // lúc đó: sự kiện được xử lý sau khi xếp hàng, khi NPC có thể đã đổi sang action khác
+    Started(NpcAction, ActionId),
+    // Action lặp vừa quay về frame 0
+    Looped(NpcAction, ActionId),
+    // Action chạy một lần vừa kết thúc (trừ Die, NPC đã quay về đứng)
+    Finished(NpcAction),
+}
+
 #[derive(Debug)]
 pub struct Npc {
     pub id: u64,
     pub name: String,
-    // pub pos: WorldPos,
+    pub pos: WorldPos,
+    // Hướng 64 bước (0 = xuống, theo chiều kim đồng hồ), xem `common::world`
     pub dir: u8,
     pub state: ActionId,
     pub kind: NpcKind,
+    // Lệnh logic đang diễn, `state` là ActionId tương ứng theo vũ khí / ngựa
+    pub action: NpcAction,
+    pub weapon: String,
+    pub mounted: bool,
+    // Đang chiến đấu: đứng / đi / chạy dùng cột Fight* thay cho Normal*
+    pub fighting: bool,
+    // Frame hiện tại trong một hướng
+    pub frame: u32,
+    // Điểm logic mỗi tick khi đi / chạy
+    pub walk_speed: u32,
+    pub run_speed: u32,
+    frame_tick: u32,
+    finished: bool,
+    next_attack2: bool,
+    // Các điểm còn phải đi qua và phần lẻ (dưới 1 điểm) của vị trí
+    path: VecDeque<WorldPos>,
+    sub: (f32, f32),
+}
+
+impl Npc {
+    pub fn new(id: u64, name: &str, kind: NpcKind) -> Self {
+        Npc {
+            id,
+            name: name.to_string(),
+            pos: WorldPos::default(),
+            dir: 0,
+            state: ActionId::FreeStand2,
+            kind,
+            action: NpcAction::NormalStand1,
+            weapon: UNARMED_WEAPON.to_string(),
+            mounted: false,
+            fighting: false,
+            frame: 0,
+            walk_speed: WALK_SPEED,
+            run_speed: RUN_SPEED,
+            frame_tick: 0,
+            finished: false,
+            next_attack2: false,
+            path: VecDeque::new(),
+            sub: (0.0, 0.0),
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.action == NpcAction::Die
+    }
+
+    /// Đang diễn action chạy một lần (đánh, bị thương, chết)
+    pub fn is_busy(&self) -> bool {
+        self.action.is_one_shot()
+    }
+
+    /// Hướng spr (0..sprite_dirs) ứng với hướng hiện tại
+    pub fn sprite_dir(&self, sprite_dirs: u32) -> u32 {
+        sprite_dir(self.dir, sprite_dirs)
+    }
+
+    /// Quay mặt về phía `target` (giữ hướng cũ nếu trùng vị trí)
+    pub fn face(&mut self, target: WorldPos) {
+        let dx = (target.x - self.pos.x) as f32;
+        let dy = (target.y - self.pos.y) as f32;
+        if let Some(dir) = dir64_from_vector(dx, dy) {
+            self.dir = dir;
+        }
+    }
+
+    /// Các điểm còn phải đi qua
+    pub fn path(&self) -> &VecDeque<WorldPos> {
+        &self.path
+    }
+
+    /// Tốc độ hiện tại (0 nếu không đi / chạy)
+    pub fn speed(&self) -> u32 {
+        match self.action {
+            NpcAction::FightWalk | NpcAction::NormalWalk => self.walk_speed,
+            NpcAction::FightRun | NpcAction::NormalRun => self.run_speed,
+            _ => 0,
+        }
+    }
+
+    /// Đi (hoặc chạy) lần lượt qua các điểm của `path`, tới nơi thì đứng lại
+    pub fn move_along<I>(&mut self, path: I, run: bool, set: &dyn ActionSet) -> bool
+    where
+        I: IntoIterator<Item = WorldPos>,
+    {
+        let path: VecDeque<WorldPos> = path.into_iter().collect();
+        let Some(&first) = path.front() else {
+            return false;
+        };
+        let cmd = if run {
+            NpcCommand::Run
+        } else {
+            NpcCommand::Walk
+        };
+        if !self.command(cmd, set) {
+            return false;
+        }
+        self.path = path;
+        self.face(first);
+        true
+    }
+
+    fn stand_action(&self) -> NpcAction {
+        if self.fighting {
+            NpcAction::FightStand
+        } else {
+            NpcAction::NormalStand1
+        }
+    }
+
+    /// Nhận lệnh. Trả về `false` nếu lệnh bị bỏ qua:
+    /// NPC đã chết, đang đánh / bị thương (chỉ Hurt và Die cắt ngang được),
+    /// hoặc action không diễn được với vũ khí / ngựa hiện tại.
+    pub fn command(&mut self, cmd: NpcCommand, set: &dyn ActionSet) -> bool {
+        let action = match cmd {
+            NpcCommand::Stand => self.stand_action(),
+            NpcCommand::Walk if self.fighting => NpcAction::FightWalk,
+            NpcCommand::Walk => NpcAction::NormalWalk,
+            NpcCommand::Run if self.fighting => NpcAction::FightRun,
+            NpcCommand::Run => NpcAction::NormalRun,
+            NpcCommand::Attack if self.next_attack2 => NpcAction::Attack2,
+            NpcCommand::Attack => NpcAction::Attack1,
+            NpcCommand::Cast => NpcAction::Magic,
+            NpcCommand::Sit => NpcAction::SitDown,
+            NpcCommand::Hurt => NpcAction::Wound,
+            NpcCommand::Die => NpcAction::Die,
+            NpcCommand::Mount | NpcCommand::Dismount => {
+                if self.is_dead() || self.is_busy() {
+                    return false;
+                }
+                return self.set_mounted(cmd == NpcCommand::Mount, set);
+            }
+        };
+        self.perform(action, set)
+    }
+
+    /// Diễn đúng `action` qua bảng vũ khí / ngựa (kỹ năng chọn sẵn Attack1 / Attack2,
+    /// công cụ xem action). Cùng luật bỏ qua với `command`.
+    pub fn perform(&mut self, action: NpcAction, set: &dyn ActionSet) -> bool {
+        if self.is_dead() {
+            return false;
+        }
+        let interrupt = matches!(action, NpcAction::Wound | NpcAction::Die);
+        if self.is_busy() && !interrupt {
+            return false;
+        }
+
+        // Action lặp đang diễn thì giữ nguyên frame
+        if action == self.action && !action.is_one_shot() {
+            return true;
+        }
+        if !self.play(action, set) {
+            return false;
+        }
+        match action {
+            NpcAction::Attack1 | NpcAction::Attack2 => {
+                self.fighting = true;
+                // Lần đánh thường kế tiếp dùng đòn còn lại
+                self.next_attack2 = action == NpcAction::Attack1;
+            }
+            NpcAction::Magic => self.fighting = true,
+            _ => {}
+        }
+        true
+    }
+
+    /// Đổi vũ khí, action đang diễn đổi theo bảng của vũ khí mới
+    pub fn set_weapon(&mut self, weapon: &str, set: &dyn ActionSet) {
+        self.weapon = weapon.to_string();
+        if let Some(state) = set.resolve(&self.weapon, self.mounted, self.action) {
+            self.state = state;
+        }
+    }
+
+    fn set_mounted(&mut self, mounted: bool, set: &dyn ActionSet) -> bool {
+        if self.mounted == mounted {
+            return false;
+        }
+        // Ngồi không có trên ngựa: lên ngựa thì đứng dậy
+        let action = match self.action {
+            NpcAction::SitDown | NpcAction::JumpFly => self.stand_action(),
+            action => action,
+        };
+        let Some(state) = set.resolve(&self.weapon, mounted, action) else {
+            return false;
+        };
+        self.mounted = mounted;
+        self.action = action;
+        self.state = state;
+        self.frame = 0;
+        self.frame_tick = 0;
+        true
+    }
+
+    fn play(&mut self, action: NpcAction, set: &dyn ActionSet) -> bool {
+        let Some(state) = set.resolve(&self.weapon, self.mounted, action) else {
+            return false;
+        };
+        self.action = action;
+        self.state = state;
+        self.frame = 0;
+        self.frame_tick = 0;
+        self.finished = false;
+        if self.speed() == 0 {
+            self.path.clear();
+        }
+        true
+    }
+
+    /// Di chuyển `speed` điểm theo đường đi, có thể vượt qua nhiều điểm trong một tick.
+    /// Không có đường đi thì đi / chạy tại chỗ (xem animation).
+    fn step(&mut self, set: &dyn ActionSet) {
+        if self.path.is_empty() {
+            return;
+        }
+        let mut budget = self.speed() as f32;
+        while budget > 0.0 {
+            let Some(&target) = self.path.front() else {
+                break;
+            };
+            let dx = (target.x - self.pos.x) as f32 - self.sub.0;
+            let dy = (target.y - self.pos.y) as f32 - self.sub.1;
+            let dist = (dx * dx + dy * dy).sqrt();
+            if let Some(dir) = dir64_from_vector(dx, dy) {
+                self.dir = dir;
+            }
+            if dist <= budget {
+                self.pos = target;
+                self.sub = (0.0, 0.0);
+                self.path.pop_front();
+                budget -= dist;
+                continue;
+            }
+
+            let fx = self.pos.x as f32 + self.sub.0 + dx / dist * budget;
+            let fy = self.pos.y as f32 + self.sub.1 + dy / dist * budget;
+            self.pos.x = fx.round() as i32;
+            self.pos.y = fy.round() as i32;
+            self.sub = (fx - self.pos.x as f32, fy - self.pos.y as f32);
+            break;
+        }
+
+        if self.path.is_empty() {
+            let stand = self.stand_action();
+            self.play(stand, set);
+        }
+    }
+
+    /// Chạy một tick logic (1/18 giây): di chuyển theo đường đi,
+    /// sau `interval` tick thì sang frame kế tiếp.
+    /// Action chạy một lần kết thúc thì quay về đứng, Die dừng ở frame cuối.
+    pub fn tick(&mut self, set: &dyn ActionSet) -> Option<AnimEvent> {
+        self.step(set);
+        if self.finished {
+            return None;
+        }
+        let timing = set
+            .timing(self.action, self.state)
+            .unwrap_or(ActionTiming::STILL);
+
+        self.frame_tick += 1;
+        if self.frame_tick < timing.interval.max(1) {
+            return None;
+        }
+        self.frame_tick = 0;
+        self.frame += 1;
+        if self.frame < timing.frames.max(1) {
+            return None;
+        }
+
+        let action = self.action;
+        if action == NpcAction::Die {
+            self.frame = timing.frames.max(1) - 1;
+            self.finished = true;
+            return Some(AnimEvent::Finished(action));
+        }
+        if action.is_one_shot() {
+            let stand = self.stand_action();
+            if !self.play(stand, set) {
+                // Bảng vũ khí không có dòng đứng: dùng ActionId đứng mặc định,
+                // không giữ ActionId của action vừa kết thúc
+                self.action = stand;
+                if let Some(state) = stand.default_action(self.mounted) {
+                    self.state = state;
+                }
+                self.frame = 0;
+            }
+            return Some(AnimEvent::Finished(action));
+        }
+        self.frame = 0;
+        Some(AnimEvent::Looped(action, self.state))
+    }
+}
+
+// --- TRANG BỊ (部件列表.txt) ---
+
+/// Một loại trang bị và các bộ phận nó điều khiển
+/// (vd Đầu khôi -> Head + Hair, Trang bị -> Shoulder + Body + LeftHand + RightHead)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EquipCategory {
+    pub index: u32,
+    pub name: String,
+    pub slots: Vec<PartSlot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquipError {
+    UnknownCategory(usize),
+    // Item không có trong bảng bộ phận của một slot liên kết
+    MissingPart { item: String, slot: PartSlot },
+}
+
+impl fmt::Display for EquipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EquipError::UnknownCategory(i) => write!(f, "Không có loại trang bị #{}", i),
+            EquipError::MissingPart { item, slot } => write!(
+                f,
+                "Item '{}' không có trong bảng bộ phận {}",
+                item,
+                slot.column_name()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EquipError {}
+
+/// Đồ đang mặc theo loại trang bị: mặc một item sẽ đặt đồng thời mọi bộ phận liên kết
+#[derive(Debug, Clone, Default)]
+pub struct Equipment {
+    pub categories: Vec<EquipCategory>,
+    // index trong `categories` -> tên item
+    items: BTreeMap<usize, String>,
 }
 
-impl Npc {}
+impl Equipment {
+    pub fn new(categories: Vec<EquipCategory>) -> Self {
+        Equipment {
+            categories,
+            items: BTreeMap::new(),
+        }
+    }
+
+    /// Dùng danh sách loại trang bị của nhân vật (cột PartFileName trong `人物类型.txt`)
+    pub fn for_character(res: &NpcResEntry) -> Self {
+        Self::new(res.part_list.clone())
+    }
+
+    /// Loại trang bị điều khiển bộ phận `slot`
+    pub fn category_of(&self, slot: PartSlot) -> Option<usize> {
+        self.categories.iter().position(|c| c.slots.contains(&slot))
+    }
+
+    /// Mặc `item` vào loại trang bị `category`. Item phải có trong bảng của mọi bộ phận liên kết,
+    /// nếu không thì giữ nguyên đồ cũ và trả về lỗi.
+    pub fn equip(
+        &mut self,
+        res: &NpcResEntry,
+        category: usize,
+        item: &str,
+    ) -> Result<(), EquipError> {
+        let cat = self
+            .categories
+            .get(category)
+            .ok_or(EquipError::UnknownCategory(category))?;
+        if let Some(&slot) = cat
+            .slots
+            .iter()
+            .find(|&&slot| !res.has_part_item(slot, item))
+        {
+            return Err(EquipError::MissingPart {
+                item: item.to_string(),
+                slot,
+            });
+        }
+        self.items.insert(category, item.to_string());
+        Ok(())
+    }
+
+    pub fn unequip(&mut self, category: usize) -> Option<String> {
+        self.items.remove(&category)
+    }
+
+    pub fn item(&self, category: usize) -> Option<&str> {
+        self.items.get(&category).map(|s| s.as_str())
+    }
+
+    /// Các item mặc được cho loại trang bị (có trong bảng của mọi bộ phận liên kết)
+    pub fn valid_items<'a>(&self, res: &'a NpcResEntry, category: usize) -> Vec<&'a str> {
+        let Some(cat) = self.categories.get(category) else {
+            return Vec::new();
+        };
+        let Some(&first) = cat.slots.first() else {
+            return Vec::new();
+        };
+        res.part_items(first)
+            .into_iter()
+            .filter(|item| cat.slots.iter().all(|&s| res.has_part_item(s, item)))
+            .collect()
+    }
+
+    /// Liệt kê mọi item thiếu trong bảng của một bộ phận liên kết (kiểm tra dữ liệu)
+    pub fn validate(&self, res: &NpcResEntry) -> Vec<EquipError> {
+        let mut errors = Vec::new();
+        for cat in &self.categories {
+            let mut all_items: Vec<&str> =
+                cat.slots.iter().flat_map(|&s| res.part_items(s)).collect();
+            all_items.sort();
+            all_items.dedup();
+
+            for item in all_items {
+                for &slot in &cat.slots {
+                    if !res.has_part_item(slot, item) {
+                        errors.push(EquipError::MissingPart {
+                            item: item.to_string(),
+                            slot,
+                        });
+                    }
+                }
+            }
+        }
+        errors
+    }
+
+    /// Bộ phận -> item, dùng trực tiếp cho composer
+    pub fn parts(&self) -> BTreeMap<PartSlot, String> {
+        let mut parts = BTreeMap::new();
+        for (&category, item) in &self.items {
+            if let Some(cat) = self.categories.get(category) {
+                for &slot in &cat.slots {
+                    parts.insert(slot, item.clone());
+                }
+            }
+        }
+        parts
+    }
+}
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct PartData {
@@ -100,3 +927,296 @@ pub struct NpcAssets {
     pub female: VisualSlots,
     pub npcs: HashMap<String, PartData>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn man() -> NpcResEntry {
+        serde_json::from_str(
+            r#"{
+                "type": "SpecialNpc",
+                "root_path": "/spr/npcres/man",
+                "weapon_logic": {
+                    "unmounted": {
+                        "Tay không": {
+                            "FightStand": "FreeStand3", "NormalStand1": "FreeStand2",
+                            "NormalWalk": "NormalWalk", "FightWalk": "FreeWalk", "NormalRun": "NormalRun",
+                            "Attack1": "FreeAttack", "Attack2": "FreeAttack",
+                            "Wound": "FreeWound", "Die": "FreeDie", "SitDown": "SitDown"
+                        },
+                        "đơn thủ đao 1": {
+                            "FightStand": "MeleeWStand", "NormalStand1": "FreeStand2",
+                            "Attack1": "MeleeWCut", "Attack2": "MeleeWPuncture",
+                            "Magic": "MeleeWMagic"
+                        }
+                    },
+                    "mounted": {
+                        "Tay không": {
+                            "FightStand": "RideStand", "NormalStand1": "RideStand",
+                            "NormalWalk": "RideWalk", "Attack1": "RideCut"
+                        }
+                    }
+                },
+                "shadows": {
+                    "FreeAttack": { "spr": "a.spr", "frames": 24, "directions": 8, "interval": 2 },
+                    "FreeDie": { "spr": "d.spr", "frames": 16, "directions": 8, "interval": 1 },
+                    "NormalWalk": { "spr": "w.spr", "frames": 32, "directions": 8, "interval": 1 }
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn tick_n(npc: &mut Npc, set: &dyn ActionSet, n: usize) -> Vec<AnimEvent> {
+        (0..n).filter_map(|_| npc.tick(set)).collect()
+    }
+
+    #[test]
+    fn commands_resolve_through_weapon_and_mount() {
+        let res = man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+
+        assert!(npc.command(NpcCommand::Walk, &res));
+        assert_eq!(npc.state, ActionId::NormalWalk);
+
+        npc.set_weapon("đơn thủ đao 1", &res);
+        assert!(npc.command(NpcCommand::Stand, &res));
+        assert!(npc.command(NpcCommand::Cast, &res));
+        assert_eq!(npc.state, ActionId::MeleeWMagic);
+        assert!(npc.fighting);
+
+        let mut rider = Npc::new(2, "MainMan", NpcKind::Special(Sex::Man));
+        assert!(rider.command(NpcCommand::Mount, &res));
+        assert_eq!(rider.state, ActionId::RideStand);
+        // Không ngồi được trên ngựa
+        assert!(!rider.command(NpcCommand::Sit, &res));
+        assert!(rider.command(NpcCommand::Walk, &res));
+        assert_eq!(rider.state, ActionId::RideWalk);
+        assert!(rider.command(NpcCommand::Dismount, &res));
+        assert_eq!(rider.state, ActionId::NormalWalk);
+    }
+
+    #[test]
+    fn attack_returns_to_stand_after_animation() {
+        let res = man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        assert!(npc.command(NpcCommand::Attack, &res));
+        assert_eq!(npc.state, ActionId::FreeAttack);
+        // Đang đánh thì không đi được
+        assert!(!npc.command(NpcCommand::Walk, &res));
+
+        // 3 frame x 2 tick
+        assert_eq!(tick_n(&mut npc, &res, 5), []);
+        assert_eq!(npc.frame, 2);
+        assert_eq!(
+            npc.tick(&res),
+            Some(AnimEvent::Finished(NpcAction::Attack1))
+        );
+        assert_eq!(npc.action, NpcAction::FightStand);
+        assert_eq!(npc.state, ActionId::FreeStand3);
+        assert_eq!(npc.frame, 0);
+
+        // Đánh lần sau dùng Attack2
+        assert!(npc.command(NpcCommand::Attack, &res));
+        assert_eq!(npc.action, NpcAction::Attack2);
+    }
+
+    #[test]
+    fn hurt_interrupts_attack_and_death_is_terminal() {
+        let res = man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        npc.command(NpcCommand::Attack, &res);
+        assert!(npc.command(NpcCommand::Hurt, &res));
+        assert_eq!(npc.state, ActionId::FreeWound);
+
+        assert!(npc.command(NpcCommand::Die, &res));
+        assert_eq!(
+            tick_n(&mut npc, &res, 10),
+            [AnimEvent::Finished(NpcAction::Die)]
+        );
+        assert!(npc.is_dead());
+        assert_eq!(npc.frame, 1);
+        assert!(!npc.command(NpcCommand::Stand, &res));
+        assert!(!npc.command(NpcCommand::Hurt, &res));
+        assert_eq!(npc.state, ActionId::FreeDie);
+    }
+
+    #[test]
+    fn looping_action_keeps_frame_on_repeat_command() {
+        let res = man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        npc.command(NpcCommand::Walk, &res);
+        tick_n(&mut npc, &res, 3);
+        assert!(npc.command(NpcCommand::Walk, &res));
+        assert_eq!(npc.frame, 3);
+        assert_eq!(
+            tick_n(&mut npc, &res, 1),
+            [AnimEvent::Looped(NpcAction::NormalWalk, npc.state)]
+        );
+        assert_eq!(npc.frame, 0);
+    }
+
+    #[test]
+    fn moves_along_path_and_stops() {
+        let res = man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        npc.pos = WorldPos::new(1, 100, 100);
+        let path = [WorldPos::new(1, 100, 112), WorldPos::new(1, 80, 112)];
+        assert!(npc.move_along(path, false, &res));
+        assert_eq!(npc.dir, 0);
+        assert_eq!(npc.speed(), WALK_SPEED);
+
+        npc.tick(&res);
+        assert_eq!(npc.pos, WorldPos::new(1, 100, 105));
+        npc.tick(&res);
+        npc.tick(&res);
+        // Qua góc trong một tick: 2 điểm xuống, 3 điểm sang trái
+        assert_eq!(npc.pos, WorldPos::new(1, 97, 112));
+        assert_eq!(npc.dir, 16);
+        assert_eq!(npc.sprite_dir(8), 2);
+
+        for _ in 0..10 {
+            npc.tick(&res);
+        }
+        assert_eq!(npc.pos, WorldPos::new(1, 80, 112));
+        assert!(npc.path().is_empty());
+        assert_eq!(npc.action, NpcAction::NormalStand1);
+    }
+
+    #[test]
+    fn diagonal_steps_keep_subpixel_remainder() {
+        let res = man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        let target = WorldPos::new(0, 300, 400);
+        assert!(npc.move_along([target], true, &res));
+        let mut ticks = 0;
+        while !npc.path().is_empty() {
+            let before = npc.pos;
+            npc.tick(&res);
+            assert!(before.distance_to(&npc.pos) <= RUN_SPEED as f32 + 1.0);
+            ticks += 1;
+        }
+        // 500 điểm / 10 điểm mỗi tick
+        assert_eq!(ticks, 50);
+        assert_eq!(npc.pos, target);
+    }
+
+    #[test]
+    fn attack_cancels_movement() {
+        let res = man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        npc.move_along([WorldPos::new(0, 0, 500)], false, &res);
+        npc.tick(&res);
+        assert!(npc.command(NpcCommand::Attack, &res));
+        assert!(npc.path().is_empty());
+        let pos = npc.pos;
+        npc.tick(&res);
+        assert_eq!(npc.pos, pos);
+    }
+
+    #[test]
+    fn normal_npc_uses_default_actions() {
+        let res: NpcResEntry =
+            serde_json::from_str(r#"{ "type": "NormalNpc", "root_path": "/spr/npcres/animal" }"#)
+                .unwrap();
+        let mut npc = Npc::new(9, "ani001", NpcKind::Normal);
+        assert!(npc.command(NpcCommand::Run, &res));
+        assert_eq!(npc.state, ActionId::NormalRun);
+        // Không có thông tin animation: một frame rồi quay về đứng
+        npc.command(NpcCommand::Hurt, &res);
+        assert_eq!(npc.tick(&res), Some(AnimEvent::Finished(NpcAction::Wound)));
+        assert_eq!(npc.action, NpcAction::NormalStand1);
+    }
+
+    // Chỉ có dòng đánh, không có dòng đứng
+    struct AttackOnly;
+
+    impl ActionSet for AttackOnly {
+        fn resolve(&self, _weapon: &str, _mounted: bool, action: NpcAction) -> Option<ActionId> {
+            (action == NpcAction::Attack1).then_some(ActionId::MeleeWCut)
+        }
+
+        fn timing(&self, _action: NpcAction, _state: ActionId) -> Option<ActionTiming> {
+            None
+        }
+    }
+
+    #[test]
+    fn one_shot_end_resets_state_without_stand_row() {
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        assert!(npc.command(NpcCommand::Attack, &AttackOnly));
+        assert_eq!(npc.state, ActionId::MeleeWCut);
+
+        assert_eq!(
+            npc.tick(&AttackOnly),
+            Some(AnimEvent::Finished(NpcAction::Attack1))
+        );
+        assert_eq!(npc.action, NpcAction::FightStand);
+        assert_eq!(npc.state, ActionId::FreeStand3);
+        assert!(!npc.is_busy());
+    }
+
+    // Đầu khôi điều khiển Head + Hair, "Khôi 02" thiếu trong bảng Hair
+    fn helmets() -> (NpcResEntry, Equipment) {
+        let res: NpcResEntry = serde_json::from_str(
+            r#"{
+                "type": "SpecialNpc",
+                "root_path": "/spr/npcres/man",
+                "components": {
+                    "head": { "Khôi 01": {}, "Khôi 02": {} },
+                    "hair": { "Khôi 01": {} }
+                }
+            }"#,
+        )
+        .unwrap();
+        let outfit = Equipment::new(vec![EquipCategory {
+            index: 0,
+            name: "Đầu khôi".to_string(),
+            slots: vec![PartSlot::Head, PartSlot::Hair],
+        }]);
+        (res, outfit)
+    }
+
+    #[test]
+    fn equip_sets_every_linked_slot() {
+        let (res, mut outfit) = helmets();
+        assert_eq!(outfit.category_of(PartSlot::Hair), Some(0));
+        assert_eq!(outfit.valid_items(&res, 0), ["Khôi 01"]);
+
+        assert_eq!(outfit.equip(&res, 0, "Khôi 01"), Ok(()));
+        assert_eq!(outfit.item(0), Some("Khôi 01"));
+        let parts: Vec<_> = outfit.parts().into_iter().collect();
+        assert_eq!(
+            parts,
+            [
+                (PartSlot::Head, "Khôi 01".to_string()),
+                (PartSlot::Hair, "Khôi 01".to_string()),
+            ]
+        );
+
+        assert_eq!(outfit.unequip(0), Some("Khôi 01".to_string()));
+        assert_eq!(outfit.unequip(0), None);
+        assert!(outfit.parts().is_empty());
+    }
+
+    #[test]
+    fn equip_errors_keep_previous_item() {
+        let (res, mut outfit) = helmets();
+        outfit.equip(&res, 0, "Khôi 01").unwrap();
+
+        let err = outfit.equip(&res, 3, "Khôi 01").unwrap_err();
+        assert_eq!(err, EquipError::UnknownCategory(3));
+        assert_eq!(err.to_string(), "Không có loại trang bị #3");
+
+        // Có trong bảng Head nhưng thiếu ở Hair
+        let missing = EquipError::MissingPart {
+            item: "Khôi 02".to_string(),
+            slot: PartSlot::Hair,
+        };
+        assert_eq!(outfit.equip(&res, 0, "Khôi 02"), Err(missing.clone()));
+        assert_eq!(outfit.item(0), Some("Khôi 01"));
+        assert_eq!(outfit.validate(&res), [missing]);
+        assert_eq!(outfit.valid_items(&res, 3), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,947 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::bundle;
+use crate::common::ini::IniFile;
+use crate::common::npc::{
+    ActionId, ActionSet, ActionTiming, EquipCategory, NpcAction, PartSlot, UNARMED_WEAPON,
+};
+use crate::common::pak::game_path;
+use crate::common::tabfile::{TabFile, TabRow, parse_int_list};
+use crate::common::world::GAME_FPS;
+
+// --- MODEL CỦA data/newdata/npcres.json ---
+
+/// Một dòng trong `人物类型.txt` sau khi được `jx npcres build` chuyển sang JSON
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NpcResEntry {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub root_path: String,
+    // mounted/unmounted -> tên vũ khí -> lệnh (FightStand, Attack1...) -> tên action
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub weapon_logic: HashMap<String, HashMap<String, HashMap<String, String>>>,
+    // bộ phận (head, body...) -> tên item -> tên action -> đường dẫn spr
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub components: HashMap<String, HashMap<String, HashMap<String, String>>>,
+    // bộ phận -> tên item theo thứ tự dòng trong bảng bộ phận; index là res id của item
+    // (bảng vũ khí 关联表 cùng thứ tự dòng với bảng vũ khí phải)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub part_rows: HashMap<String, Vec<String>>,
+    // section -> key -> value thô (Dir1=-1,14,13,...)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub render_order: HashMap<String, HashMap<String, String>>,
+    // tên action -> bóng (主角动作阴影对应表.txt)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub shadows: HashMap<String, ActionSprInfo>,
+    // NPC thường: tên NpcAction -> spr (普通npc资源.txt + 普通npc资源信息.txt)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub actions: HashMap<String, ActionSprInfo>,
+    // tên action (ActionId với nhân vật chính, NpcAction với NPC thường) -> file wav
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sounds: HashMap<String, String>,
+    // các loại trang bị (部件列表.txt)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub part_list: Vec<EquipCategory>,
+}
+
+impl NpcResEntry {
+    pub fn is_special(&self) -> bool {
+        self.kind == "SpecialNpc"
+    }
+
+    /// Danh sách item của một bộ phận (sắp xếp để UI/CLI ổn định)
+    pub fn part_items(&self, slot: PartSlot) -> Vec<&str> {
+        let mut items: Vec<&str> = self
+            .components
+            .get(&slot.component_key())
+            .map(|m| m.keys().map(|k| k.as_str()).collect())
+            .unwrap_or_default();
+        items.sort();
+        items
+    }
+
+    /// Tên item ở dòng `res_id` của bảng bộ phận `slot` (res id trong bảng item)
+    pub fn part_by_res_id(&self, slot: PartSlot, res_id: u32) -> Option<&str> {
+        self.part_rows
+            .get(&slot.component_key())?
+            .get(res_id as usize)
+            .map(|s| s.as_str())
+            .filter(|s| !s.is_empty())
+    }
+
+    pub fn has_part_item(&self, slot: PartSlot, item: &str) -> bool {
+        self.components
+            .get(&slot.component_key())
+            .is_some_and(|m| m.contains_key(item))
+    }
+
+    /// Đường dẫn spr của `item` thuộc bộ phận `slot` khi đang diễn `action`
+    pub fn part_spr_path(&self, slot: PartSlot, item: &str, action: ActionId) -> Option<&str> {
+        let actions = self.components.get(&slot.component_key())?.get(item)?;
+        actions
+            .iter()
+            .find(|(name, _)| ActionId::from_name(name) == Some(action))
+            .map(|(_, path)| path.as_str())
+    }
+
+    /// File wav phát khi nhân vật chính bắt đầu `action`
+    pub fn action_sound(&self, action: ActionId) -> Option<&str> {
+        self.sounds
+            .iter()
+            .find(|(name, _)| ActionId::from_name(name) == Some(action))
+            .map(|(_, wav)| wav.as_str())
+    }
+
+    /// File wav phát khi NPC thường bắt đầu `action`
+    pub fn npc_action_sound(&self, action: NpcAction) -> Option<&str> {
+        self.sounds
+            .iter()
+            .find(|(name, _)| NpcAction::from_name(name) == Some(action))
+            .map(|(_, wav)| wav.as_str())
+    }
+
+    /// Bóng của nhân vật khi đang diễn `action`
+    pub fn shadow(&self, action: ActionId) -> Option<&ActionSprInfo> {
+        self.shadows
+            .iter()
+            .find(|(name, _)| ActionId::from_name(name) == Some(action))
+            .map(|(_, info)| info)
+    }
+
+    /// Spr của NPC thường khi đang diễn `action`
+    pub fn npc_action_spr(&self, action: NpcAction) -> Option<&ActionSprInfo> {
+        self.actions
+            .iter()
+            .find(|(name, _)| NpcAction::from_name(name) == Some(action))
+            .map(|(_, info)| info)
+    }
+}
+
+impl ActionSet for NpcResEntry {
+    /// Tra bảng vũ khí (vũ khí lạ thì coi như tay không).
+    /// NPC không có bảng vũ khí dùng dòng tay không mặc định.
+    fn resolve(&self, weapon: &str, mounted: bool, action: NpcAction) -> Option<ActionId> {
+        let key = if mounted { "mounted" } else { "unmounted" };
+        let Some(table) = self.weapon_logic.get(key) else {
+            return action.default_action(mounted);
+        };
+        let row = table.get(weapon).or_else(|| table.get(UNARMED_WEAPON))?;
+        row.iter()
+            .find(|(name, _)| NpcAction::from_name(name) == Some(action))
+            .and_then(|(_, id)| ActionId::from_name(id))
+    }
+
+    /// Nhân vật chính lấy theo bảng bóng (bóng đồng bộ frame với body).
+    /// NPC thường lấy theo bảng spr, cột interval ở đây là ms mỗi frame (85, 200...)
+    /// chứ không phải tick như bảng của nhân vật chính nên đổi sang tick.
+    fn timing(&self, action: NpcAction, state: ActionId) -> Option<ActionTiming> {
+        if self.is_special() {
+            let shadow = self.shadow(state)?;
+            return Some(ActionTiming {
+                frames: shadow.frames_per_direction(),
+                interval: shadow.interval.max(1),
+            });
+        }
+        let info = self.npc_action_spr(action)?;
+        Some(ActionTiming {
+            frames: info.frames_per_direction(),
+            interval: ((info.interval * GAME_FPS + 500) / 1000).max(1),
+        })
+    }
+}
+
+/// Phiên bản định dạng npcres.json. Tăng khi đổi cấu trúc để file cũ báo lỗi rõ ràng
+/// thay vì đọc thiếu dữ liệu.
+pub const NPCRES_SCHEMA_VERSION: u32 = 2;
+
+// { "schema_version": 2, "characters": { tên nhân vật -> NpcResEntry } }
+#[derive(Deserialize)]
+struct NpcResFile {
+    #[serde(default)]
+    schema_version: u32,
+    characters: BTreeMap<String, NpcResEntry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NpcResDb {
+    pub chars: BTreeMap<String, NpcResEntry>,
+}
+
+impl NpcResDb {
+    /// Đọc npcres.json hoặc bundle nhị phân (`jx npcres bundle`), nhận theo magic đầu file
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        if bundle::is_bundle(&data) {
+            bundle::decode_npcres(&data)
+        } else {
+            Self::from_reader(data.as_slice())
+        }
+    }
+
+    pub fn from_reader<R: io::Read>(reader: R) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let file: NpcResFile = serde_json::from_reader(reader).map_err(|e| {
+            invalid(format!(
+                "{} (file cũ hoặc hỏng? chạy lại `jx npcres build`)",
+                e
+            ))
+        })?;
+        if file.schema_version != NPCRES_SCHEMA_VERSION {
+            return Err(invalid(format!(
+                "npcres.json phiên bản {}, cần {}: chạy lại `jx npcres build`",
+                file.schema_version, NPCRES_SCHEMA_VERSION
+            )));
+        }
+        Ok(NpcResDb {
+            chars: file.characters,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NpcResEntry> {
+        self.chars.get(name)
+    }
+
+    /// Dạng JSON giống hệt file `jx npcres build` ghi ra (key sắp xếp)
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "schema_version": NPCRES_SCHEMA_VERSION,
+            "characters": self.chars,
+        })
+    }
+}
+
+// --- BÓNG (主角动作阴影对应表.txt) ---
+
+/// Spr của một action (bóng, NPC thường): file spr + cột info "frames,directions,interval"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ActionSprInfo {
+    pub spr: String,
+    pub frames: u32,
+    pub directions: u32,
+    pub interval: u32,
+}
+
+impl ActionSprInfo {
+    fn from_cells(spr: &str, info: &str) -> Self {
+        let info = parse_int_list(info);
+        let field = |i: usize| info.get(i).copied().unwrap_or(0).max(0) as u32;
+        ActionSprInfo {
+            spr: spr.to_string(),
+            frames: field(0),
+            directions: field(1),
+            interval: field(2),
+        }
+    }
+
+    pub fn frames_per_direction(&self) -> u32 {
+        (self.frames / self.directions.max(1)).max(1)
+    }
+}
+
+/// Parse bảng bóng: mỗi dòng là một nhân vật, sau cột tên là từng cặp cột
+/// (spr, info) theo đúng thứ tự `ActionId`. Header gốc có vài tên cột sai
+/// (NormalRuninfo bị lỗi font, RangeWDie ghi nhầm thành MeleeWDie) nên đọc theo vị trí cột.
+/// Đường dẫn spr giữ nguyên như trong bảng (tương đối so với ResFilePath).
+pub fn parse_shadow_table(table: &TabFile) -> BTreeMap<String, BTreeMap<ActionId, ActionSprInfo>> {
+    let mut result = BTreeMap::new();
+
+    for row in table.rows() {
+        let char_name = row.cell(0);
+        if char_name.is_empty() {
+            continue;
+        }
+
+        let mut actions = BTreeMap::new();
+        for action in ActionId::ALL {
+            let col = 1 + action as usize * 2;
+            let spr = row.cell(col);
+            if spr.is_empty() {
+                continue;
+            }
+            actions.insert(action, ActionSprInfo::from_cells(spr, row.cell(col + 1)));
+        }
+        result.insert(char_name.to_string(), actions);
+    }
+
+    result
+}
+
+// --- NPC THƯỜNG (普通npc资源.txt + 普通npc资源信息.txt) ---
+
+/// Parse cặp bảng spr / info của NPC thường: mỗi dòng là một NPC, các cột sau tên
+/// theo đúng thứ tự `NpcAction`. Ô info có dấu nháy ("48,8,200"), `TabFile` đã bỏ nháy.
+/// Đường dẫn spr giữ nguyên như trong bảng (tương đối so với ResFilePath).
+pub fn parse_npc_res_table(
+    spr_table: &TabFile,
+    info_table: &TabFile,
+) -> BTreeMap<String, BTreeMap<NpcAction, ActionSprInfo>> {
+    let infos: HashMap<&str, TabRow> = info_table
+        .rows()
+        .filter(|row| !row.cell(0).is_empty())
+        .map(|row| (row.cell(0), row))
+        .collect();
+
+    let mut result = BTreeMap::new();
+    for row in spr_table.rows() {
+        let npc = row.cell(0);
+        if npc.is_empty() {
+            continue;
+        }
+        let info_row = infos.get(npc);
+        let mut actions = BTreeMap::new();
+        for action in NpcAction::ALL {
+            let col = 1 + action as usize;
+            let spr = row.cell(col);
+            if spr.is_empty() {
+                continue;
+            }
+            let info = info_row.map(|r| r.cell(col)).unwrap_or("");
+            actions.insert(action, ActionSprInfo::from_cells(spr, info));
+        }
+        result.insert(npc.to_string(), actions);
+    }
+
+    result
+}
+
+// --- DANH SÁCH TRANG BỊ (部件列表.txt) ---
+
+/// Parse bảng loại trang bị: Số thứ tự, Tên loại, rồi tối đa 4 cột tên bộ phận
+pub fn parse_part_list(table: &TabFile) -> Vec<EquipCategory> {
+    let mut result = Vec::new();
+
+    for row in table.rows() {
+        let Ok(index) = row.cell(0).parse::<u32>() else {
+            continue;
+        };
+        result.push(EquipCategory {
+            index,
+            name: row.cell(1).to_string(),
+            slots: row
+                .cells()
+                .iter()
+                .skip(2)
+                .filter_map(|c| PartSlot::from_column_name(c))
+                .collect(),
+        });
+    }
+
+    result.sort_by_key(|c| c.index);
+    result
+}
+
+// --- ÂM THANH (主角动作声音表.txt / npc动作声音表.txt) ---
+
+/// Parse bảng âm thanh nhân vật chính: mỗi dòng là một action (theo thứ tự `ActionId`,
+/// bảng gốc ghi nhầm RangeWDie thành MeleeWDie), mỗi cột là một nhân vật.
+/// Kết quả: tên nhân vật -> action -> file wav
+pub fn parse_special_sound_table(table: &TabFile) -> BTreeMap<String, BTreeMap<ActionId, String>> {
+    let headers = table.headers().get(1..).unwrap_or_default();
+
+    let mut result: BTreeMap<String, BTreeMap<ActionId, String>> = headers
+        .iter()
+        .filter(|h| !h.is_empty())
+        .map(|h| (h.clone(), BTreeMap::new()))
+        .collect();
+
+    for (action, row) in ActionId::ALL.iter().zip(table.rows()) {
+        for (header, wav) in headers.iter().zip(row.cells().iter().skip(1)) {
+            if let Some(actions) = result.get_mut(header)
+                && !wav.is_empty()
+            {
+                actions.insert(*action, wav.to_string());
+            }
+        }
+    }
+    result
+}
+
+/// Parse bảng âm thanh NPC thường: mỗi dòng là một NPC, các cột theo thứ tự `NpcAction`.
+/// Kết quả: tên NPC -> hành động -> file wav (NPC không có âm thanh nào bị bỏ qua)
+pub fn parse_npc_sound_table(table: &TabFile) -> BTreeMap<String, BTreeMap<NpcAction, String>> {
+    let mut result = BTreeMap::new();
+
+    for row in table.rows() {
+        let npc_name = row.cell(0);
+        if npc_name.is_empty() {
+            continue;
+        }
+
+        let actions: BTreeMap<NpcAction, String> = NpcAction::ALL
+            .iter()
+            .zip(row.cells().iter().skip(1))
+            .filter(|(_, wav)| !wav.is_empty())
+            .map(|(action, wav)| (*action, wav.to_string()))
+            .collect();
+        if !actions.is_empty() {
+            result.insert(npc_name.to_string(), actions);
+        }
+    }
+    result
+}
+
+// --- HIỆU ỨNG TRẠNG THÁI (状态图形对照表.txt) ---
+
+/// Vị trí gắn hiệu ứng trên nhân vật
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttachPoint {
+    Head,
+    Body,
+    Foot,
+    // Chỉ hiện trên bản đồ nhỏ, không vẽ lên nhân vật
+    MiniMap,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    // Cột "Loop" để trống: chạy hết một lượt rồi tự tắt
+    Once,
+}
+
+/// Hiệu ứng vẽ trước hay sau nhân vật (cột 图层)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusDepth {
+    Front,
+    Back,
+}
+
+/// Một dòng trong `状态图形对照表.txt`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusEffectVisual {
+    // "Status1", "Status2"...
+    pub id: String,
+    // `None` khi cột spr là "Special" (engine tự vẽ, không có file spr)
+    pub spr: Option<String>,
+    pub attach: AttachPoint,
+    pub mode: PlayMode,
+    // Đoạn frame lặp lại sau lượt chạy đầu, (0, 0) = lặp toàn bộ
+    pub loop_start: u32,
+    pub loop_end: u32,
+    pub frames: u32,
+    pub directions: u32,
+    // Số game frame (1/18 giây) cho mỗi frame của spr
+    pub interval: u32,
+    pub depth: StatusDepth,
+    pub name: String,
+}
+
+impl StatusEffectVisual {
+    /// Frame của spr sau `elapsed` game frame kể từ lúc gắn hiệu ứng.
+    /// `None` khi hiệu ứng chạy một lần đã kết thúc.
+    pub fn frame_at(&self, elapsed: u64) -> Option<u32> {
+        let frames = self.frames.max(1) as u64;
+        let step = elapsed / self.interval.max(1) as u64;
+
+        match self.mode {
+            PlayMode::Once => (step < frames).then_some(step as u32),
+            PlayMode::Loop => {
+                let start = self.loop_start as u64;
+                let end = (self.loop_end as u64).min(frames - 1);
+                if end <= start {
+                    // Không có đoạn lặp riêng: lặp toàn bộ
+                    return Some((step % frames) as u32);
+                }
+                if step <= end {
+                    // Lượt chạy đầu tiên
+                    return Some(step as u32);
+                }
+                Some((start + (step - end - 1) % (end - start + 1)) as u32)
+            }
+        }
+    }
+}
+
+/// Parse bảng hiệu ứng trạng thái. Header gốc bị lỗi font nên đọc theo vị trí cột:
+/// Status, Spr, Vị trí, Loop, Frame lặp đầu, Frame lặp cuối, Số frame, Số hướng, Interval, Layer, Tên
+pub fn parse_status_table(table: &TabFile) -> Vec<StatusEffectVisual> {
+    let mut result = Vec::new();
+
+    for row in table.rows() {
+        let cell = |i: usize| row.cell(i);
+        let num = |i: usize| cell(i).parse::<u32>().unwrap_or(0);
+
+        if cell(0).is_empty() {
+            continue;
+        }
+        let attach = match cell(2) {
+            "Head" => AttachPoint::Head,
+            "Foot" => AttachPoint::Foot,
+            "MiniMap" => AttachPoint::MiniMap,
+            _ => AttachPoint::Body,
+        };
+        let spr = match cell(1) {
+            "" | "Special" => None,
+            path => Some(path.replace('\\', "/")),
+        };
+
+        result.push(StatusEffectVisual {
+            id: cell(0).to_string(),
+            spr,
+            attach,
+            mode: if cell(3).eq_ignore_ascii_case("Loop") {
+                PlayMode::Loop
+            } else {
+                PlayMode::Once
+            },
+            loop_start: num(4),
+            loop_end: num(5),
+            frames: num(6),
+            directions: num(7),
+            interval: num(8),
+            depth: if num(9) == 2 {
+                StatusDepth::Back
+            } else {
+                StatusDepth::Front
+            },
+            name: cell(10).to_string(),
+        });
+    }
+
+    result
+}
+
+// --- THỨ TỰ VẼ (贴图顺序表.txt) ---
+
+/// Thứ tự layer của một section (một action hoặc DEFAULT)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActionRenderOrder {
+    // DirN (1-based) -> các bộ phận theo thứ tự vẽ (dưới -> trên)
+    pub dirs: BTreeMap<u32, Vec<PartSlot>>,
+    // LineN=frame,... : ngoại lệ cho một frame tuyệt đối trong file spr
+    pub frames: HashMap<u32, Vec<PartSlot>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderOrder {
+    pub default: ActionRenderOrder,
+    pub actions: HashMap<ActionId, ActionRenderOrder>,
+    pub unit_num: usize,
+    pub dir_num: usize,
+}
+
+impl RenderOrder {
+    pub fn from_sections(sections: &HashMap<String, HashMap<String, String>>) -> Self {
+        let mut order = RenderOrder::default();
+
+        for (section, keys) in sections {
+            let mut parsed = ActionRenderOrder::default();
+
+            for (key, value) in keys {
+                if let Some(n) = key.strip_prefix("Dir").and_then(|n| n.parse::<u32>().ok()) {
+                    // Số đầu tiên luôn là -1 (không gắn với frame nào)
+                    let ids = parse_int_list(value);
+                    parsed.dirs.insert(
+                        n,
+                        ids.iter()
+                            .skip(1)
+                            .filter_map(|&id| PartSlot::from_part_id(id))
+                            .collect(),
+                    );
+                } else if key.starts_with("Line") {
+                    // Số đầu tiên là frame tuyệt đối, phần còn lại là thứ tự layer
+                    let ids = parse_int_list(value);
+                    if let Some((&frame, rest)) = ids.split_first()
+                        && frame >= 0
+                    {
+                        let slots = rest
+                            .iter()
+                            .filter_map(|&id| PartSlot::from_part_id(id))
+                            .collect();
+                        parsed.frames.insert(frame as u32, slots);
+                    }
+                } else if key == "UnitNum" {
+                    order.unit_num = value.trim().parse().unwrap_or(0);
+                } else if key == "DirNum" {
+                    order.dir_num = value.trim().parse().unwrap_or(0);
+                }
+            }
+
+            if section == "DEFAULT" {
+                order.default = parsed;
+            } else if let Some(action) = ActionId::from_name(section) {
+                order.actions.insert(action, parsed);
+            }
+        }
+
+        order
+    }
+
+    /// Đọc thẳng từ file 贴图顺序表.txt gốc (dạng ini)
+    pub fn from_ini(ini: &IniFile) -> Self {
+        // Section lặp lại gộp vào cùng một tên
+        let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for section in ini.sections() {
+            sections
+                .entry(section.name().to_string())
+                .or_default()
+                .extend(
+                    section
+                        .entries()
+                        .map(|(key, value)| (key.to_string(), value.to_string())),
+                );
+        }
+        Self::from_sections(&sections)
+    }
+
+    /// Số hướng của bảng. Bảng gốc ghi DirNum=18 nhưng chỉ có Dir1..Dir16 nên lấy
+    /// không quá DirN lớn nhất của DEFAULT (thiếu DirNum thì dùng luôn số đó)
+    pub fn table_dirs(&self) -> u32 {
+        let defined = self.default.dirs.keys().max().copied().unwrap_or(0);
+        match (self.dir_num as u32, defined) {
+            (0, defined) => defined.max(1),
+            (n, 0) => n,
+            (n, defined) => n.min(defined),
+        }
+    }
+
+    /// Key DirN (1-based) ứng với hướng `dir` của spr có `sprite_dirs` hướng
+    /// (spr thường 8 hướng, bảng 16 hướng: hướng 1 của spr là Dir3)
+    pub fn dir_key(&self, dir: u32, sprite_dirs: u32) -> u32 {
+        let sprite_dirs = sprite_dirs.max(1);
+        (dir % sprite_dirs) * self.table_dirs() / sprite_dirs + 1
+    }
+
+    /// Thứ tự layer cho `action` ở hướng `dir` (0-based, trên `sprite_dirs` hướng của spr),
+    /// `abs_frame` là frame tuyệt đối của layer body (dùng cho các dòng LineN).
+    /// Thứ tự ưu tiên: LineN của action -> DirN của action -> DirN của DEFAULT -> Dir1 của DEFAULT
+    pub fn layers(
+        &self,
+        action: ActionId,
+        dir: u32,
+        sprite_dirs: u32,
+        abs_frame: u32,
+    ) -> &[PartSlot] {
+        let key = self.dir_key(dir, sprite_dirs);
+        let action_order = self.actions.get(&action);
+
+        if let Some(slots) = action_order.and_then(|o| o.frames.get(&abs_frame)) {
+            return slots;
+        }
+        if let Some(slots) = action_order.and_then(|o| o.dirs.get(&key)) {
+            return slots;
+        }
+        if let Some(slots) = self.default.dirs.get(&key) {
+            return slots;
+        }
+        self.default
+            .dirs
+            .get(&1)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+// --- TÀI NGUYÊN THAM CHIẾU ---
+// Danh sách file cần lấy từ pak cho các nhân vật (giải nén, đóng gói lại, kiểm tra thiếu file)
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    // Spr bộ phận (components) của nhân vật chính, spr action của NPC thường
+    Spr,
+    Shadow,
+    Sound,
+    Status,
+}
+
+impl AssetKind {
+    pub const ALL: [AssetKind; 4] = [
+        AssetKind::Spr,
+        AssetKind::Shadow,
+        AssetKind::Sound,
+        AssetKind::Status,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AssetKind::Spr => "spr",
+            AssetKind::Shadow => "shadow",
+            AssetKind::Sound => "sound",
+            AssetKind::Status => "status",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|k| k.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Một file được tham chiếu. `path` là đường dẫn game đúng dạng để hash (`\spr\...`)
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AssetRef {
+    pub kind: AssetKind,
+    pub path: String,
+    // Nhân vật (hoặc id trạng thái) tham chiếu đầu tiên
+    pub owner: String,
+}
+
+impl NpcResEntry {
+    /// Gọi `visit` cho mọi file mà nhân vật tham chiếu (có thể lặp lại).
+    /// File wav trong bảng chỉ có tên, nằm trong `sound_root` (vd `\sound`).
+    pub fn visit_assets(&self, sound_root: &str, mut visit: impl FnMut(AssetKind, String)) {
+        for items in self.components.values() {
+            for actions in items.values() {
+                for spr in actions.values() {
+                    visit(AssetKind::Spr, game_path(spr));
+                }
+            }
+        }
+        for info in self.actions.values() {
+            visit(AssetKind::Spr, game_path(&info.spr));
+        }
+        for info in self.shadows.values() {
+            visit(AssetKind::Shadow, game_path(&info.spr));
+        }
+        for wav in self.sounds.values() {
+            let path = format!("{}\\{}", sound_root.trim_end_matches(['\\', '/']), wav);
+            visit(AssetKind::Sound, game_path(&path));
+        }
+    }
+}
+
+impl NpcResDb {
+    /// Mọi file mà các nhân vật tham chiếu, mỗi đường dẫn một lần (xem `merge_assets`)
+    pub fn assets(&self, sound_root: &str) -> Vec<AssetRef> {
+        let mut refs = Vec::new();
+        for (name, entry) in &self.chars {
+            entry.visit_assets(sound_root, |kind, path| {
+                refs.push(AssetRef {
+                    kind,
+                    path,
+                    owner: name.clone(),
+                })
+            });
+        }
+        merge_assets(refs)
+    }
+}
+
+/// Spr của các hiệu ứng trạng thái (bỏ qua dòng "Special" không có file)
+pub fn status_assets(statuses: &[StatusEffectVisual]) -> Vec<AssetRef> {
+    let refs = statuses
+        .iter()
+        .filter_map(|status| {
+            Some(AssetRef {
+                kind: AssetKind::Status,
+                path: game_path(status.spr.as_deref()?),
+                owner: status.id.clone(),
+            })
+        })
+        .collect();
+    merge_assets(refs)
+}
+
+/// Gộp danh sách: mỗi đường dẫn (không phân biệt hoa thường ASCII, như hash của pak) giữ
+/// một lần với loại và owner nhỏ nhất, sắp theo (loại, đường dẫn) để kết quả ổn định
+pub fn merge_assets(mut refs: Vec<AssetRef>) -> Vec<AssetRef> {
+    refs.retain(|r| r.path.len() > 1);
+    refs.sort_by(|a, b| {
+        (a.path.to_ascii_lowercase(), a.kind, &a.owner).cmp(&(
+            b.path.to_ascii_lowercase(),
+            b.kind,
+            &b.owner,
+        ))
+    });
+    refs.dedup_by(|b, a| a.path.eq_ignore_ascii_case(&b.path));
+    refs.sort_by(|a, b| (a.kind, &a.path).cmp(&(b.kind, &b.path)));
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_checks_schema_version() {
+        let json = r#"{"schema_version": 2, "characters": {
+            "ani001": {"type": "NormalNpc", "root_path": "/spr/npcres/animal/ani001"}
+        }}"#;
+        let db = NpcResDb::from_reader(json.as_bytes()).unwrap();
+        assert!(!db.get("ani001").unwrap().is_special());
+
+        // File phẳng cũ (không có schema_version) và phiên bản lạ đều bị từ chối
+        let legacy = r#"{"ani001": {"type": "NormalNpc", "root_path": "/a"}}"#;
+        assert!(NpcResDb::from_reader(legacy.as_bytes()).is_err());
+        let future = r#"{"schema_version": 99, "characters": {}}"#;
+        assert!(NpcResDb::from_reader(future.as_bytes()).is_err());
+    }
+
+    fn sections(text: &str) -> HashMap<String, HashMap<String, String>> {
+        let ini = IniFile::from_text(text);
+        ini.sections()
+            .map(|section| {
+                let keys = section
+                    .entries()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                (section.name().to_string(), keys)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn render_order_scales_sprite_directions() {
+        // Dir1: Body (1) dưới Head (0); Dir3: Head dưới Body; Dir16: RightWeapon (14) trên cùng
+        let mut text = String::from("[DEFAULT]\n");
+        for n in 1..=16 {
+            let ids = match n {
+                3 => "-1,0,1,",
+                16 => "-1,1,14,",
+                _ => "-1,1,0,",
+            };
+            text.push_str(&format!("Dir{}={}\n", n, ids));
+        }
+        text.push_str("UnitNum=11\nDirNum=18\n[MeleeWCut]\nDir1=-1,14,1,\nLine1=30,0,14,\n");
+        let order = RenderOrder::from_sections(&sections(&text));
+
+        assert_eq!(order.dir_num, 18);
+        assert_eq!(order.table_dirs(), 16);
+        assert_eq!(order.dir_key(1, 8), 3);
+        assert_eq!(order.dir_key(7, 8), 15);
+        assert_eq!(order.dir_key(15, 16), 16);
+
+        let body_head = [PartSlot::Body, PartSlot::Head];
+        assert_eq!(order.layers(ActionId::FreeStand1, 0, 8, 0), body_head);
+        // hướng 1 của spr 8 hướng là Dir3 chứ không phải Dir2
+        assert_eq!(
+            order.layers(ActionId::FreeStand1, 1, 8, 0),
+            [PartSlot::Head, PartSlot::Body]
+        );
+        assert_eq!(
+            order.layers(ActionId::FreeStand1, 15, 16, 0),
+            [PartSlot::Body, PartSlot::RightWeapon]
+        );
+
+        // action riêng: LineN theo frame tuyệt đối, rồi DirN của action, rồi DEFAULT
+        assert_eq!(
+            order.layers(ActionId::MeleeWCut, 0, 8, 30),
+            [PartSlot::Head, PartSlot::RightWeapon]
+        );
+        assert_eq!(
+            order.layers(ActionId::MeleeWCut, 0, 8, 0),
+            [PartSlot::RightWeapon, PartSlot::Body]
+        );
+        assert_eq!(order.layers(ActionId::MeleeWCut, 2, 8, 0), body_head);
+    }
+
+    #[test]
+    fn shadow_table_reads_columns_by_position() {
+        // Header hỏng như bảng gốc, chỉ cột 0 đúng: đọc theo cặp (spr, info) của từng ActionId
+        let mut header = vec!["CharacterName".to_string()];
+        header.extend((0..ActionId::ALL.len() * 2).map(|i| format!("x{}", i)));
+        let mut row = vec![String::new(); header.len()];
+        row[0] = "MainLady".to_string();
+        row[1] = "\\spr\\woman\\stand1.spr".to_string();
+        row[2] = "16,8,2".to_string();
+        let last = 1 + ActionId::RideStand2 as usize * 2;
+        row[last] = "ride.spr".to_string();
+        row[last + 1] = "8,8".to_string();
+        let text = format!(
+            "{}\n{}\n\tunnamed.spr\t1,1,1\n",
+            header.join("\t"),
+            row.join("\t")
+        );
+
+        let shadows = parse_shadow_table(&TabFile::from_text(&text));
+        assert_eq!(shadows.len(), 1);
+        let lady = &shadows["MainLady"];
+        assert_eq!(lady.len(), 2);
+        let stand = &lady[&ActionId::FreeStand1];
+        assert_eq!(stand.spr, "\\spr\\woman\\stand1.spr");
+        assert_eq!((stand.frames, stand.directions, stand.interval), (16, 8, 2));
+        assert_eq!(stand.frames_per_direction(), 2);
+        // ô info thiếu interval thì coi là 0
+        let ride = &lady[&ActionId::RideStand2];
+        assert_eq!((ride.spr.as_str(), ride.interval), ("ride.spr", 0));
+    }
+
+    #[test]
+    fn npc_res_table_pairs_spr_and_info() {
+        // Cột sau tên theo thứ tự NpcAction: FightStand, NormalStand1, NormalStand2, FightWalk...
+        let spr = TabFile::from_text(
+            "Npc\tFightStand\tNormalStand1\nani001\ta.spr\tb.spr\nani002\tc.spr\n\tx.spr\n",
+        );
+        let info = TabFile::from_text("Npc\tFightStand\tNormalStand1\nani001\t\"48,8,200\"\t\n");
+
+        let npcs = parse_npc_res_table(&spr, &info);
+        assert_eq!(npcs.len(), 2);
+        let stand = &npcs["ani001"][&NpcAction::FightStand];
+        assert_eq!(
+            (stand.frames, stand.directions, stand.interval),
+            (48, 8, 200)
+        );
+        // Thiếu ô info (hoặc cả dòng) thì vẫn giữ spr, số liệu bằng 0
+        assert_eq!(npcs["ani001"][&NpcAction::NormalStand1].frames, 0);
+        assert_eq!(npcs["ani002"][&NpcAction::FightStand].spr, "c.spr");
+    }
+
+    #[test]
+    fn timing_uses_shadow_or_npc_table() {
+        let json = r#"{"schema_version": 2, "characters": {
+            "MainMan": {"type": "SpecialNpc", "root_path": "/spr/npcres/man",
+                "shadows": {"FreeStand3": {"spr": "s.spr", "frames": 16, "directions": 8, "interval": 3}}},
+            "ani001": {"type": "NormalNpc", "root_path": "/spr/npcres/animal",
+                "actions": {"FightStand": {"spr": "a.spr", "frames": 48, "directions": 8, "interval": 200}}}
+        }}"#;
+        let db = NpcResDb::from_reader(json.as_bytes()).unwrap();
+
+        // Nhân vật chính: theo ActionId, interval đã là tick
+        let man = db.get("MainMan").unwrap();
+        let timing = man
+            .timing(NpcAction::FightStand, ActionId::FreeStand3)
+            .unwrap();
+        assert_eq!((timing.frames, timing.interval), (2, 3));
+        assert!(
+            man.timing(NpcAction::FightStand, ActionId::FreeStand1)
+                .is_none()
+        );
+
+        // NPC thường: theo NpcAction, 200 ms = 3.6 tick -> 4
+        let npc = db.get("ani001").unwrap();
+        let timing = npc
+            .timing(NpcAction::FightStand, ActionId::FreeStand3)
+            .unwrap();
+        assert_eq!((timing.frames, timing.interval), (6, 4));
+        assert!(
+            npc.timing(NpcAction::NormalWalk, ActionId::FreeStand3)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn assets_cover_every_reference_once() {
+        let json = r#"{"schema_version": 2, "characters": {
+            "MainLady": {"type": "SpecialNpc", "root_path": "/spr/npcres/woman",
+                "components": {"body": {"Thân thể 01": {
+                    "FightStand": "/spr/npcres/woman/a.spr", "FightRun": "/spr/npcres/woman/b.spr"}}},
+                "shadows": {"FightStand": {"spr": "/spr/npcres/woman/s.spr",
+                    "frames": 8, "directions": 8, "interval": 1}},
+                "sounds": {"FightStand": "sound_m1.wav", "FightRun": "sound_m1.wav"}},
+            "ani001": {"type": "NormalNpc", "root_path": "/spr/npcres/animal/ani001",
+                "actions": {"Die": {"spr": "/spr/npcres/woman/A.spr",
+                    "frames": 8, "directions": 8, "interval": 1}}}
+        }}"#;
+        let db = NpcResDb::from_reader(json.as_bytes()).unwrap();
+        let assets = db.assets("\\sound\\");
+        let paths: Vec<(AssetKind, &str)> =
+            assets.iter().map(|r| (r.kind, r.path.as_str())).collect();
+        assert_eq!(
+            paths,
+            [
+                // "A.spr" của ani001 trùng "a.spr" (hash không phân biệt hoa thường)
+                (AssetKind::Spr, "\\spr\\npcres\\woman\\a.spr"),
+                (AssetKind::Spr, "\\spr\\npcres\\woman\\b.spr"),
+                (AssetKind::Shadow, "\\spr\\npcres\\woman\\s.spr"),
+                (AssetKind::Sound, "\\sound\\sound_m1.wav"),
+            ]
+        );
+    }
+}
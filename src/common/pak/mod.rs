@@ -1,12 +1,18 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
 use encoding_rs::GBK;
 
+use crate::common::text::Encoding;
+
 // --- CONSTANTS ---
 pub const PACK_SIGNATURE: u32 = 0x4b434150; // 'PACK'
+// Header trên đĩa: signature, count, index_offset, data_offset, crc32, reserved[12]
+pub const PAK_HEADER_SIZE: u32 = 32;
+// Kích thước lưu trữ chỉ có 24 bit trong compress_flag
+pub const MAX_PAK_ENTRY_SIZE: usize = 0x00FF_FFFF;
 
 // --- STRUCTS ---
 
@@ -75,7 +81,7 @@ pub fn jx_file_name_hash(path: &str) -> u32 {
         // Xử lý chữ hoa -> chữ thường (Chỉ áp dụng cho ASCII 'A'-'Z')
         // Ký tự tiếng Trung (GBK) luôn có byte > 127 (hoặc < 0 khi là signed),
         // nên sẽ tự động bỏ qua check này (vì byte 'A' là 65).
-        if byte >= b'A' && byte <= b'Z' {
+        if byte.is_ascii_uppercase() {
             char_code = (byte + (b'a' - b'A')) as i8 as i32;
         }
 
@@ -159,13 +165,21 @@ impl PakReader {
         })
     }
 
+    /// Mọi entry trong index, sắp xếp theo id (hash tên file)
+    pub fn entries(&self) -> Vec<PakEntry> {
+        let mut entries: Vec<PakEntry> = self.index_map.values().copied().collect();
+        entries.sort_by_key(|e| e.id);
+        entries
+    }
+
     /// Tìm file trong Index đã cache
     pub fn find_file(&self, path: &str) -> Option<&PakEntry> {
         let target_hash = jx_file_name_hash(path);
         self.index_map.get(&target_hash)
     }
 
-    /// Đọc dữ liệu raw
+    /// Đọc dữ liệu đã giải nén. Kiểu nén chưa hỗ trợ (BZIP2, hiếm gặp trong client) là lỗi
+    /// `Unsupported` chứ không trả dữ liệu nén như thể đã giải nén.
     pub fn read_entry_data(&mut self, entry: &PakEntry) -> io::Result<Vec<u8>> {
         let stored_size = entry.get_stored_size();
         self.file.seek(SeekFrom::Start(entry.offset as u64))?;
@@ -181,28 +195,160 @@ impl PakReader {
             }
             1 => {
                 // UCL/NRV2B: Gọi hàm giải nén
-                // println!("   ... Decompressing (UCL) {} -> {} bytes", stored_size, entry.original_size);
-
                 nrv2b_decompress_8(&buffer, entry.original_size as usize).map_err(|e| {
                     io::Error::new(io::ErrorKind::InvalidData, format!("UCL Error: {}", e))
                 })
             }
-            2 => {
-                // BZIP2 (Hiếm gặp trong Client, thường ở Server)
-                // Hiện tại chưa implement, trả về raw
-                println!("⚠️ Warning: BZIP2 compression not supported yet.");
-                Ok(buffer)
-            }
-            _ => {
-                println!("⚠️ Warning: Unknown compression type.");
-                Ok(buffer)
+            2 => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Chưa hỗ trợ nén BZIP2",
+            )),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Kiểu nén lạ: {}", other),
+            )),
+        }
+    }
+}
+
+// --- GHI PAK ---
+
+/// Ghi file PAK không nén từ các cặp (đường dẫn game, dữ liệu): header, dữ liệu, rồi index
+/// sắp xếp theo id vì engine tìm file bằng tìm kiếm nhị phân. Trả về số file đã ghi.
+pub fn write_pak<P: AsRef<Path>>(path: P, files: &[(String, Vec<u8>)]) -> io::Result<usize> {
+    let mut entries: Vec<PakEntry> = Vec::with_capacity(files.len());
+    let mut offset = PAK_HEADER_SIZE;
+    for (name, data) in files {
+        if data.len() > MAX_PAK_ENTRY_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} lớn hơn 16MB, PAK không lưu được", name),
+            ));
+        }
+        entries.push(PakEntry {
+            id: jx_file_name_hash(name),
+            offset,
+            original_size: data.len() as u32,
+            compress_flag: data.len() as u32,
+        });
+        offset = offset
+            .checked_add(data.len() as u32)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "PAK lớn hơn 4GB"))?;
+    }
+
+    let mut sorted = entries.clone();
+    sorted.sort_by_key(|e| e.id);
+    if let Some(pair) = sorted.windows(2).find(|w| w[0].id == w[1].id) {
+        let names: Vec<&str> = files
+            .iter()
+            .zip(&entries)
+            .filter(|(_, e)| e.id == pair[0].id)
+            .map(|(f, _)| f.0.as_str())
+            .collect();
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Trùng hash {:08X}: {}", pair[0].id, names.join(", ")),
+        ));
+    }
+
+    let mut out = BufWriter::new(File::create(path)?);
+    let header = [
+        PACK_SIGNATURE,
+        files.len() as u32,
+        offset,
+        PAK_HEADER_SIZE,
+        0,
+    ];
+    for v in header {
+        out.write_all(&v.to_le_bytes())?;
+    }
+    out.write_all(&[0u8; 12])?;
+    for (_, data) in files {
+        out.write_all(data)?;
+    }
+    for e in &sorted {
+        for v in [e.id, e.offset, e.original_size, e.compress_flag] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+    }
+    out.flush()?;
+    Ok(files.len())
+}
+
+// --- PAK SET (NHIỀU FILE PAK) ---
+
+/// Đường dẫn game đúng dạng engine dùng để hash: phân cách bằng '\\', bắt đầu bằng '\\'
+/// ("/spr/a.spr" -> "\\spr\\a.spr")
+pub fn game_path(path: &str) -> String {
+    format!("\\{}", path.replace('/', "\\").trim_start_matches('\\'))
+}
+
+/// Tập nhiều file PAK, tìm file theo thứ tự đã add (file add trước được ưu tiên)
+#[derive(Default)]
+pub struct PakSet {
+    pub readers: Vec<PakReader>,
+    // Bảng mã văn bản của bản client (bảng, ini, tên trong file region); `None` là tự nhận diện
+    pub encoding: Option<Encoding>,
+}
+
+impl PakSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load tất cả file *.pak trong thư mục (sắp xếp theo tên cho ổn định)
+    pub fn open_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                p.extension()
+                    .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case("pak"))
+            })
+            .collect();
+        paths.sort();
+
+        let mut set = PakSet::new();
+        for path in paths {
+            set.add(PakReader::new(&path)?);
+        }
+        Ok(set)
+    }
+
+    pub fn add(&mut self, reader: PakReader) {
+        self.readers.push(reader);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readers.is_empty()
+    }
+
+    /// Các dạng đường dẫn cần thử: có/không có '\' ở đầu, có/không có tiền tố "spr\"
+    pub fn candidate_paths(path: &str) -> Vec<String> {
+        let full = game_path(path);
+        let trimmed = &full[1..];
+        let mut candidates = vec![full.clone(), trimmed.to_string()];
+        if let Some(rest) = trimmed.strip_prefix("spr\\") {
+            candidates.push(rest.to_string());
+        }
+        candidates
+    }
+
+    /// Đọc file (đã giải nén) theo đường dẫn game. `Ok(None)` nếu không pak nào chứa file.
+    pub fn read(&mut self, path: &str) -> io::Result<Option<Vec<u8>>> {
+        let candidates = Self::candidate_paths(path);
+        for reader in &mut self.readers {
+            for try_path in &candidates {
+                if let Some(entry) = reader.find_file(try_path).copied() {
+                    return reader.read_entry_data(&entry).map(Some);
+                }
             }
         }
+        Ok(None)
     }
 }
 
 /// Thuật toán giải nén NRV2B 8-bit (Port từ ucl/n2b_d.c)
-
 pub fn nrv2b_decompress_8(src: &[u8], dst_len: usize) -> Result<Vec<u8>, String> {
     let mut dst = Vec::with_capacity(dst_len);
 
@@ -325,3 +471,58 @@ pub fn nrv2b_decompress_8(src: &[u8], dst_len: usize) -> Result<Vec<u8>, String>
 
     Ok(dst)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_pak_reads_back() {
+        let path = std::env::temp_dir().join(format!("jx_pak_test_{}.pak", std::process::id()));
+        let files = vec![
+            ("\\settings\\a.txt".to_string(), b"hello".to_vec()),
+            ("\\spr\\人物.spr".to_string(), vec![7u8; 300]),
+            ("\\empty.ini".to_string(), Vec::new()),
+        ];
+        assert_eq!(write_pak(&path, &files).unwrap(), 3);
+
+        let mut set = PakSet::new();
+        set.add(PakReader::new(&path).unwrap());
+        assert_eq!(set.readers[0].header.count, 3);
+        let ids: Vec<u32> = set.readers[0].entries().iter().map(|e| e.id).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        // Tra không phân biệt hoa thường / dấu '\\' đầu như engine
+        assert_eq!(set.read("SETTINGS/A.TXT").unwrap(), Some(b"hello".to_vec()));
+        assert_eq!(set.read("\\spr\\人物.spr").unwrap(), Some(vec![7u8; 300]));
+        assert_eq!(set.read("\\empty.ini").unwrap(), Some(Vec::new()));
+        assert_eq!(set.read("\\missing").unwrap(), None);
+
+        let dup = vec![("\\a".to_string(), vec![1]), ("\\A".to_string(), vec![2])];
+        assert!(write_pak(&path, &dup).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn unsupported_compression_is_an_error() {
+        let path = std::env::temp_dir().join(format!("jx_pak_bz_{}.pak", std::process::id()));
+        write_pak(&path, &[("\\a.txt".to_string(), b"BZh9".to_vec())]).unwrap();
+        let mut reader = PakReader::new(&path).unwrap();
+        let entry = reader.entries()[0];
+
+        // Cùng dữ liệu nhưng index ghi BZIP2 / kiểu lạ: không được trả byte nén như đã giải nén
+        let bzip2 = PakEntry {
+            compress_flag: entry.get_stored_size() | 2 << 24,
+            ..entry
+        };
+        let err = reader.read_entry_data(&bzip2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        let unknown = PakEntry {
+            compress_flag: entry.get_stored_size() | 9 << 24,
+            ..entry
+        };
+        assert!(reader.read_entry_data(&unknown).is_err());
+        assert_eq!(reader.read_entry_data(&entry).unwrap(), b"BZh9");
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,450 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+
+use crate::common::map::{CellGrid, Obstacle, RegionData};
+use crate::common::world::{REGION_CELLS_X, REGION_CELLS_Y, WorldPos};
+
+// --- LƯỚI VẬT CẢN ---
+// Tìm đường trên ô toàn bản đồ (`WorldPos::global_cell`), không quan tâm ranh giới region.
+
+/// Nguồn thông tin ô đi được
+pub trait ObstacleMap {
+    fn is_walkable(&self, cell: (i32, i32)) -> bool;
+}
+
+/// Vật cản của các region đã tải. Ô thuộc region chưa tải coi như bị chặn.
+#[derive(Debug, Clone, Default)]
+pub struct MapGrid {
+    regions: HashMap<(i32, i32), CellGrid>,
+}
+
+impl MapGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, region: (i32, i32), obstacles: CellGrid) {
+        self.regions.insert(region, obstacles);
+    }
+
+    pub fn insert_region(&mut self, region: (i32, i32), data: &RegionData) {
+        self.insert(region, data.obstacles.clone());
+    }
+
+    pub fn remove(&mut self, region: (i32, i32)) {
+        self.regions.remove(&region);
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+impl ObstacleMap for MapGrid {
+    fn is_walkable(&self, cell: (i32, i32)) -> bool {
+        let region = (
+            cell.0.div_euclid(REGION_CELLS_X),
+            cell.1.div_euclid(REGION_CELLS_Y),
+        );
+        self.regions.get(&region).is_some_and(|grid| {
+            let local = (
+                cell.0.rem_euclid(REGION_CELLS_X),
+                cell.1.rem_euclid(REGION_CELLS_Y),
+            );
+            !Obstacle(grid.get(local.0, local.1)).is_blocked()
+        })
+    }
+}
+
+// --- A* ---
+
+// Chi phí đi thẳng / đi chéo một ô (xấp xỉ 1 và √2)
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+// Thứ tự duyệt hàng xóm cố định để kết quả tất định
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+    (1, 0),
+    (-1, 1),
+    (-1, -1),
+    (1, -1),
+    (1, 1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    StartBlocked,
+    GoalBlocked,
+    // Đã duyệt hết vùng liên thông mà không tới đích
+    NoPath,
+    // Vượt quá `PathOptions::max_nodes`
+    LimitReached,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::StartBlocked => write!(f, "Điểm xuất phát nằm trong vật cản"),
+            PathError::GoalBlocked => write!(f, "Điểm đích nằm trong vật cản"),
+            PathError::NoPath => write!(f, "Không có đường tới đích"),
+            PathError::LimitReached => write!(f, "Vượt quá giới hạn tìm đường"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathOptions {
+    // Số ô tối đa được mở rộng trước khi bỏ cuộc
+    pub max_nodes: usize,
+    // Nắn thẳng đường đi bằng kiểm tra tầm nhìn giữa các ô
+    pub smooth: bool,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        PathOptions {
+            max_nodes: 20_000,
+            smooth: true,
+        }
+    }
+}
+
+fn heuristic(a: (i32, i32), b: (i32, i32)) -> u32 {
+    let dx = a.0.abs_diff(b.0);
+    let dy = a.1.abs_diff(b.1);
+    STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+}
+
+struct Node {
+    g: u32,
+    parent: Option<(i32, i32)>,
+    closed: bool,
+}
+
+/// A* 8 hướng trên ô, không cắt góc (đi chéo cần cả hai ô kề đi được).
+/// Trả về danh sách ô từ `start` tới `goal`, tính cả hai đầu.
+pub fn find_cell_path(
+    map: &dyn ObstacleMap,
+    start: (i32, i32),
+    goal: (i32, i32),
+    max_nodes: usize,
+) -> Result<Vec<(i32, i32)>, PathError> {
+    if !map.is_walkable(start) {
+        return Err(PathError::StartBlocked);
+    }
+    if !map.is_walkable(goal) {
+        return Err(PathError::GoalBlocked);
+    }
+
+    let mut nodes: HashMap<(i32, i32), Node> = HashMap::new();
+    // (f, h, ô): cùng f thì ưu tiên ô gần đích hơn, cuối cùng so ô để thứ tự luôn cố định
+    let mut open = BinaryHeap::new();
+    nodes.insert(
+        start,
+        Node {
+            g: 0,
+            parent: None,
+            closed: false,
+        },
+    );
+    open.push(Reverse((
+        heuristic(start, goal),
+        heuristic(start, goal),
+        start,
+    )));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, _, cell))) = open.pop() {
+        let node = nodes
+            .get_mut(&cell)
+            .expect("ô trong open phải có trong nodes");
+        if node.closed {
+            continue;
+        }
+        node.closed = true;
+        let g = node.g;
+
+        if cell == goal {
+            let mut path = vec![cell];
+            let mut current = cell;
+            while let Some(parent) = nodes[&current].parent {
+                path.push(parent);
+                current = parent;
+            }
+            path.reverse();
+            return Ok(path);
+        }
+
+        expanded += 1;
+        if expanded > max_nodes {
+            return Err(PathError::LimitReached);
+        }
+
+        for (dx, dy) in NEIGHBOURS {
+            let next = (cell.0 + dx, cell.1 + dy);
+            if !map.is_walkable(next) {
+                continue;
+            }
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal
+                && (!map.is_walkable((cell.0 + dx, cell.1))
+                    || !map.is_walkable((cell.0, cell.1 + dy)))
+            {
+                continue;
+            }
+            let cost = g + if diagonal {
+                DIAGONAL_COST
+            } else {
+                STRAIGHT_COST
+            };
+            match nodes.entry(next) {
+                Entry::Occupied(mut e) => {
+                    let n = e.get_mut();
+                    if n.closed || n.g <= cost {
+                        continue;
+                    }
+                    n.g = cost;
+                    n.parent = Some(cell);
+                }
+                Entry::Vacant(e) => {
+                    e.insert(Node {
+                        g: cost,
+                        parent: Some(cell),
+                        closed: false,
+                    });
+                }
+            }
+            let h = heuristic(next, goal);
+            open.push(Reverse((cost + h, h, next)));
+        }
+    }
+    Err(PathError::NoPath)
+}
+
+// --- NẮN ĐƯỜNG ---
+
+/// Đoạn thẳng nối tâm hai ô chỉ đi qua ô đi được (đi qua đúng góc ô thì cần cả hai ô kề)
+pub fn line_of_sight(map: &dyn ObstacleMap, a: (i32, i32), b: (i32, i32)) -> bool {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (nx, ny) = (dx.abs(), dy.abs());
+    let (sx, sy) = (dx.signum(), dy.signum());
+    let (mut x, mut y) = a;
+    let (mut ix, mut iy) = (0, 0);
+    if !map.is_walkable(a) {
+        return false;
+    }
+    while ix < nx || iy < ny {
+        let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+        if decision == 0 {
+            if !map.is_walkable((x + sx, y)) || !map.is_walkable((x, y + sy)) {
+                return false;
+            }
+            x += sx;
+            y += sy;
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            x += sx;
+            ix += 1;
+        } else {
+            y += sy;
+            iy += 1;
+        }
+        if !map.is_walkable((x, y)) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Bỏ các ô trung gian khi từ ô neo nhìn thẳng được tới ô xa hơn
+pub fn smooth_path(map: &dyn ObstacleMap, cells: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let Some(&first) = cells.first() else {
+        return Vec::new();
+    };
+    let mut result = vec![first];
+    let mut anchor = 0;
+    while anchor + 1 < cells.len() {
+        let mut next = anchor + 1;
+        while next + 1 < cells.len() && line_of_sight(map, cells[anchor], cells[next + 1]) {
+            next += 1;
+        }
+        result.push(cells[next]);
+        anchor = next;
+    }
+    result
+}
+
+/// Tìm đường từ `from` tới `to`, trả về các điểm đến (không gồm điểm xuất phát)
+/// cho `Npc::move_along`. Điểm cuối là đúng `to`, các điểm giữa là tâm ô.
+pub fn find_path(
+    map: &dyn ObstacleMap,
+    from: WorldPos,
+    to: WorldPos,
+    options: &PathOptions,
+) -> Result<Vec<WorldPos>, PathError> {
+    let (start, goal) = (from.global_cell(), to.global_cell());
+    if options.smooth && line_of_sight(map, start, goal) {
+        return Ok(vec![to]);
+    }
+
+    let mut cells = find_cell_path(map, start, goal, options.max_nodes)?;
+    if options.smooth {
+        cells = smooth_path(map, &cells);
+    }
+    let mut path: Vec<WorldPos> = cells
+        .into_iter()
+        .skip(1)
+        .map(|cell| WorldPos::from_global_cell(to.world, cell))
+        .collect();
+    match path.last_mut() {
+        Some(last) => *last = to,
+        None => path.push(to),
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Lưới vẽ bằng ký tự: '#' là vật cản, ngoài lưới là vật cản
+    struct Ascii(Vec<&'static [u8]>);
+
+    impl ObstacleMap for Ascii {
+        fn is_walkable(&self, (x, y): (i32, i32)) -> bool {
+            x >= 0
+                && y >= 0
+                && self
+                    .0
+                    .get(y as usize)
+                    .and_then(|row| row.get(x as usize))
+                    .is_some_and(|&c| c != b'#')
+        }
+    }
+
+    fn assert_walkable(map: &dyn ObstacleMap, cells: &[(i32, i32)]) {
+        for pair in cells.windows(2) {
+            assert!(line_of_sight(map, pair[0], pair[1]), "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn finds_shortest_path_around_wall() {
+        let map = Ascii(vec![
+            b"........",
+            b"..####..",
+            b".....#..",
+            b"..S..#G.",
+            b".....#..",
+            b"........",
+        ]);
+        let cells = find_cell_path(&map, (2, 3), (6, 3), 1000).unwrap();
+        assert_eq!(cells.first(), Some(&(2, 3)));
+        assert_eq!(cells.last(), Some(&(6, 3)));
+        // Vòng xuống dưới tường: 2 bước chéo + 4 bước thẳng
+        let cost: u32 = cells
+            .windows(2)
+            .map(|p| {
+                if p[0].0 != p[1].0 && p[0].1 != p[1].1 {
+                    DIAGONAL_COST
+                } else {
+                    STRAIGHT_COST
+                }
+            })
+            .sum();
+        assert_eq!(cost, 2 * DIAGONAL_COST + 4 * STRAIGHT_COST);
+        assert_walkable(&map, &cells);
+
+        // Tất định: chạy lại cho cùng kết quả
+        assert_eq!(find_cell_path(&map, (2, 3), (6, 3), 1000).unwrap(), cells);
+
+        let smooth = smooth_path(&map, &cells);
+        assert!(smooth.len() < cells.len());
+        assert_eq!(smooth, vec![(2, 3), (5, 5), (6, 5), (6, 3)]);
+        assert_walkable(&map, &smooth);
+    }
+
+    #[test]
+    fn does_not_cut_corners() {
+        let map = Ascii(vec![b"..#", b".#.", b"..."]);
+        assert!(!line_of_sight(&map, (0, 0), (2, 2)));
+        assert!(!line_of_sight(&map, (0, 1), (2, 1)));
+        let cells = find_cell_path(&map, (2, 1), (0, 1), 100).unwrap();
+        assert_eq!(cells, vec![(2, 1), (2, 2), (1, 2), (0, 2), (0, 1)]);
+        assert_walkable(&map, &cells);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let map = Ascii(vec![b"..#..", b"..#..", b"..#.#"]);
+        assert_eq!(
+            find_cell_path(&map, (0, 0), (4, 0), 100),
+            Err(PathError::NoPath)
+        );
+        assert_eq!(
+            find_cell_path(&map, (2, 0), (0, 0), 100),
+            Err(PathError::StartBlocked)
+        );
+        assert_eq!(
+            find_cell_path(&map, (0, 0), (4, 2), 100),
+            Err(PathError::GoalBlocked)
+        );
+
+        let open = Ascii(vec![b"..........", b"....####..", b".........."]);
+        assert_eq!(
+            find_cell_path(&open, (5, 0), (5, 2), 2),
+            Err(PathError::LimitReached)
+        );
+        assert!(find_cell_path(&open, (5, 0), (5, 2), 100).is_ok());
+    }
+
+    #[test]
+    fn crosses_region_boundaries() {
+        // Hai region cạnh nhau; region trái có tường dọc ở cột 10 chừa lỗ ở hàng 30
+        let mut left = CellGrid::default();
+        for y in 0..REGION_CELLS_Y {
+            if y != 30 {
+                left.cells[(10 * REGION_CELLS_Y + y) as usize] = 1;
+            }
+        }
+        let mut map = MapGrid::new();
+        map.insert((0, 0), left);
+        map.insert((1, 0), CellGrid::default());
+
+        let from = WorldPos::from_cell(3, (0, 0), (2, 2));
+        let to = WorldPos::new(3, 512 + 5 * 32 + 7, 3 * 32 + 9);
+        let path = find_path(&map, from, to, &PathOptions::default()).unwrap();
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.len() >= 2);
+
+        let mut cells = vec![from.global_cell()];
+        cells.extend(path.iter().map(|p| p.global_cell()));
+        assert_walkable(&map, &cells);
+
+        // Đích ở region chưa tải
+        let far = WorldPos::from_cell(3, (2, 0), (1, 1));
+        assert_eq!(
+            find_path(&map, from, far, &PathOptions::default()),
+            Err(PathError::GoalBlocked)
+        );
+
+        // Nhìn thẳng được: đi một mạch
+        let near = WorldPos::from_cell(3, (0, 0), (8, 20));
+        assert_eq!(
+            find_path(&map, from, near, &PathOptions::default()),
+            Ok(vec![near])
+        );
+    }
+}
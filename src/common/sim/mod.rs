@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+use crate::common::npc::{ActionId, ActionSet, AnimEvent, Npc, NpcAction, NpcCommand, NpcKind};
+use crate::common::world::{GAME_FPS, WorldPos};
+
+// --- ĐỒNG HỒ LOGIC ---
+// Logic chạy theo bước cố định 1/18 giây như game gốc, không phụ thuộc tốc độ khung hình.
+// Renderer chạy bao nhiêu FPS cũng được: nó nội suy giữa tick trước và tick hiện tại.
+
+/// Thời lượng một tick logic (giây)
+pub const TICK_SECONDS: f32 = 1.0 / GAME_FPS as f32;
+
+// Số tick tối đa chạy bù trong một lần `advance` (máy bị treo, kéo cửa sổ...),
+// phần thời gian còn lại bị bỏ để không bị chậm dần mãi
+pub const MAX_CATCH_UP_TICKS: u32 = 5;
+
+// Số sự kiện animation giữ lại tối đa khi không ai gọi `drain_events` (server headless),
+// vượt quá thì bỏ sự kiện cũ nhất
+pub const MAX_PENDING_EVENTS: usize = 4096;
+
+/// Đổi thời gian thực thành số tick cố định
+#[derive(Debug, Clone, Default)]
+pub struct TickClock {
+    // Tổng số tick đã chạy
+    pub tick: u64,
+    accumulator: f32,
+}
+
+impl TickClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cộng `dt` giây, trả về số tick cần chạy (tối đa `MAX_CATCH_UP_TICKS`)
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.max(0.0);
+        let mut steps = 0;
+        while self.accumulator >= TICK_SECONDS {
+            self.accumulator -= TICK_SECONDS;
+            steps += 1;
+            if steps == MAX_CATCH_UP_TICKS {
+                self.accumulator = self.accumulator.min(TICK_SECONDS);
+                break;
+            }
+        }
+        self.tick += steps as u64;
+        steps
+    }
+
+    /// Vị trí giữa tick trước và tick kế tiếp (0.0 - 1.0), dùng để nội suy khi vẽ
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK_SECONDS).clamp(0.0, 1.0)
+    }
+}
+
+// --- MÔ PHỎNG ---
+
+/// Tài nguyên hành động dùng chung giữa các NPC cùng loại (thường là `NpcResEntry`)
+pub type SharedActionSet = Arc<dyn ActionSet + Send + Sync>;
+
+struct SimNpc {
+    npc: Npc,
+    set: SharedActionSet,
+    // Vị trí ở tick trước, để nội suy
+    prev_pos: WorldPos,
+}
+
+/// Trạng thái NPC để vẽ ở một khung hình, vị trí đã nội suy (điểm logic)
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpcView {
+    pub id: u64,
+    pub x: f32,
+    pub y: f32,
+    pub dir: u8,
+    pub action: NpcAction,
+    pub state: ActionId,
+    pub frame: u32,
+    pub mounted: bool,
+}
+
+/// Lõi mô phỏng: sở hữu mọi NPC và chạy chúng theo tick cố định.
+/// Không phụ thuộc client nên server headless dùng lại được.
+#[derive(Default)]
+pub struct Simulation {
+    pub clock: TickClock,
+    npcs: BTreeMap<u64, SimNpc>,
+    next_id: u64,
+    // Sự kiện animation từ các tick chưa được lấy ra
+    events: VecDeque<(u64, AnimEvent)>,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Simulation {
+            next_id: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Tạo NPC tại `pos`, trả về id
+    pub fn spawn(&mut self, name: &str, kind: NpcKind, pos: WorldPos, set: SharedActionSet) -> u64 {
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+        let mut npc = Npc::new(id, name, kind);
+        npc.pos = pos;
+        // ActionId đứng theo bảng vũ khí của tài nguyên
+        if let Some(state) = set.resolve(&npc.weapon, npc.mounted, npc.action) {
+            npc.state = state;
+        }
+        self.npcs.insert(
+            id,
+            SimNpc {
+                npc,
+                set,
+                prev_pos: pos,
+            },
+        );
+        id
+    }
+
+    pub fn despawn(&mut self, id: u64) -> Option<Npc> {
+        self.npcs.remove(&id).map(|s| s.npc)
+    }
+
+    pub fn npc(&self, id: u64) -> Option<&Npc> {
+        self.npcs.get(&id).map(|s| &s.npc)
+    }
+
+    pub fn npcs(&self) -> impl Iterator<Item = &Npc> {
+        self.npcs.values().map(|s| &s.npc)
+    }
+
+    pub fn len(&self) -> usize {
+        self.npcs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.npcs.is_empty()
+    }
+
+    /// Gửi lệnh cho NPC, `false` nếu không có NPC hoặc lệnh bị bỏ qua
+    pub fn command(&mut self, id: u64, cmd: NpcCommand) -> bool {
+        self.send(id, |npc, set| npc.command(cmd, set))
+    }
+
+    /// Cho NPC diễn đúng `action` (xem `Npc::perform`)
+    pub fn perform(&mut self, id: u64, action: NpcAction) -> bool {
+        self.send(id, |npc, set| npc.perform(action, set))
+    }
+
+    pub fn move_along(&mut self, id: u64, path: Vec<WorldPos>, run: bool) -> bool {
+        self.send(id, |npc, set| npc.move_along(path, run, set))
+    }
+
+    /// Chạy `f` trên NPC `id`, báo `Started` nếu NPC vừa đổi action
+    /// hoặc diễn lại action chạy một lần (bị thương khi đang bị thương)
+    fn send(&mut self, id: u64, f: impl FnOnce(&mut Npc, &dyn ActionSet) -> bool) -> bool {
+        let Some(s) = self.npcs.get_mut(&id) else {
+            return false;
+        };
+        let before = (s.npc.action, s.npc.state);
+        if !f(&mut s.npc, s.set.as_ref()) {
+            return false;
+        }
+        if before != (s.npc.action, s.npc.state) || s.npc.is_busy() {
+            let event = AnimEvent::Started(s.npc.action, s.npc.state);
+            self.push_event(id, event);
+        }
+        true
+    }
+
+    fn push_event(&mut self, id: u64, event: AnimEvent) {
+        if self.events.len() >= MAX_PENDING_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back((id, event));
+    }
+
+    pub fn set_weapon(&mut self, id: u64, weapon: &str) {
+        if let Some(s) = self.npcs.get_mut(&id) {
+            s.npc.set_weapon(weapon, s.set.as_ref());
+        }
+    }
+
+    /// Quay NPC về hướng 64 bước `dir`
+    pub fn set_dir(&mut self, id: u64, dir: u8) {
+        if let Some(s) = self.npcs.get_mut(&id) {
+            s.npc.dir = dir;
+        }
+    }
+
+    /// Đặt thẳng vị trí (dịch chuyển), không nội suy từ chỗ cũ
+    pub fn teleport(&mut self, id: u64, pos: WorldPos) {
+        if let Some(s) = self.npcs.get_mut(&id) {
+            s.npc.pos = pos;
+            s.prev_pos = pos;
+        }
+    }
+
+    /// Chạy đúng một tick logic cho mọi NPC (theo thứ tự id để kết quả tất định)
+    pub fn step(&mut self) {
+        let mut events = Vec::new();
+        for (&id, s) in self.npcs.iter_mut() {
+            s.prev_pos = s.npc.pos;
+            let before = (s.npc.action, s.npc.state);
+            if let Some(event) = s.npc.tick(s.set.as_ref()) {
+                events.push((id, event));
+            }
+            // Tới nơi hoặc đánh xong thì quay về đứng
+            if before != (s.npc.action, s.npc.state) {
+                events.push((id, AnimEvent::Started(s.npc.action, s.npc.state)));
+            }
+        }
+        for (id, event) in events {
+            self.push_event(id, event);
+        }
+    }
+
+    /// Cộng `dt` giây thời gian thực và chạy số tick tương ứng, trả về số tick đã chạy
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        let steps = self.clock.advance(dt);
+        for _ in 0..steps {
+            self.step();
+        }
+        steps
+    }
+
+    /// Lấy ra các sự kiện animation tích luỹ từ lần gọi trước (tối đa `MAX_PENDING_EVENTS`)
+    pub fn drain_events(&mut self) -> Vec<(u64, AnimEvent)> {
+        self.events.drain(..).collect()
+    }
+
+    /// Trạng thái để vẽ NPC `id`, vị trí nội suy theo `clock.alpha()`
+    pub fn view(&self, id: u64) -> Option<NpcView> {
+        self.npcs.get(&id).map(|s| self.view_of(s))
+    }
+
+    /// Trạng thái để vẽ mọi NPC
+    pub fn views(&self) -> Vec<NpcView> {
+        self.npcs.values().map(|s| self.view_of(s)).collect()
+    }
+
+    fn view_of(&self, s: &SimNpc) -> NpcView {
+        let t = self.clock.alpha();
+        let lerp = |a: i32, b: i32| a as f32 + (b - a) as f32 * t;
+        NpcView {
+            id: s.npc.id,
+            x: lerp(s.prev_pos.x, s.npc.pos.x),
+            y: lerp(s.prev_pos.y, s.npc.pos.y),
+            dir: s.npc.dir,
+            action: s.npc.action,
+            state: s.npc.state,
+            frame: s.npc.frame,
+            mounted: s.npc.mounted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::npc::ActionTiming;
+
+    // Mọi action dùng bảng tay không, 2 frame mỗi action, 1 tick mỗi frame
+    struct TwoFrames;
+
+    impl ActionSet for TwoFrames {
+        fn resolve(&self, _weapon: &str, mounted: bool, action: NpcAction) -> Option<ActionId> {
+            action.default_action(mounted)
+        }
+
+        fn timing(&self, _action: NpcAction, _state: ActionId) -> Option<ActionTiming> {
+            Some(ActionTiming {
+                frames: 2,
+                interval: 1,
+            })
+        }
+    }
+
+    #[test]
+    fn clock_runs_fixed_ticks() {
+        let mut clock = TickClock::new();
+        assert_eq!(clock.advance(TICK_SECONDS * 0.5), 0);
+        assert!((clock.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(clock.advance(TICK_SECONDS * 0.6), 1);
+        assert!((clock.alpha() - 0.1).abs() < 1e-3);
+
+        // 60 FPS trong 1 giây = 18 tick
+        let mut clock = TickClock::new();
+        let steps: u32 = (0..60).map(|_| clock.advance(1.0 / 60.0)).sum();
+        assert!((17..=18).contains(&steps));
+        assert_eq!(clock.tick, steps as u64);
+
+        // Treo 2 giây: chỉ bù tối đa MAX_CATCH_UP_TICKS
+        assert_eq!(clock.advance(2.0), MAX_CATCH_UP_TICKS);
+        assert!(clock.alpha() <= 1.0);
+    }
+
+    #[test]
+    fn simulation_moves_and_interpolates() {
+        let mut sim = Simulation::new();
+        let set: SharedActionSet = Arc::new(TwoFrames);
+        let a = sim.spawn("a", NpcKind::Normal, WorldPos::new(1, 0, 0), set.clone());
+        let b = sim.spawn("b", NpcKind::Normal, WorldPos::new(1, 500, 500), set);
+        assert_ne!(a, b);
+        assert_eq!(sim.npc(a).unwrap().state, ActionId::FreeStand2);
+
+        assert!(sim.move_along(a, vec![WorldPos::new(1, 100, 0)], false));
+        assert_eq!(sim.advance(TICK_SECONDS * 1.5), 1);
+        let speed = sim.npc(a).unwrap().walk_speed as f32;
+        assert_eq!(sim.npc(a).unwrap().pos.x as f32, speed);
+
+        // Dư nửa tick: vẽ giữa vị trí tick trước và tick hiện tại
+        let view = sim.view(a).unwrap();
+        assert!((view.x - speed * 0.5).abs() < 1e-3);
+        assert_eq!(sim.view(b).unwrap().x, 500.0);
+
+        // Animation lặp 2 frame: mỗi 2 tick báo Looped
+        sim.drain_events();
+        for _ in 0..4 {
+            sim.step();
+        }
+        let events = sim.drain_events();
+        assert_eq!(events.iter().filter(|(id, _)| *id == b).count(), 2);
+        assert!(
+            events
+                .iter()
+                .all(|(_, e)| matches!(e, AnimEvent::Looped(..)))
+        );
+        assert!(sim.drain_events().is_empty());
+
+        // Dịch chuyển không nội suy
+        sim.teleport(b, WorldPos::new(1, 0, 900));
+        assert_eq!(sim.view(b).unwrap().y, 900.0);
+        assert!(sim.despawn(b).is_some());
+        assert_eq!(sim.len(), 1);
+        assert!(!sim.command(b, NpcCommand::Run));
+    }
+
+    #[test]
+    fn undrained_events_are_capped() {
+        let mut sim = Simulation::new();
+        let id = sim.spawn(
+            "a",
+            NpcKind::Normal,
+            WorldPos::default(),
+            Arc::new(TwoFrames),
+        );
+        // Mỗi 2 tick một Looped, không ai lấy ra
+        for _ in 0..MAX_PENDING_EVENTS * 3 {
+            sim.step();
+        }
+        assert!(sim.perform(id, NpcAction::Attack2));
+        let events = sim.drain_events();
+        assert_eq!(events.len(), MAX_PENDING_EVENTS);
+        // Giữ sự kiện mới nhất
+        assert_eq!(
+            events.last(),
+            Some(&(
+                id,
+                AnimEvent::Started(NpcAction::Attack2, ActionId::FreeAttack)
+            ))
+        );
+    }
+
+    #[test]
+    fn same_input_gives_same_state() {
+        let run = || {
+            let mut sim = Simulation::new();
+            let set: SharedActionSet = Arc::new(TwoFrames);
+            let id = sim.spawn("a", NpcKind::Normal, WorldPos::new(1, 0, 0), set);
+            sim.move_along(
+                id,
+                vec![WorldPos::new(1, 37, 91), WorldPos::new(1, 0, 200)],
+                true,
+            );
+            // Khung hình không đều vẫn cho cùng số tick
+            for dt in [0.016, 0.033, 0.1, 0.007, 0.05, 0.2, 0.016] {
+                sim.advance(dt);
+            }
+            (
+                sim.clock.tick,
+                sim.npc(id).unwrap().pos,
+                sim.npc(id).unwrap().frame,
+            )
+        };
+        assert_eq!(run(), run());
+    }
+}
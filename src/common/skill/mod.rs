@@ -0,0 +1,520 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::common::npc::{ActionId, ActionSet, Npc, NpcAction};
+use crate::common::pak::PakSet;
+use crate::common::tabfile::{RowError, TabFile, TabRow};
+use crate::common::world::{CELL_WIDTH, DIR_COUNT, WorldPos, dir64_vector};
+
+// --- BẢNG KỸ NĂNG (settings\skills.txt, settings\missles.txt) ---
+// skills.txt: SkillName, SkillId, CastAction (Attack1 / Attack2 / Magic, cột của bảng vũ khí),
+// AttackRadius (tầm, điểm), WaitTime (hồi chiêu, tick), HorseLimited (0 mọi lúc, 1 không dùng
+// khi cưỡi ngựa, 2 chỉ khi cưỡi ngựa), MissleId, MissleNum, MissleForm (0 toả quạt, 1 vòng tròn,
+// 2 nối đuôi). missles.txt: MissleName, MissleId, Speed (điểm / tick), LifeTime (tick), Image.
+// Bảng gốc viết "Missle" nên tên cột giữ nguyên như vậy.
+
+pub const SKILL_TABLE: &str = "skills.txt";
+pub const MISSILE_TABLE: &str = "missles.txt";
+/// Thư mục chứa bảng kỹ năng trong pak
+pub const SKILL_TABLE_DIR: &str = "\\settings\\";
+
+// Khoảng cách hướng (trên 64) giữa hai đạn cạnh nhau khi toả quạt
+const FAN_SPREAD: i32 = 2;
+// Khoảng cách giữa hai đạn nối đuôi
+const LINE_SPACING: f32 = CELL_WIDTH as f32;
+
+/// Giới hạn ngựa của kỹ năng (cột HorseLimited)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MountRule {
+    Any,
+    OnFoot,
+    Mounted,
+}
+
+impl MountRule {
+    fn from_index(index: i32) -> Self {
+        match index {
+            1 => MountRule::OnFoot,
+            2 => MountRule::Mounted,
+            _ => MountRule::Any,
+        }
+    }
+
+    pub fn allows(self, mounted: bool) -> bool {
+        match self {
+            MountRule::Any => true,
+            MountRule::OnFoot => !mounted,
+            MountRule::Mounted => mounted,
+        }
+    }
+}
+
+/// Cách bắn nhiều đạn (cột MissleForm)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MissileForm {
+    Fan,
+    Circle,
+    Line,
+}
+
+impl MissileForm {
+    fn from_index(index: i32) -> Self {
+        match index {
+            1 => MissileForm::Circle,
+            2 => MissileForm::Line,
+            _ => MissileForm::Fan,
+        }
+    }
+}
+
+/// Một dòng missles.txt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissileDef {
+    pub id: u32,
+    pub name: String,
+    pub speed: u32,
+    pub life_time: u32,
+    pub image: String,
+}
+
+// Cột id bắt buộc: số không âm
+fn id_column(row: &TabRow, column: &str) -> Result<u32, String> {
+    let id = row.require_int(column)?;
+    u32::try_from(id).map_err(|_| format!("cột {} = {} âm", column, id))
+}
+
+impl MissileDef {
+    fn from_row(row: &TabRow) -> Result<Self, String> {
+        Ok(MissileDef {
+            id: id_column(row, "MissleId")?,
+            name: row.get("MissleName").to_string(),
+            speed: row.get_int("Speed").unwrap_or(0).max(0) as u32,
+            life_time: row.get_int("LifeTime").unwrap_or(0).max(0) as u32,
+            image: row.get("Image").to_string(),
+        })
+    }
+}
+
+/// Một dòng skills.txt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skill {
+    pub id: u32,
+    pub name: String,
+    // Lệnh tra bảng vũ khí khi thi triển, ra ActionId theo vũ khí / ngựa
+    pub action: NpcAction,
+    pub range: u32,
+    pub cooldown: u32,
+    pub mount: MountRule,
+    pub missile: Option<u32>,
+    pub missile_count: u32,
+    pub missile_form: MissileForm,
+}
+
+/// Một viên đạn sinh ra khi thi triển
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MissileSpawn {
+    pub missile: u32,
+    pub pos: WorldPos,
+    pub dir: u8,
+}
+
+impl Skill {
+    fn from_row(row: &TabRow) -> Result<Self, String> {
+        // Cột trống là Magic (đa số kỹ năng)
+        let action = match row.get("CastAction").trim() {
+            "" => NpcAction::Magic,
+            name => NpcAction::from_name(name).ok_or_else(|| {
+                format!("CastAction \"{}\" không phải lệnh của bảng vũ khí", name)
+            })?,
+        };
+        // Cột trống hoặc id âm (-1) là kỹ năng không bắn đạn
+        let missile = match row.get("MissleId") {
+            "" => None,
+            _ => u32::try_from(row.require_int("MissleId")?).ok(),
+        };
+        Ok(Skill {
+            id: id_column(row, "SkillId")?,
+            name: row.get("SkillName").to_string(),
+            action,
+            range: row.get_int("AttackRadius").unwrap_or(0).max(0) as u32,
+            cooldown: row.get_int("WaitTime").unwrap_or(0).max(0) as u32,
+            mount: MountRule::from_index(row.get_int("HorseLimited").unwrap_or(0)),
+            missile,
+            missile_count: row.get_int("MissleNum").unwrap_or(1).max(1) as u32,
+            missile_form: MissileForm::from_index(row.get_int("MissleForm").unwrap_or(0)),
+        })
+    }
+
+    /// ActionId người thi triển diễn khi cầm `weapon` (vd Magic -> FreeMagic / MeleeWMagic /
+    /// RideMagic, Attack1 với ám khí -> DartThrow). `None` nếu không diễn được.
+    pub fn cast_action(
+        &self,
+        set: &dyn ActionSet,
+        weapon: &str,
+        mounted: bool,
+    ) -> Option<ActionId> {
+        if !self.mount.allows(mounted) {
+            return None;
+        }
+        set.resolve(weapon, mounted, self.action)
+    }
+
+    /// Các viên đạn bắn từ `origin` theo hướng `dir` (64 hướng)
+    pub fn missile_spawns(&self, origin: WorldPos, dir: u8) -> Vec<MissileSpawn> {
+        let Some(missile) = self.missile else {
+            return Vec::new();
+        };
+        let count = self.missile_count.max(1);
+        let turn = |offset: i32| (dir as i32 + offset).rem_euclid(DIR_COUNT as i32) as u8;
+        (0..count)
+            .map(|i| {
+                let (pos, dir) = match self.missile_form {
+                    MissileForm::Fan => {
+                        let offset = (2 * i as i32 - (count as i32 - 1)) * FAN_SPREAD / 2;
+                        (origin, turn(offset))
+                    }
+                    MissileForm::Circle => (origin, turn((i * DIR_COUNT / count) as i32)),
+                    MissileForm::Line => {
+                        let (vx, vy) = dir64_vector(dir);
+                        let step = LINE_SPACING * i as f32;
+                        let mut pos = origin;
+                        pos.x += (vx * step).round() as i32;
+                        pos.y += (vy * step).round() as i32;
+                        (pos, dir)
+                    }
+                };
+                MissileSpawn { missile, pos, dir }
+            })
+            .collect()
+    }
+}
+
+/// Mọi kỹ năng và đạn
+#[derive(Debug, Clone, Default)]
+pub struct SkillDb {
+    pub skills: HashMap<u32, Skill>,
+    pub missiles: HashMap<u32, MissileDef>,
+    // Dòng bị bỏ qua của hai bảng, để công cụ / log báo cho người sửa bảng
+    pub errors: Vec<RowError>,
+}
+
+impl SkillDb {
+    pub fn from_tables(skills: &TabFile, missiles: &TabFile) -> Self {
+        let mut errors = Vec::new();
+        let skills = skills.parse_rows(SKILL_TABLE, &mut errors, Skill::from_row);
+        let missiles = missiles.parse_rows(MISSILE_TABLE, &mut errors, MissileDef::from_row);
+        SkillDb {
+            skills: skills.into_iter().map(|s| (s.id, s)).collect(),
+            missiles: missiles.into_iter().map(|m| (m.id, m)).collect(),
+            errors,
+        }
+    }
+
+    /// Đọc từ thư mục settings đã giải nén
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        Ok(Self::from_tables(
+            &TabFile::load(dir.join(SKILL_TABLE))?,
+            &TabFile::load(dir.join(MISSILE_TABLE))?,
+        ))
+    }
+
+    pub fn load_pak(paks: &mut PakSet) -> io::Result<Self> {
+        let skills = TabFile::load_pak(paks, &format!("{}{}", SKILL_TABLE_DIR, SKILL_TABLE))?;
+        let missiles = TabFile::load_pak(paks, &format!("{}{}", SKILL_TABLE_DIR, MISSILE_TABLE))?;
+        Ok(Self::from_tables(&skills, &missiles))
+    }
+
+    pub fn skill(&self, id: u32) -> Option<&Skill> {
+        self.skills.get(&id)
+    }
+
+    pub fn missile(&self, id: u32) -> Option<&MissileDef> {
+        self.missiles.get(&id)
+    }
+}
+
+// --- THI TRIỂN ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastError {
+    // Còn bao nhiêu tick nữa mới hồi chiêu
+    Cooldown(u32),
+    OutOfRange,
+    MountRule(MountRule),
+    // NPC không nhận lệnh (đã chết, đang bận) hoặc bảng vũ khí không có action
+    Rejected,
+}
+
+impl fmt::Display for CastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CastError::Cooldown(ticks) => write!(f, "Chưa hồi chiêu (còn {} tick)", ticks),
+            CastError::OutOfRange => write!(f, "Mục tiêu ngoài tầm"),
+            CastError::MountRule(MountRule::OnFoot) => write!(f, "Không dùng được khi cưỡi ngựa"),
+            CastError::MountRule(_) => write!(f, "Chỉ dùng được khi cưỡi ngựa"),
+            CastError::Rejected => write!(f, "Không thi triển được lúc này"),
+        }
+    }
+}
+
+impl std::error::Error for CastError {}
+
+/// Thời điểm (tick) mỗi kỹ năng hồi chiêu xong của một nhân vật
+#[derive(Debug, Clone, Default)]
+pub struct SkillCooldowns {
+    ready_at: HashMap<u32, u64>,
+}
+
+impl SkillCooldowns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Số tick còn phải chờ, 0 nếu dùng được
+    pub fn remaining(&self, skill: &Skill, now: u64) -> u32 {
+        let ready = self.ready_at.get(&skill.id).copied().unwrap_or(0);
+        ready.saturating_sub(now) as u32
+    }
+
+    pub fn start(&mut self, skill: &Skill, now: u64) {
+        self.ready_at.insert(skill.id, now + skill.cooldown as u64);
+    }
+
+    /// Thi triển `skill` vào `target` ở tick `now`: kiểm tra hồi chiêu, tầm, ngựa, rồi cho `npc`
+    /// quay về mục tiêu và diễn đúng CastAction của kỹ năng qua `Npc::perform` (ActionId theo
+    /// vũ khí / ngựa qua `set`; kỹ năng Attack1 không bị đổi thành Attack2 như đánh thường).
+    /// Trả về các viên đạn cần sinh.
+    pub fn cast(
+        &mut self,
+        npc: &mut Npc,
+        skill: &Skill,
+        target: WorldPos,
+        now: u64,
+        set: &dyn ActionSet,
+    ) -> Result<Vec<MissileSpawn>, CastError> {
+        let remaining = self.remaining(skill, now);
+        if remaining > 0 {
+            return Err(CastError::Cooldown(remaining));
+        }
+        if skill.range > 0 && npc.pos.distance_to(&target) > skill.range as f32 {
+            return Err(CastError::OutOfRange);
+        }
+        if !skill.mount.allows(npc.mounted) {
+            return Err(CastError::MountRule(skill.mount));
+        }
+
+        let dir = npc.dir;
+        npc.face(target);
+        if !npc.perform(skill.action, set) {
+            npc.dir = dir;
+            return Err(CastError::Rejected);
+        }
+        self.start(skill, now);
+        Ok(skill.missile_spawns(npc.pos, npc.dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::npc::{NpcCommand, NpcKind, Sex};
+    use crate::common::npcres::{NpcResDb, NpcResEntry};
+
+    const SKILLS: &str = "SkillName\tSkillId\tCastAction\tAttackRadius\tWaitTime\tHorseLimited\tMissleId\tMissleNum\tMissleForm\n\
+        Phi Đao\t1\tAttack1\t400\t0\t0\t10\t3\t0\n\
+        Hỏa Cầu\t2\tMagic\t300\t36\t1\t11\t4\t1\n\
+        Mã Thượng Thương\t3\t\t100\t0\t2\t\t\t\n\
+        Sai cột\t4\tBay\t0\t0\t0\t\t\t\n";
+
+    const MISSILES: &str = "MissleName\tMissleId\tSpeed\tLifeTime\tImage\n\
+        Phi đao\t10\t24\t20\t\\spr\\skill\\dart.spr\n\
+        Hỏa cầu\t11\t16\t30\t\\spr\\skill\\fireball.spr\n";
+
+    fn db() -> SkillDb {
+        SkillDb::from_tables(&TabFile::from_text(SKILLS), &TabFile::from_text(MISSILES))
+    }
+
+    fn main_man() -> NpcResEntry {
+        let json = r#"{"schema_version": 2, "characters": {"MainMan": {
+            "type": "SpecialNpc", "root_path": "/spr/npcres/man",
+            "weapon_logic": {
+                "unmounted": {
+                    "Tay không": {"Attack1": "FreeAttack", "Magic": "FreeMagic", "FightStand": "FreeStand3"},
+                    "ám khí": {"Attack1": "DartThrow", "Magic": "FreeMagic", "FightStand": "FreeStand3"},
+                    "đơn thủ kiếm 1": {"Attack1": "MeleeWPuncture", "Attack2": "MeleeWCut", "Magic": "MeleeWMagic", "FightStand": "MeleeWStand"}},
+                "mounted": {
+                    "Tay không": {"Attack1": "RideCut", "Magic": "RideMagic", "FightStand": "RideStand"}}}
+        }}}"#;
+        let db = NpcResDb::from_reader(json.as_bytes()).unwrap();
+        db.get("MainMan").unwrap().clone()
+    }
+
+    #[test]
+    fn parses_skill_and_missile_tables() {
+        let db = db();
+        assert_eq!(db.skills.len(), 3);
+        assert_eq!(db.missiles.len(), 2);
+
+        let fireball = db.skill(2).unwrap();
+        assert_eq!(fireball.action, NpcAction::Magic);
+        assert_eq!((fireball.range, fireball.cooldown), (300, 36));
+        assert_eq!(fireball.mount, MountRule::OnFoot);
+        assert_eq!(fireball.missile_form, MissileForm::Circle);
+        assert_eq!(db.missile(fireball.missile.unwrap()).unwrap().speed, 16);
+
+        let lance = db.skill(3).unwrap();
+        assert_eq!(lance.action, NpcAction::Magic);
+        assert_eq!((lance.missile, lance.missile_count), (None, 1));
+        assert_eq!(lance.mount, MountRule::Mounted);
+        assert!(db.skill(4).is_none());
+        assert_eq!(
+            db.errors,
+            [RowError {
+                table: SKILL_TABLE.to_string(),
+                row: 3,
+                message: "CastAction \"Bay\" không phải lệnh của bảng vũ khí".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn cast_action_follows_weapon_and_mount() {
+        let db = db();
+        let res = main_man();
+        let (dart, fireball, lance) = (
+            db.skill(1).unwrap(),
+            db.skill(2).unwrap(),
+            db.skill(3).unwrap(),
+        );
+
+        assert_eq!(
+            dart.cast_action(&res, "ám khí", false),
+            Some(ActionId::DartThrow)
+        );
+        assert_eq!(
+            dart.cast_action(&res, "Tay không", false),
+            Some(ActionId::FreeAttack)
+        );
+        assert_eq!(
+            dart.cast_action(&res, "Tay không", true),
+            Some(ActionId::RideCut)
+        );
+        assert_eq!(
+            fireball.cast_action(&res, "đơn thủ kiếm 1", false),
+            Some(ActionId::MeleeWMagic)
+        );
+        assert_eq!(fireball.cast_action(&res, "Tay không", true), None);
+        assert_eq!(lance.cast_action(&res, "Tay không", false), None);
+        assert_eq!(
+            lance.cast_action(&res, "Tay không", true),
+            Some(ActionId::RideMagic)
+        );
+    }
+
+    #[test]
+    fn missile_spawns_follow_form() {
+        let db = db();
+        let origin = WorldPos::new(1, 1000, 1000);
+
+        let fan: Vec<u8> = db
+            .skill(1)
+            .unwrap()
+            .missile_spawns(origin, 0)
+            .iter()
+            .map(|s| s.dir)
+            .collect();
+        assert_eq!(fan, vec![62, 0, 2]);
+
+        let circle = db.skill(2).unwrap().missile_spawns(origin, 8);
+        let dirs: Vec<u8> = circle.iter().map(|s| s.dir).collect();
+        assert_eq!(dirs, vec![8, 24, 40, 56]);
+        assert!(circle.iter().all(|s| s.missile == 11 && s.pos == origin));
+
+        let line = Skill {
+            missile_form: MissileForm::Line,
+            ..db.skill(1).unwrap().clone()
+        };
+        let spawns = line.missile_spawns(origin, 0);
+        assert!(spawns.iter().all(|s| s.dir == 0));
+        assert_eq!(spawns[0].pos, origin);
+        assert!((spawns[2].pos.distance_to(&origin) - 2.0 * LINE_SPACING).abs() < 1.5);
+
+        assert!(db.skill(3).unwrap().missile_spawns(origin, 0).is_empty());
+    }
+
+    #[test]
+    fn cast_checks_cooldown_range_and_plays_action() {
+        let db = db();
+        let res = main_man();
+        let fireball = db.skill(2).unwrap();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        npc.pos = WorldPos::new(1, 1000, 1000);
+        npc.set_weapon("đơn thủ kiếm 1", &res);
+        let mut cooldowns = SkillCooldowns::new();
+
+        let far = WorldPos::new(1, 2000, 1000);
+        assert_eq!(
+            cooldowns.cast(&mut npc, fireball, far, 0, &res),
+            Err(CastError::OutOfRange)
+        );
+
+        let target = WorldPos::new(1, 1100, 1000);
+        let spawns = cooldowns.cast(&mut npc, fireball, target, 0, &res).unwrap();
+        assert_eq!(spawns.len(), 4);
+        assert_eq!(npc.action, NpcAction::Magic);
+        assert_eq!(npc.state, ActionId::MeleeWMagic);
+        assert!(npc.fighting);
+        assert_eq!(
+            cooldowns.cast(&mut npc, fireball, target, 10, &res),
+            Err(CastError::Cooldown(26))
+        );
+
+        // đang diễn chiêu: NPC không nhận lệnh, hồi chiêu không bị tính
+        let dart = db.skill(1).unwrap();
+        assert_eq!(
+            cooldowns.cast(&mut npc, dart, target, 10, &res),
+            Err(CastError::Rejected)
+        );
+        assert_eq!(cooldowns.remaining(dart, 10), 0);
+
+        npc.mounted = true;
+        assert_eq!(
+            cooldowns.cast(&mut npc, fireball, target, 36, &res),
+            Err(CastError::MountRule(MountRule::OnFoot))
+        );
+    }
+
+    #[test]
+    fn attack_skill_keeps_its_cast_action() {
+        let db = db();
+        let res = main_man();
+        let mut npc = Npc::new(1, "MainMan", NpcKind::Special(Sex::Man));
+        npc.pos = WorldPos::new(1, 1000, 1000);
+        npc.set_weapon("đơn thủ kiếm 1", &res);
+
+        // Đánh thường một lần: lần đánh thường kế tiếp sẽ là Attack2
+        assert!(npc.command(NpcCommand::Attack, &res));
+        assert_eq!(npc.state, ActionId::MeleeWPuncture);
+        for _ in 0..100 {
+            if !npc.is_busy() {
+                break;
+            }
+            npc.tick(&res);
+        }
+        assert!(!npc.is_busy());
+
+        // Phi Đao ghi CastAction Attack1 nên vẫn diễn Attack1
+        let dart = db.skill(1).unwrap();
+        let target = WorldPos::new(1, 1100, 1000);
+        assert!(
+            SkillCooldowns::new()
+                .cast(&mut npc, dart, target, 0, &res)
+                .is_ok()
+        );
+        assert_eq!(npc.action, NpcAction::Attack1);
+        assert_eq!(npc.state, ActionId::MeleeWPuncture);
+    }
+}
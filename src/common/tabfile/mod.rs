@@ -0,0 +1,345 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::common::pak::PakSet;
+use crate::common::text::{self, Encoding};
+
+// --- BẢNG TAB (tương đương KTabFile) ---
+// File settings dạng bảng: dòng đầu là tên cột, mỗi dòng sau là một bản ghi, các ô cách nhau
+// bằng tab. Ô có dấu phẩy thường được bọc trong dấu nháy ("48,8,200") và được bỏ nháy khi đọc.
+// Như KTabFile, mỗi dòng luôn là một bản ghi: dấu nháy không bao giờ nối dòng, nên một ô
+// hỏng không làm lệch chỉ số các dòng sau (chỉ số dòng là res id trong nhiều bảng).
+
+/// Bảng tab đã đọc vào bộ nhớ. Ô đã trim, dòng trống (kể cả dòng chỉ có tab) bị bỏ qua.
+/// Chỉ số dòng tính từ 0 và không tính dòng header.
+#[derive(Debug, Clone, Default)]
+pub struct TabFile {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    // Chỉ số các dòng có dấu nháy lẻ (ô bị cắt cụt), giữ nguyên văn
+    malformed: Vec<usize>,
+}
+
+impl TabFile {
+    pub fn from_text(text: &str) -> Self {
+        let mut lines = text
+            .lines()
+            .map(|line| (line.matches('"').count() % 2 != 0, split_cells(line)))
+            .filter(|(_, cells)| cells.iter().any(|c| !c.is_empty()));
+
+        let headers = lines.next().map(|(_, cells)| cells).unwrap_or_default();
+        let mut rows = Vec::new();
+        let mut malformed = Vec::new();
+        for (bad, cells) in lines {
+            if bad {
+                malformed.push(rows.len());
+            }
+            rows.push(cells);
+        }
+        TabFile {
+            headers,
+            rows,
+            malformed,
+        }
+    }
+
+    /// Bảng mã tự nhận diện (UTF-8 / GBK, không chắc thì Windows-1258), xem `text::detect`
+    pub fn from_bytes(data: &[u8]) -> Self {
+        Self::from_text(&text::decode_auto(data))
+    }
+
+    /// Bảng mã chỉ định cho nguồn dữ liệu (client Big5, TCVN3...)
+    pub fn from_bytes_as(data: &[u8], encoding: Encoding) -> Self {
+        Self::from_text(&text::decode(data, encoding))
+    }
+
+    /// Đọc từ đĩa
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    /// Đọc từ đĩa với bảng mã chỉ định
+    pub fn load_as<P: AsRef<Path>>(path: P, encoding: Encoding) -> io::Result<Self> {
+        Ok(Self::from_bytes_as(&fs::read(path)?, encoding))
+    }
+
+    /// Đọc từ pak theo đường dẫn game (`\settings\...`), theo bảng mã của `paks`
+    pub fn load_pak(paks: &mut PakSet, path: &str) -> io::Result<Self> {
+        let data = paks.read(path)?.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("Không tìm thấy {}", path))
+        })?;
+        Ok(Self::from_text(&text::decode_with(&data, paks.encoding)))
+    }
+
+    /// Các dòng có dấu nháy không đóng, để công cụ báo lỗi dữ liệu
+    pub fn malformed(&self) -> &[usize] {
+        &self.malformed
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Số dòng dữ liệu (không tính header)
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Chỉ số cột theo tên trong header (cột đầu tiên nếu trùng tên)
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|h| h == name)
+    }
+
+    pub fn row(&self, index: usize) -> Option<TabRow<'_>> {
+        self.rows
+            .get(index)
+            .map(|cells| TabRow { file: self, cells })
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = TabRow<'_>> {
+        self.rows.iter().map(|cells| TabRow { file: self, cells })
+    }
+
+    /// Đọc mọi dòng bằng `parse`. Dòng `parse` báo lỗi bị bỏ qua và ghi vào `errors`, cùng với
+    /// các dòng có dấu nháy không đóng, để bảng hỏng không âm thầm mất dòng.
+    /// `table` là tên bảng ghi trong lỗi.
+    pub fn parse_rows<'a, T>(
+        &'a self,
+        table: &str,
+        errors: &mut Vec<RowError>,
+        mut parse: impl FnMut(&TabRow<'a>) -> Result<T, String>,
+    ) -> Vec<T> {
+        let mut parsed = Vec::with_capacity(self.rows.len());
+        for (row, cells) in self.rows.iter().enumerate() {
+            let error = |message: String| RowError {
+                table: table.to_string(),
+                row,
+                message,
+            };
+            if self.malformed.binary_search(&row).is_ok() {
+                errors.push(error("dấu nháy không đóng".to_string()));
+            }
+            match parse(&TabRow { file: self, cells }) {
+                Ok(value) => parsed.push(value),
+                Err(message) => errors.push(error(message)),
+            }
+        }
+        parsed
+    }
+
+    /// Dòng đầu tiên có cột đầu tiên bằng `key` (bảng tra theo tên: nhân vật, vũ khí...)
+    pub fn find_row(&self, key: &str) -> Option<TabRow<'_>> {
+        self.rows().find(|row| row.cell(0) == key)
+    }
+
+    /// Ô ở dòng `row`, cột `col`; chuỗi rỗng nếu không có
+    pub fn cell(&self, row: usize, col: usize) -> &str {
+        self.row(row).map(|r| r.cell(col)).unwrap_or("")
+    }
+
+    pub fn get_str(&self, row: usize, column: &str) -> &str {
+        self.row(row).map(|r| r.get(column)).unwrap_or("")
+    }
+
+    pub fn get_int(&self, row: usize, column: &str) -> Option<i32> {
+        self.row(row)?.get_int(column)
+    }
+
+    pub fn get_float(&self, row: usize, column: &str) -> Option<f32> {
+        self.row(row)?.get_float(column)
+    }
+
+    pub fn get_int_list(&self, row: usize, column: &str) -> Vec<i32> {
+        self.row(row)
+            .map(|r| r.get_int_list(column))
+            .unwrap_or_default()
+    }
+}
+
+/// Một dòng dữ liệu, tra ô theo tên cột hoặc vị trí
+#[derive(Debug, Clone, Copy)]
+pub struct TabRow<'a> {
+    file: &'a TabFile,
+    cells: &'a [String],
+}
+
+impl<'a> TabRow<'a> {
+    pub fn cells(&self) -> &'a [String] {
+        self.cells
+    }
+
+    /// Ô thứ `col`; chuỗi rỗng nếu dòng ngắn hơn
+    pub fn cell(&self, col: usize) -> &'a str {
+        self.cells.get(col).map(|c| c.as_str()).unwrap_or("")
+    }
+
+    /// Ô theo tên cột; chuỗi rỗng nếu không có cột
+    pub fn get(&self, column: &str) -> &'a str {
+        self.file.column(column).map(|i| self.cell(i)).unwrap_or("")
+    }
+
+    pub fn get_int(&self, column: &str) -> Option<i32> {
+        self.get(column).parse().ok()
+    }
+
+    /// Như `get_int` cho cột bắt buộc: lỗi ghi tên cột và ô để báo cho người sửa bảng
+    pub fn require_int(&self, column: &str) -> Result<i32, String> {
+        let cell = self.get(column);
+        cell.parse()
+            .map_err(|_| format!("cột {} \"{}\" không phải số", column, cell))
+    }
+
+    pub fn get_int_at(&self, col: usize) -> Option<i32> {
+        self.cell(col).parse().ok()
+    }
+
+    pub fn get_float(&self, column: &str) -> Option<f32> {
+        self.get(column).parse().ok()
+    }
+
+    /// Ô dạng "a,b,c" -> [a, b, c], bỏ phần tử không phải số
+    pub fn get_int_list(&self, column: &str) -> Vec<i32> {
+        parse_int_list(self.get(column))
+    }
+}
+
+/// Một dòng bị bỏ qua khi đọc bảng, xem `TabFile::parse_rows`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    pub table: String,
+    // chỉ số dòng như `TabFile::row`
+    pub row: usize,
+    pub message: String,
+}
+
+// Số dòng tính như trong file (header là dòng 1)
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: dòng {}: {}", self.table, self.row + 2, self.message)
+    }
+}
+
+// Tách một dòng theo tab, trim và bỏ cặp nháy bọc ngoài ô
+fn split_cells(line: &str) -> Vec<String> {
+    line.split('\t')
+        .map(|cell| {
+            let cell = cell.trim();
+            cell.strip_prefix('"')
+                .and_then(|c| c.strip_suffix('"'))
+                .unwrap_or(cell)
+                .to_string()
+        })
+        .collect()
+}
+
+/// Danh sách số "a,b,c" (ô info, giá trị ini), bỏ phần tử không phải số
+pub fn parse_int_list(value: &str) -> Vec<i32> {
+    value
+        .split(',')
+        .filter_map(|s| s.trim().parse::<i32>().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::GBK;
+
+    #[test]
+    fn reads_headers_rows_and_quoted_cells() {
+        let text = "Name\tLevel\tInfo\r\n\
+                    Bandit\t12\t\"48,8,200\"\r\n\
+                    \t\t\r\n\
+                    \r\n\
+                    Guard\t x \t\r\n\
+                    Short\r\n";
+        let table = TabFile::from_text(text);
+        assert_eq!(table.headers(), ["Name", "Level", "Info"]);
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.column("Info"), Some(2));
+        assert_eq!(table.column("Missing"), None);
+
+        assert_eq!(table.get_str(0, "Info"), "48,8,200");
+        assert_eq!(table.get_int_list(0, "Info"), vec![48, 8, 200]);
+        assert_eq!(table.get_int(0, "Level"), Some(12));
+        assert_eq!(table.get_int(1, "Level"), None);
+        assert_eq!(table.get_str(1, "Level"), "x");
+
+        // Dòng ngắn và cột không có: chuỗi rỗng
+        let short = table.find_row("Short").unwrap();
+        assert_eq!(short.get("Info"), "");
+        assert_eq!(short.cell(5), "");
+        assert_eq!(table.cell(9, 0), "");
+        assert!(table.find_row("Nobody").is_none());
+    }
+
+    #[test]
+    fn unbalanced_quote_stays_on_its_line() {
+        let text = "Name\tInfo\nA\t\"48,8\nB\t\"1,2\"\nC\tx\"y\"\n";
+        let table = TabFile::from_text(text);
+        // Nháy không đóng không nuốt các dòng sau: B vẫn là dòng 1
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get_str(0, "Info"), "\"48,8");
+        assert_eq!(table.find_row("B").unwrap().get("Info"), "1,2");
+        assert_eq!(table.get_str(2, "Info"), "x\"y\"");
+        assert_eq!(table.malformed(), [0]);
+
+        let mut errors = Vec::new();
+        let names = table.parse_rows("test.txt", &mut errors, |row| {
+            row.require_int("Info").map(|_| row.cell(0))
+        });
+        assert!(names.is_empty());
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "test.txt: dòng 2: dấu nháy không đóng",
+                "test.txt: dòng 2: cột Info \"\"48,8\" không phải số",
+                "test.txt: dòng 3: cột Info \"1,2\" không phải số",
+                "test.txt: dòng 4: cột Info \"x\"y\"\" không phải số",
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_source_encodings() {
+        let (gbk, _, _) = GBK.encode("名称\t等级\n主角\t3\n");
+        let table = TabFile::from_bytes(&gbk);
+        assert_eq!(table.headers(), ["名称", "等级"]);
+        assert_eq!(table.find_row("主角").unwrap().get_int("等级"), Some(3));
+
+        let mut bom = "\u{feff}Name\tHp\n".as_bytes().to_vec();
+        bom.extend_from_slice("Tay không\t100\n".as_bytes());
+        let table = TabFile::from_bytes(&bom);
+        assert_eq!(table.column("Name"), Some(0));
+        assert_eq!(table.row(0).unwrap().get_int_at(1), Some(100));
+
+        // "Tên\tSố\nGiáp\t1" TCVN3 phải chỉ định bảng mã
+        let table = TabFile::from_bytes_as(b"T\xAAn\tS\xE8\nGi\xB8p\t1\n", Encoding::Tcvn3);
+        assert_eq!(table.find_row("Giáp").unwrap().get_int("Số"), Some(1));
+    }
+
+    #[test]
+    fn load_pak_uses_source_encoding() {
+        use crate::common::pak::{PakReader, write_pak};
+
+        // "Tên\tThể lực\nGiáp\t5" Windows-1258: tự nhận diện không chắc, pak chỉ định bảng mã
+        let data = b"T\xEAn\tTh\xEA\xD2 l\xFD\xF2c\nGi\xE1p\t5\n".to_vec();
+        let path = std::env::temp_dir().join(format!("jx_tab_test_{}.pak", std::process::id()));
+        write_pak(&path, &[("\\settings\\a.txt".to_string(), data)]).unwrap();
+        let mut paks = PakSet::new();
+        paks.add(PakReader::new(&path).unwrap());
+        paks.encoding = Some(Encoding::Windows1258);
+
+        let table = TabFile::load_pak(&mut paks, "\\settings\\a.txt").unwrap();
+        assert_eq!(table.headers(), ["Tên", "Thể lực"]);
+        assert_eq!(table.get_int(0, "Thể lực"), Some(5));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+use std::str::FromStr;
+
+use encoding_rs::{BIG5, GBK, WINDOWS_1252, WINDOWS_1258};
+
+// --- BẢNG MÃ VĂN BẢN ---
+// File của client gốc không phải UTF-8: bản Trung Quốc dùng GBK, bản Đài Loan Big5,
+// bản Việt Nam dùng Windows-1258 hoặc TCVN3 (ABC) cho tên vật phẩm, NPC...
+// Dữ liệu đã chuyển đổi trong repo là UTF-8. Mọi bộ đọc bảng / ini đi qua đây.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Utf8,
+    Gbk,
+    Big5,
+    Windows1258,
+    Tcvn3,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Gbk,
+        Encoding::Big5,
+        Encoding::Windows1258,
+        Encoding::Tcvn3,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Gbk => "gbk",
+            Encoding::Big5 => "big5",
+            Encoding::Windows1258 => "windows-1258",
+            Encoding::Tcvn3 => "tcvn3",
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    /// Nhận cả tên thường gặp: "utf8", "gb2312", "cp1258", "abc"...
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "gbk" | "gb2312" | "cp936" => Ok(Encoding::Gbk),
+            "big5" | "cp950" => Ok(Encoding::Big5),
+            "windows-1258" | "cp1258" | "1258" => Ok(Encoding::Windows1258),
+            "tcvn3" | "tcvn" | "abc" => Ok(Encoding::Tcvn3),
+            other => Err(format!("Bảng mã không hỗ trợ: {}", other)),
+        }
+    }
+}
+
+// --- NHẬN DIỆN ---
+
+/// Đoán bảng mã, `None` khi không chắc. UTF-8 hợp lệ (có hoặc không BOM) là UTF-8. GBK chỉ khi
+/// mọi byte >= 0x80 ghép đủ cặp GB2312 (byte đầu 0xA1-0xF7, byte sau 0xA1-0xFE) và có ít nhất
+/// hai chữ Hán liền nhau: tiếng Việt Windows-1258 cũng hay có cặp byte (ê + dấu hỏi tổ hợp
+/// "\xEA\xD2") nhưng hiếm khi bốn byte liền. Windows-1258, Big5 và TCVN3 không tách được chắc
+/// chắn với nhau nên phải chỉ định rõ (`decode`, `PakSet::encoding`).
+pub fn detect(data: &[u8]) -> Option<Encoding> {
+    if std::str::from_utf8(data).is_ok() {
+        return Some(Encoding::Utf8);
+    }
+
+    let (mut longest, mut run) = (0, 0);
+    let mut bytes = data.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte < 0x80 {
+            run = 0;
+            continue;
+        }
+        match bytes.next() {
+            Some(&trail) if (0xA1..=0xF7).contains(&byte) && (0xA1..=0xFE).contains(&trail) => {
+                run += 1;
+                longest = longest.max(run);
+            }
+            _ => return None,
+        }
+    }
+    (longest >= 2).then_some(Encoding::Gbk)
+}
+
+/// Giải mã theo bảng mã cho trước (byte lỗi thành U+FFFD), bỏ BOM UTF-8 nếu có
+pub fn decode(data: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(data)
+            .trim_start_matches('\u{feff}')
+            .to_string(),
+        Encoding::Gbk => GBK.decode_without_bom_handling(data).0.into_owned(),
+        Encoding::Big5 => BIG5.decode_without_bom_handling(data).0.into_owned(),
+        Encoding::Windows1258 => compose_tones(&WINDOWS_1258.decode_without_bom_handling(data).0),
+        Encoding::Tcvn3 => data.iter().map(|&b| tcvn3_char(b)).collect(),
+    }
+}
+
+/// Giải mã với bảng mã tự nhận diện (`detect`). Không chắc thì đọc như Windows-1258: bảng mã
+/// một byte nên không nuốt byte nào, chữ Việt vẫn đúng còn chữ Hán sai thì thấy ngay.
+pub fn decode_auto(data: &[u8]) -> String {
+    decode(data, detect(data).unwrap_or(Encoding::Windows1258))
+}
+
+/// Giải mã theo bảng mã của nguồn dữ liệu, tự nhận diện nếu nguồn không chỉ định
+pub fn decode_with(data: &[u8], encoding: Option<Encoding>) -> String {
+    match encoding {
+        Some(encoding) => decode(data, encoding),
+        None => decode_auto(data),
+    }
+}
+
+/// Mã hoá để ghi lại file cho client. Ký tự không có trong bảng mã thành '?'.
+pub fn encode(text: &str, encoding: Encoding) -> Vec<u8> {
+    let with = |enc: &'static encoding_rs::Encoding| -> Vec<u8> {
+        let mut out = Vec::with_capacity(text.len());
+        let mut buf = [0u8; 4];
+        let mut push = |c: char, out: &mut Vec<u8>| -> bool {
+            let (bytes, _, had_errors) = enc.encode(c.encode_utf8(&mut buf));
+            if !had_errors {
+                out.extend_from_slice(&bytes);
+            }
+            !had_errors
+        };
+        for c in text.chars() {
+            if push(c, &mut out) {
+                continue;
+            }
+            // Windows-1258 không có đa số chữ dựng sẵn: ghi chữ gốc + dấu thanh tổ hợp
+            let mark = out.len();
+            let split = enc == WINDOWS_1258
+                && decompose_tone(c)
+                    .is_some_and(|(base, tone)| push(base, &mut out) && push(tone, &mut out));
+            if !split {
+                out.truncate(mark);
+                out.push(b'?');
+            }
+        }
+        out
+    };
+    match encoding {
+        Encoding::Utf8 => text.as_bytes().to_vec(),
+        Encoding::Gbk => with(GBK),
+        Encoding::Big5 => with(BIG5),
+        Encoding::Windows1258 => with(WINDOWS_1258),
+        Encoding::Tcvn3 => text.chars().map(tcvn3_byte).collect(),
+    }
+}
+
+// --- DẤU THANH TIẾNG VIỆT ---
+// Windows-1258 ghi chữ có dấu thanh thành chữ gốc + dấu tổ hợp ("ể" = ê + U+0309), còn các bảng
+// của repo (npcres, item) dùng chữ dựng sẵn (NFC). Giải mã thì ghép lại, mã hoá thì tách ra.
+
+// Huyền, sắc, ngã, hỏi, nặng theo thứ tự cột của TONE_TABLE
+const TONES: [char; 5] = ['\u{300}', '\u{301}', '\u{303}', '\u{309}', '\u{323}'];
+
+#[rustfmt::skip]
+const TONE_TABLE: &[(char, [char; 5])] = &[
+    ('a', ['à', 'á', 'ã', 'ả', 'ạ']), ('ă', ['ằ', 'ắ', 'ẵ', 'ẳ', 'ặ']), ('â', ['ầ', 'ấ', 'ẫ', 'ẩ', 'ậ']),
+    ('e', ['è', 'é', 'ẽ', 'ẻ', 'ẹ']), ('ê', ['ề', 'ế', 'ễ', 'ể', 'ệ']), ('i', ['ì', 'í', 'ĩ', 'ỉ', 'ị']),
+    ('o', ['ò', 'ó', 'õ', 'ỏ', 'ọ']), ('ô', ['ồ', 'ố', 'ỗ', 'ổ', 'ộ']), ('ơ', ['ờ', 'ớ', 'ỡ', 'ở', 'ợ']),
+    ('u', ['ù', 'ú', 'ũ', 'ủ', 'ụ']), ('ư', ['ừ', 'ứ', 'ữ', 'ử', 'ự']), ('y', ['ỳ', 'ý', 'ỹ', 'ỷ', 'ỵ']),
+    ('A', ['À', 'Á', 'Ã', 'Ả', 'Ạ']), ('Ă', ['Ằ', 'Ắ', 'Ẵ', 'Ẳ', 'Ặ']), ('Â', ['Ầ', 'Ấ', 'Ẫ', 'Ẩ', 'Ậ']),
+    ('E', ['È', 'É', 'Ẽ', 'Ẻ', 'Ẹ']), ('Ê', ['Ề', 'Ế', 'Ễ', 'Ể', 'Ệ']), ('I', ['Ì', 'Í', 'Ĩ', 'Ỉ', 'Ị']),
+    ('O', ['Ò', 'Ó', 'Õ', 'Ỏ', 'Ọ']), ('Ô', ['Ồ', 'Ố', 'Ỗ', 'Ổ', 'Ộ']), ('Ơ', ['Ờ', 'Ớ', 'Ỡ', 'Ở', 'Ợ']),
+    ('U', ['Ù', 'Ú', 'Ũ', 'Ủ', 'Ụ']), ('Ư', ['Ừ', 'Ứ', 'Ữ', 'Ử', 'Ự']), ('Y', ['Ỳ', 'Ý', 'Ỹ', 'Ỷ', 'Ỵ']),
+];
+
+// Ghép chữ gốc + dấu thanh tổ hợp thành chữ dựng sẵn; dấu không ghép được giữ nguyên
+fn compose_tones(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        let composed = TONES.iter().position(|&t| t == c).and_then(|tone| {
+            let base = out.chars().next_back()?;
+            let (_, toned) = TONE_TABLE.iter().find(|(b, _)| *b == base)?;
+            Some((base, toned[tone]))
+        });
+        match composed {
+            Some((base, toned)) => {
+                out.truncate(out.len() - base.len_utf8());
+                out.push(toned);
+            }
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+// Chữ dựng sẵn có dấu thanh -> (chữ gốc, dấu tổ hợp)
+fn decompose_tone(c: char) -> Option<(char, char)> {
+    TONE_TABLE.iter().find_map(|(base, toned)| {
+        toned
+            .iter()
+            .position(|&t| t == c)
+            .map(|tone| (*base, TONES[tone]))
+    })
+}
+
+// --- TCVN3 (ABC) ---
+// encoding_rs không có TCVN3. Các byte 0xA1-0xFE là chữ tiếng Việt dựng sẵn, byte không có
+// trong bảng đọc như Windows-1252. Chữ hoa có dấu dùng font hoa (.VnTimeH) với cùng mã
+// chữ thường nên không có trong bảng, mã hoá ra '?'.
+
+#[rustfmt::skip]
+const TCVN3_TABLE: &[(u8, char)] = &[
+    (0xA1, 'Ă'), (0xA2, 'Â'), (0xA3, 'Ê'), (0xA4, 'Ô'), (0xA5, 'Ơ'), (0xA6, 'Ư'), (0xA7, 'Đ'),
+    (0xA8, 'ă'), (0xA9, 'â'), (0xAA, 'ê'), (0xAB, 'ô'), (0xAC, 'ơ'), (0xAD, 'ư'), (0xAE, 'đ'),
+    (0xB5, 'à'), (0xB6, 'ả'), (0xB7, 'ã'), (0xB8, 'á'), (0xB9, 'ạ'),
+    (0xBB, 'ằ'), (0xBC, 'ẳ'), (0xBD, 'ẵ'), (0xBE, 'ắ'), (0xC6, 'ặ'),
+    (0xC7, 'ầ'), (0xC8, 'ẩ'), (0xC9, 'ẫ'), (0xCA, 'ấ'), (0xCB, 'ậ'),
+    (0xCC, 'è'), (0xCE, 'ẻ'), (0xCF, 'ẽ'), (0xD0, 'é'), (0xD1, 'ẹ'),
+    (0xD2, 'ề'), (0xD3, 'ể'), (0xD4, 'ễ'), (0xD5, 'ế'), (0xD6, 'ệ'),
+    (0xD7, 'ì'), (0xD8, 'ỉ'), (0xDC, 'ĩ'), (0xDD, 'í'), (0xDE, 'ị'),
+    (0xDF, 'ò'), (0xE1, 'ỏ'), (0xE2, 'õ'), (0xE3, 'ó'), (0xE4, 'ọ'),
+    (0xE5, 'ồ'), (0xE6, 'ổ'), (0xE7, 'ỗ'), (0xE8, 'ố'), (0xE9, 'ộ'),
+    (0xEA, 'ờ'), (0xEB, 'ở'), (0xEC, 'ỡ'), (0xED, 'ớ'), (0xEE, 'ợ'),
+    (0xEF, 'ù'), (0xF1, 'ủ'), (0xF2, 'ũ'), (0xF3, 'ú'), (0xF4, 'ụ'),
+    (0xF5, 'ừ'), (0xF6, 'ử'), (0xF7, 'ữ'), (0xF8, 'ứ'), (0xF9, 'ự'),
+    (0xFA, 'ỳ'), (0xFB, 'ỷ'), (0xFC, 'ỹ'), (0xFD, 'ý'), (0xFE, 'ỵ'),
+];
+
+fn tcvn3_char(byte: u8) -> char {
+    if byte < 0x80 {
+        return byte as char;
+    }
+    TCVN3_TABLE
+        .iter()
+        .find(|(b, _)| *b == byte)
+        .map(|(_, c)| *c)
+        .unwrap_or_else(|| {
+            WINDOWS_1252
+                .decode_without_bom_handling(&[byte])
+                .0
+                .chars()
+                .next()
+                .unwrap_or('\u{fffd}')
+        })
+}
+
+fn tcvn3_byte(c: char) -> u8 {
+    if c.is_ascii() {
+        return c as u8;
+    }
+    TCVN3_TABLE
+        .iter()
+        .find(|(_, ch)| *ch == c)
+        .map(|(b, _)| *b)
+        .unwrap_or(b'?')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_utf8_gbk_and_vietnamese() {
+        assert_eq!(detect("Thân thể 01".as_bytes()), Some(Encoding::Utf8));
+
+        // "人物类型.txt" GBK
+        let gbk = [
+            0xC8, 0xCB, 0xCE, 0xEF, 0xC0, 0xE0, 0xD0, 0xCD, b'.', b't', b'x', b't',
+        ];
+        assert_eq!(detect(&gbk), Some(Encoding::Gbk));
+        assert_eq!(decode_auto(&gbk), "人物类型.txt");
+
+        // "Thân thể 01" Windows-1258: ể = ê + dấu hỏi tổ hợp
+        let cp1258 = b"Th\xE2n th\xEA\xD2 01";
+        assert_eq!(detect(cp1258), None);
+        assert_eq!(decode_auto(cp1258), "Thân thể 01");
+    }
+
+    #[test]
+    fn vietnamese_byte_pairs_are_not_gbk() {
+        // "Thể lực", "Sinh lực tối đa": mỗi âm tiết là một cặp byte như chữ Hán GBK
+        for (bytes, text) in [
+            (&b"Th\xEA\xD2 l\xFD\xF2c"[..], "Thể lực"),
+            (&b"Sinh l\xFD\xF2c t\xF4\xECi \xF0a"[..], "Sinh lực tối đa"),
+        ] {
+            assert_eq!(detect(bytes), None);
+            assert_eq!(decode_auto(bytes), text);
+            assert_eq!(decode_with(bytes, Some(Encoding::Windows1258)), text);
+            // Ghi lại thành chữ gốc + dấu tổ hợp như client
+            assert_eq!(encode(text, Encoding::Windows1258), bytes);
+        }
+        // Một chữ Hán đứng riêng chưa đủ để chắc là GBK
+        assert_eq!(detect(b"\xC8\xCB 1"), None);
+        assert_eq!(decode_with(b"\xC8\xCB 1", Some(Encoding::Gbk)), "人 1");
+    }
+
+    #[test]
+    fn decodes_configured_encodings() {
+        // "人物" Big5
+        assert_eq!(decode(&[0xA4, 0x48, 0xAA, 0xAB], Encoding::Big5), "人物");
+        // TCVN3 hay hiện thành "Th©n thÓ 01" khi mở nhầm bằng Windows-1252
+        let tcvn3 = b"Th\xA9n th\xD3 01";
+        assert_eq!(decode(tcvn3, Encoding::Tcvn3), "Thân thể 01");
+        assert_eq!(encode("Thân thể 01", Encoding::Tcvn3), tcvn3);
+        assert_eq!(decode("\u{feff}abc".as_bytes(), Encoding::Utf8), "abc");
+    }
+
+    #[test]
+    fn encode_round_trips_and_marks_unmappable() {
+        for (text, enc) in [("人物类型", Encoding::Gbk), ("Ngũ Độc", Encoding::Tcvn3)] {
+            assert_eq!(decode(&encode(text, enc), enc), text);
+        }
+        assert_eq!(encode("a人b", Encoding::Windows1258), b"a?b");
+        // Chữ dựng sẵn có trong bảng mã giữ một byte, dấu lạc không có chữ gốc giữ nguyên
+        assert_eq!(encode("Áo", Encoding::Windows1258), b"\xC1o");
+        assert_eq!(decode(b"\xD2x", Encoding::Windows1258), "\u{309}x");
+        assert_eq!(
+            decode(
+                &encode("Ngũ Độc Giáo", Encoding::Windows1258),
+                Encoding::Windows1258
+            ),
+            "Ngũ Độc Giáo"
+        );
+        assert_eq!("CP1258".parse::<Encoding>(), Ok(Encoding::Windows1258));
+        assert!("latin9".parse::<Encoding>().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::f32::consts::TAU;
+
+// --- TOẠ ĐỘ THẾ GIỚI ---
+// Toạ độ logic (điểm) giống client gốc: một region rộng 16 x 32 ô, mỗi ô 32 x 32 điểm.
+// Trục y logic gấp đôi trục y màn hình (region 512 x 1024 điểm hiển thị thành 512 x 512).
+
+pub const CELL_WIDTH: i32 = 32;
+pub const CELL_HEIGHT: i32 = 32;
+pub const REGION_CELLS_X: i32 = 16;
+pub const REGION_CELLS_Y: i32 = 32;
+pub const REGION_WIDTH: i32 = CELL_WIDTH * REGION_CELLS_X;
+pub const REGION_HEIGHT: i32 = CELL_HEIGHT * REGION_CELLS_Y;
+
+/// Số game frame (tick logic) mỗi giây của client / server gốc
+pub const GAME_FPS: u32 = 18;
+
+// Tốc độ mặc định (điểm logic mỗi game frame 1/18 giây)
+pub const WALK_SPEED: u32 = 5;
+pub const RUN_SPEED: u32 = 10;
+
+/// Vị trí trong một bản đồ (world / subworld) tính bằng điểm logic
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WorldPos {
+    pub world: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl WorldPos {
+    pub fn new(world: u32, x: i32, y: i32) -> Self {
+        WorldPos { world, x, y }
+    }
+
+    /// Tâm của ô `cell` trong region `region`
+    pub fn from_cell(world: u32, region: (i32, i32), cell: (i32, i32)) -> Self {
+        WorldPos {
+            world,
+            x: region.0 * REGION_WIDTH + cell.0 * CELL_WIDTH + CELL_WIDTH / 2,
+            y: region.1 * REGION_HEIGHT + cell.1 * CELL_HEIGHT + CELL_HEIGHT / 2,
+        }
+    }
+
+    /// Chỉ số region chứa điểm này
+    pub fn region(&self) -> (i32, i32) {
+        (
+            self.x.div_euclid(REGION_WIDTH),
+            self.y.div_euclid(REGION_HEIGHT),
+        )
+    }
+
+    /// Ô trong region (0..16, 0..32)
+    pub fn cell(&self) -> (i32, i32) {
+        (
+            self.x.rem_euclid(REGION_WIDTH) / CELL_WIDTH,
+            self.y.rem_euclid(REGION_HEIGHT) / CELL_HEIGHT,
+        )
+    }
+
+    /// Ô tính trên toàn bản đồ (không chia theo region), dùng cho tìm đường
+    pub fn global_cell(&self) -> (i32, i32) {
+        (
+            self.x.div_euclid(CELL_WIDTH),
+            self.y.div_euclid(CELL_HEIGHT),
+        )
+    }
+
+    /// Tâm của ô toàn bản đồ `cell`
+    pub fn from_global_cell(world: u32, cell: (i32, i32)) -> Self {
+        WorldPos {
+            world,
+            x: cell.0 * CELL_WIDTH + CELL_WIDTH / 2,
+            y: cell.1 * CELL_HEIGHT + CELL_HEIGHT / 2,
+        }
+    }
+
+    /// Độ lệch trong ô (0..32)
+    pub fn offset_in_cell(&self) -> (i32, i32) {
+        (
+            self.x.rem_euclid(CELL_WIDTH),
+            self.y.rem_euclid(CELL_HEIGHT),
+        )
+    }
+
+    pub fn distance_to(&self, other: &WorldPos) -> f32 {
+        let dx = (other.x - self.x) as f32;
+        let dy = (other.y - self.y) as f32;
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+// --- HƯỚNG ---
+// Client dùng 64 hướng: 0 = xuống (nam), tăng theo chiều kim đồng hồ trên màn hình
+// (16 = trái, 32 = lên, 48 = phải). Spr có 8 hoặc 16 hướng cùng gốc và chiều.
+
+pub const DIR_COUNT: u32 = 64;
+
+/// Hướng 64 bước của vector di chuyển, `None` nếu vector bằng 0
+pub fn dir64_from_vector(dx: f32, dy: f32) -> Option<u8> {
+    if dx == 0.0 && dy == 0.0 {
+        return None;
+    }
+    let angle = (-dx).atan2(dy).rem_euclid(TAU);
+    let dir = (angle / TAU * DIR_COUNT as f32).round() as u32 % DIR_COUNT;
+    Some(dir as u8)
+}
+
+/// Vector đơn vị của hướng 64 bước
+pub fn dir64_vector(dir: u8) -> (f32, f32) {
+    let angle = (dir as u32 % DIR_COUNT) as f32 / DIR_COUNT as f32 * TAU;
+    (-angle.sin(), angle.cos())
+}
+
+/// Hướng spr (0..sprite_dirs) gần nhất với hướng 64 bước
+pub fn sprite_dir(dir: u8, sprite_dirs: u32) -> u32 {
+    let n = sprite_dirs.clamp(1, DIR_COUNT);
+    let dir = dir as u32 % DIR_COUNT;
+    (dir * n + DIR_COUNT / 2) / DIR_COUNT % n
+}
+
+/// Hướng 64 bước ứng với hướng spr `dir` (ngược lại của `sprite_dir`)
+pub fn dir64_from_sprite(dir: u32, sprite_dirs: u32) -> u8 {
+    let n = sprite_dirs.clamp(1, DIR_COUNT);
+    ((dir % n) * DIR_COUNT / n) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_and_cell() {
+        let pos = WorldPos::from_cell(11, (3, 2), (5, 7));
+        assert_eq!(pos.region(), (3, 2));
+        assert_eq!(pos.cell(), (5, 7));
+        assert_eq!(pos.offset_in_cell(), (16, 16));
+
+        assert_eq!(pos.global_cell(), (3 * 16 + 5, 2 * 32 + 7));
+        assert_eq!(WorldPos::from_global_cell(11, pos.global_cell()), pos);
+
+        let neg = WorldPos::new(11, -1, -1);
+        assert_eq!(neg.region(), (-1, -1));
+        assert_eq!(neg.cell(), (15, 31));
+    }
+
+    #[test]
+    fn vector_to_direction() {
+        assert_eq!(dir64_from_vector(0.0, 1.0), Some(0));
+        assert_eq!(dir64_from_vector(-1.0, 0.0), Some(16));
+        assert_eq!(dir64_from_vector(0.0, -1.0), Some(32));
+        assert_eq!(dir64_from_vector(1.0, 0.0), Some(48));
+        assert_eq!(dir64_from_vector(-1.0, 1.0), Some(8));
+        assert_eq!(dir64_from_vector(0.0, 0.0), None);
+
+        for dir in 0..64u8 {
+            let (dx, dy) = dir64_vector(dir);
+            assert_eq!(dir64_from_vector(dx, dy), Some(dir));
+        }
+    }
+
+    #[test]
+    fn direction_to_sprite() {
+        assert_eq!(sprite_dir(0, 8), 0);
+        assert_eq!(sprite_dir(3, 8), 0);
+        assert_eq!(sprite_dir(4, 8), 1);
+        assert_eq!(sprite_dir(60, 8), 0);
+        assert_eq!(sprite_dir(59, 8), 7);
+        assert_eq!(sprite_dir(16, 8), 2);
+        assert_eq!(sprite_dir(16, 16), 4);
+        assert_eq!(sprite_dir(62, 16), 0);
+        for n in [8, 16] {
+            assert!((0..n).all(|d| sprite_dir(dir64_from_sprite(d, n), n) == d));
+        }
+    }
+}